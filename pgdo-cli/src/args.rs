@@ -1,8 +1,9 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::Args;
 
-use pgdo::cluster::DATABASE_POSTGRES;
+use pgdo::cluster::{ShutdownMode, StopOptions, DATABASE_POSTGRES};
 use pgdo::runtime::constraint::Constraint;
 
 #[derive(Args, Debug, Default)]
@@ -49,15 +50,35 @@ pub struct DatabaseArgs {
     pub name: String,
 }
 
-#[derive(Args, Debug, Default)]
+#[derive(Args, Clone, Debug, Default)]
 pub struct RuntimeArgs {
     /// Select the default runtime, used when creating new clusters.
+    ///
+    /// The constraint can be a version, e.g. `16`, a comparison, e.g. `>=14`,
+    /// a range, e.g. `14..16` or `14..=16`, or a glob pattern matching the
+    /// runtime's `bin` directory, e.g. `/usr/lib/**`. Combine these with
+    /// `and`, `or`, `not`, and parentheses, e.g. `>=15 and not /opt/**`. Use
+    /// `path:"…"` for patterns with no `/`, or to combine patterns containing
+    /// spaces or parentheses.
     #[clap(
         long = "runtime-default",
         value_name = "CONSTRAINT",
+        value_parser = parse_constraint,
         display_order = 80
     )]
     pub fallback: Option<Constraint>,
+
+    /// Fail if the runtime that created the cluster has disappeared or changed.
+    ///
+    /// By default pgdo warns, then selects another compatible runtime.
+    #[clap(long = "strict-runtime", display_order = 81)]
+    pub strict: bool,
+}
+
+/// Parse a runtime constraint, rendering any error with its location.
+fn parse_constraint(s: &str) -> Result<Constraint, String> {
+    s.parse()
+        .map_err(|err| format!("{:?}", miette::Report::new(err)))
 }
 
 #[derive(Args, Debug, Default)]
@@ -68,8 +89,50 @@ pub struct LifecycleArgs {
     pub destroy: bool,
 }
 
+#[derive(Args, Clone, Copy, Debug, Default)]
+pub struct StopArgs {
+    /// How to shut down the cluster, when there are no other users.
+    #[clap(
+        long = "stop-mode",
+        value_name = "MODE",
+        default_value = "fast",
+        display_order = 101
+    )]
+    pub stop_mode: StopMode,
+
+    /// How long to wait, in seconds, for the cluster to shut down before
+    /// trying a faster mode. Without this, `pg_ctl`'s default applies: 60
+    /// seconds, or `PGCTLTIMEOUT` seconds if that is set.
+    #[clap(long = "stop-timeout", value_name = "SECONDS", display_order = 102)]
+    pub stop_timeout: Option<u64>,
+}
+
+impl From<StopArgs> for StopOptions {
+    fn from(StopArgs { stop_mode, stop_timeout }: StopArgs) -> Self {
+        Self {
+            mode: match stop_mode {
+                StopMode::Smart => ShutdownMode::Smart,
+                StopMode::Fast => ShutdownMode::Fast,
+                StopMode::Immediate => ShutdownMode::Immediate,
+            },
+            timeout: stop_timeout.map(Duration::from_secs),
+        }
+    }
+}
+
 // ----------------------------------------------------------------------------
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum StopMode {
+    /// Wait for sessions to end.
+    Smart,
+    /// Disconnect sessions, rolling back their transactions.
+    #[default]
+    Fast,
+    /// Abort without a clean shutdown; crash recovery follows at next start.
+    Immediate,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 pub enum ClusterMode {
     /// Resets fsync, full_page_writes, and synchronous_commit to defaults.
@@ -1,9 +1,17 @@
 mod backup;
 mod clone;
 mod exec;
+mod ext;
+mod logical_sync;
+mod promote;
+mod reload;
+mod replica;
+mod restart;
 mod restore;
+mod role;
 mod runtimes;
 mod shell;
+mod status;
 
 use super::ExitResult;
 pub(crate) use shell::Shell as Default;
@@ -29,6 +37,30 @@ pub(crate) enum Command {
     Restore(restore::Restore),
 
     #[clap(display_order = 6)]
+    Replica(replica::Replica),
+
+    #[clap(display_order = 7)]
+    Promote(promote::Promote),
+
+    #[clap(display_order = 8)]
+    LogicalSync(logical_sync::LogicalSync),
+
+    #[clap(display_order = 9)]
+    Role(role::Role),
+
+    #[clap(display_order = 10)]
+    Ext(ext::Ext),
+
+    #[clap(display_order = 11)]
+    Status(status::Status),
+
+    #[clap(display_order = 12)]
+    Restart(restart::Restart),
+
+    #[clap(display_order = 13)]
+    Reload(reload::Reload),
+
+    #[clap(display_order = 14)]
     Runtimes(runtimes::Runtimes),
 }
 
@@ -41,6 +73,14 @@ impl Command {
             Self::Backup(backup) => backup.invoke(),
             Self::BackupTools(tools) => tools.invoke(),
             Self::Restore(restore) => restore.invoke(),
+            Self::Replica(replica) => replica.invoke(),
+            Self::Promote(promote) => promote.invoke(),
+            Self::LogicalSync(logical_sync) => logical_sync.invoke(),
+            Self::Role(role) => role.invoke(),
+            Self::Ext(ext) => ext.invoke(),
+            Self::Status(status) => status.invoke(),
+            Self::Restart(restart) => restart.invoke(),
+            Self::Reload(reload) => reload.invoke(),
             Self::Runtimes(runtimes) => runtimes.invoke(),
         }
     }
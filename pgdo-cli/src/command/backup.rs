@@ -38,17 +38,52 @@ pub struct Backup {
     /// The directory into which to write backups.
     #[clap(long = "into", value_name = "BACKUP_DIR", display_order = 100)]
     pub backup_dir: PathBuf,
+
+    /// The format of the base backup. A plain backup is a copy of the cluster's
+    /// data directory. A tar backup is a set of tar archives, one for the data
+    /// directory and one for each tablespace, and can be compressed.
+    #[clap(long = "format", value_name = "FORMAT", display_order = 200)]
+    pub format: Option<BackupFormat>,
+
+    /// Compress a tar-format base backup, e.g. `gzip`, `server-zstd:9`. The
+    /// method can be `gzip`, `lz4`, or `zstd`, optionally prefixed with where to
+    /// compress, `client-` (the default) or `server-`, and optionally suffixed
+    /// with a compression level. Implies `--format=tar`. Only client-side gzip
+    /// is available before PostgreSQL 15.
+    #[clap(long = "compress", value_name = "COMPRESSION", display_order = 300)]
+    pub compress: Option<backup::Compression>,
+
+    /// Take an incremental backup based on the latest base backup. This needs
+    /// PostgreSQL 17 or later, and a plain-format backup. If the latest base
+    /// backup cannot be used as a basis, a full backup is taken instead.
+    #[clap(long = "incremental", display_order = 400)]
+    pub incremental: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupFormat {
+    Plain,
+    Tar,
 }
 
 impl Backup {
     pub fn invoke(self) -> ExitResult {
-        let Self { cluster, backup_dir } = self;
+        let Self { cluster, backup_dir, format, compress, incremental } = self;
+
+        let format = match (format, compress) {
+            (None | Some(BackupFormat::Plain), None) => backup::Format::Plain,
+            (Some(BackupFormat::Plain), Some(_)) => {
+                Err(miette::miette!("Only tar-format backups can be compressed"))?
+            }
+            (None | Some(BackupFormat::Tar), compression) => backup::Format::Tar(compression),
+        };
+        let options = backup::BaseBackupOptions { format, incremental };
 
         let (datadir, lock) = runner::lock_for(cluster.dir)?;
-        let strategy = runner::determine_strategy(None)?;
+        let strategy = runner::determine_strategy(None, &["pg_basebackup"])?;
         let cluster = cluster::Cluster::new(datadir, strategy)?;
         let resource = resource::ResourceFree::new(lock, cluster);
-        backup(resource, backup_dir)?;
+        backup(resource, backup_dir, &options)?;
 
         Ok(ExitCode::SUCCESS)
     }
@@ -107,11 +142,16 @@ pub(crate) enum BackupTool {
 /// - Configure archiving into `backup_dir`.
 /// - Perform a base backup into `backup_dir`.
 ///
-/// TODO: Clean up old WAL files?
+/// Tablespaces other than the built-in ones are backed up alongside the base
+/// backup, and their original locations recorded.
 ///
-/// TODO: Handle table-spaces?
+/// TODO: Clean up old WAL files?
 ///
-fn backup<D: AsRef<Path>>(resource: resource::ResourceFree, backup_dir: D) -> miette::Result<()> {
+fn backup<D: AsRef<Path>>(
+    resource: resource::ResourceFree,
+    backup_dir: D,
+    options: &backup::BaseBackupOptions,
+) -> miette::Result<()> {
     // `Backup::prepare` creates `backup_dir` and the WAL archive directory if
     // these do not exist, and allocates a temporary location for the base
     // backup.
@@ -134,7 +174,7 @@ fn backup<D: AsRef<Path>>(resource: resource::ResourceFree, backup_dir: D) -> mi
                 // We started the cluster AND we have an exclusive resource, so
                 // we try to shut it down.
                 log::info!("Shutting down cluster…");
-                resource.facet().stop()
+                resource.facet().stop(cluster::StopOptions::default())
             }
             (State::Modified, Ok(Left(_)) | Err(_)) => {
                 // Somehow we started the cluster BUT we have only a shared
@@ -167,9 +207,8 @@ fn backup<D: AsRef<Path>>(resource: resource::ResourceFree, backup_dir: D) -> mi
         format!("{pgdo_exe_shell} backup:tools wal:archive %p {destination_wal_shell}/%f")
     };
 
-    // Configure the cluster to continuously archive WAL files. This may require
-    // a restart of the cluster on the first time through.
-    let needs_restart = with_cleanup(do_cleanup, || {
+    // Configure the cluster to continuously archive WAL files.
+    with_cleanup(do_cleanup, || {
         let rt = tokio::runtime::Runtime::new()?;
         rt.block_on(async {
             match resource.read().as_deref() {
@@ -183,39 +222,43 @@ fn backup<D: AsRef<Path>>(resource: resource::ResourceFree, backup_dir: D) -> mi
         })
     })?;
 
-    if needs_restart {
-        log::info!("The cluster must be restarted so that configuration changes come into effect.");
-        match resource.read().as_deref() {
-            Ok(Left(_)) => {
-                // Need to restart the cluster BUT we do NOT have an exclusive lock.
-                Err(backup::BackupError::GeneralError(
-                    concat!(
-                        "The cluster is in use, and so cannot be restarted automatically. ",
-                        "Please restart the cluster manually then try this backup again.",
-                    )
-                    .into(),
-                ))?;
-            }
-            Ok(Right(resource)) => {
-                // Need to restart the cluster AND we have an exclusive lock.
-                let facet = resource.facet();
-                with_cleanup(do_cleanup, || {
-                    log::info!("Restarting cluster; stopping…");
-                    facet.stop().and_then(|_| {
-                        log::info!("Restarting cluster; starting up again…");
-                        facet.start(&[])
-                    })
-                })?;
-            }
-            Err(err) => panic!("Could not acquire resource: {err}"),
-        };
+    // Bring that configuration into effect. This may require a restart of the
+    // cluster on the first time through.
+    let applied = match resource.read().as_deref() {
+        Ok(resource) => with_cleanup(do_cleanup, || {
+            resource::ensure_applied(resource, cluster::StopOptions::default())
+        })?,
+        Err(err) => panic!("Could not acquire resource: {err}"),
+    };
+    match applied {
+        resource::Applied::Reloaded => {}
+        resource::Applied::Restarted(settings) => {
+            log::info!(
+                "Restarted cluster so that changes to {} came into effect.",
+                settings.join(", ")
+            );
+        }
+        resource::Applied::RestartRequired(settings) => {
+            // Need to restart the cluster BUT we do NOT have an exclusive lock.
+            log::info!(
+                "The cluster must be restarted so that changes to {} come into effect.",
+                settings.join(", ")
+            );
+            Err(backup::BackupError::GeneralError(
+                concat!(
+                    "The cluster is in use, and so cannot be restarted automatically. ",
+                    "Please restart the cluster manually then try this backup again.",
+                )
+                .into(),
+            ))?;
+        }
     }
 
     log::info!("Performing base backup…");
     let destination_data = match resource.read().as_deref() {
         Ok(resource) => with_finally(do_cleanup, || {
             let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(async { backup.do_base_backup(resource).await })
+            rt.block_on(async { backup.do_base_backup(resource, options).await })
         }),
         Err(err) => panic!("Could not acquire resource: {err}"),
     }?;
@@ -1,11 +1,25 @@
-use std::{ffi::OsStr, path::PathBuf};
+use std::{
+    ffi::OsStr,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use miette::WrapErr;
+use either::{Left, Right};
+use miette::{bail, IntoDiagnostic, WrapErr};
 
 use super::ExitResult;
 use crate::{args, runner};
 
+use pgdo::{cluster, coordinate};
+
 /// Perform a one-off clone/backup of an existing cluster.
+///
+/// By default the cluster is started, if necessary, and cloned with
+/// `pg_basebackup`. With `--offline` the cluster must be stopped, and its data
+/// directory is copied directly, using reflinks where the filesystem supports
+/// them. Either way the clone can be used straight away with `pgdo -D
+/// DESTINATION`.
 #[derive(clap::Args)]
 #[clap(next_help_heading = Some("Options for clone"))]
 pub struct Clone {
@@ -15,31 +29,31 @@ pub struct Clone {
     /// The directory into which to clone the cluster.
     #[clap(long = "destination", display_order = 100)]
     pub destination: PathBuf,
+
+    /// Copy the cluster's data directory while it is stopped, rather than
+    /// starting it and using `pg_basebackup`. The cluster must not be in use.
+    /// Tablespaces are not supported.
+    #[clap(long = "offline", display_order = 200)]
+    pub offline: bool,
+
+    /// Reset archiving settings in the clone so that it does not archive WAL
+    /// into the original cluster's backups.
+    #[clap(long = "reset-archiving", display_order = 300)]
+    pub reset_archiving: bool,
 }
 
 impl Clone {
     pub fn invoke(self) -> ExitResult {
-        let Self { cluster, destination } = self;
-        let args: &[&OsStr] = &[
-            "--pgdata".as_ref(),
-            destination.as_ref(),
-            "--format".as_ref(),
-            "plain".as_ref(),
-            "--progress".as_ref(),
-        ];
-        runner::run(
-            runner::Runner::RunAndStopIfExists,
-            cluster,
-            args::ClusterModeArgs::default(),
-            args::RuntimeArgs::default(),
-            |cluster| {
-                runner::check_exit(
-                    cluster
-                        .exec(None, "pg_basebackup".as_ref(), args)
-                        .wrap_err("Executing command in cluster failed")?,
-                )
-            },
-        )
+        let Self { cluster, destination, offline, reset_archiving } = self;
+        let exit = if offline {
+            clone_offline(&cluster.dir, &destination)?
+        } else {
+            clone_online(cluster, &destination)?
+        };
+        if exit == ExitCode::SUCCESS && reset_archiving {
+            reset_archiving_in_clone(&destination)?;
+        }
+        Ok(exit)
     }
 }
 
@@ -48,3 +62,123 @@ impl From<Clone> for super::Command {
         Self::Clone(clone)
     }
 }
+
+/// Clone a cluster with `pg_basebackup`, starting it if necessary.
+fn clone_online(cluster: args::ClusterArgs, destination: &Path) -> ExitResult {
+    let args: &[&OsStr] = &[
+        "--pgdata".as_ref(),
+        destination.as_ref(),
+        "--format".as_ref(),
+        "plain".as_ref(),
+        "--progress".as_ref(),
+    ];
+    runner::run(
+        runner::Runner::RunAndStopIfExists,
+        cluster,
+        args::ClusterModeArgs::default(),
+        args::RuntimeArgs::default(),
+        &["pg_basebackup"],
+        args::StopArgs::default(),
+        |cluster| {
+            let status = cluster
+                .exec(None, "pg_basebackup".as_ref(), args)
+                .wrap_err("Executing command in cluster failed")?;
+            if status.success() {
+                runner::remove_socket_lock_files(destination)
+                    .into_diagnostic()
+                    .wrap_err("Could not remove socket lock files from clone")?;
+            }
+            runner::check_exit(status)
+        },
+    )
+}
+
+/// Clone a stopped cluster by copying its data directory, holding the
+/// cluster's lock exclusively throughout.
+fn clone_offline(cluster_dir: &Path, destination: &Path) -> ExitResult {
+    let term = console::Term::stdout();
+    let (datadir, lock) = runner::lock_for(cluster_dir)?;
+    let strategy = runner::determine_strategy(None, &[])?;
+    let cluster = cluster::Cluster::new(&datadir, strategy)?;
+    let resource = match cluster::resource::ResourceFree::new(lock, cluster).try_exclusive()? {
+        Left(_) => bail!("Cluster is in use; cannot clone it offline"),
+        Right(resource) => resource,
+    };
+
+    let facet = resource.facet();
+    if !facet.exists()? {
+        bail!("Cluster does not exist in {datadir:?}");
+    }
+    if facet.running()? {
+        bail!("Cluster is running; stop it before cloning it offline");
+    }
+    if datadir
+        .join("pg_tblspc")
+        .read_dir()
+        .into_diagnostic()?
+        .next()
+        .is_some()
+    {
+        bail!("Cluster has tablespaces, which cannot be cloned offline; clone it online instead");
+    }
+
+    std::fs::create_dir_all(destination).into_diagnostic()?;
+    if destination.read_dir().into_diagnostic()?.next().is_some() {
+        bail!("Destination directory {destination:?} is not empty");
+    }
+    write!(&term, "Copying cluster…").into_diagnostic()?;
+    copy_dir(&datadir, destination, true)
+        .into_diagnostic()
+        .wrap_err("Could not copy cluster")?;
+    writeln!(&term, " done.").into_diagnostic()?;
+
+    resource.release()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Copy the contents of directory `source` into directory `target`,
+/// recursively, using reflinks where possible, and preserving permissions.
+///
+/// At the `top` level of a data directory this skips `postmaster.pid` and any
+/// Unix sockets and their lock files; a clone should not appear to be running.
+fn copy_dir(source: &Path, target: &Path, top: bool) -> std::io::Result<()> {
+    std::fs::set_permissions(target, source.metadata()?.permissions())?;
+    for entry in source.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if top && (name == "postmaster.pid" || name.as_encoded_bytes().starts_with(b".s.PGSQL.")) {
+            continue;
+        }
+        let (from, to) = (entry.path(), target.join(&name));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            std::fs::create_dir(&to)?;
+            copy_dir(&from, &to, false)?;
+        } else if file_type.is_file() {
+            reflink_copy::reflink_or_copy(&from, &to)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(&from)?, &to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Start the clone with archiving disabled and reset its archiving settings.
+fn reset_archiving_in_clone(destination: &Path) -> miette::Result<()> {
+    let term = console::Term::stdout();
+    let (datadir, lock) = runner::lock_for(destination)?;
+    let strategy = runner::determine_strategy(None, &[])?;
+    let clone = cluster::Cluster::new(datadir, strategy)?;
+    coordinate::run_and_stop(
+        &clone,
+        &[(runner::ARCHIVE_MODE, "off".into())],
+        cluster::StopOptions::default(),
+        lock,
+        || {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(async { runner::reset_archiving(&term, &clone.pool(None)?).await })
+        },
+    )??;
+    writeln!(&term, "Archiving disabled in clone.").into_diagnostic()?;
+    Ok(())
+}
@@ -28,6 +28,9 @@ pub struct Exec {
     #[clap(flatten)]
     pub runtime: args::RuntimeArgs,
 
+    #[clap(flatten)]
+    pub stop: args::StopArgs,
+
     /// The executable to invoke. By default it will start a shell.
     #[clap(env = "SHELL", value_name = "COMMAND", display_order = 999)]
     pub command: OsString,
@@ -47,6 +50,7 @@ impl Exec {
             args,
             lifecycle,
             runtime,
+            stop,
         } = self;
         runner::run(
             if lifecycle.destroy {
@@ -57,6 +61,8 @@ impl Exec {
             cluster,
             cluster_mode,
             runtime,
+            &[],
+            stop,
             |cluster| {
                 runner::ensure_database(cluster, &database.name)?;
                 runner::check_exit(
@@ -0,0 +1,269 @@
+use std::{
+    io::Write,
+    process::ExitCode,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use miette::{bail, IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{
+    cluster::extension::{ExtensionState, Preload},
+    coordinate::State,
+};
+
+/// Manage extensions in a database.
+///
+/// The cluster is created and started as necessary. Some extensions must be
+/// preloaded; adding one of these updates `shared_preload_libraries` and
+/// restarts the cluster, if it's not in use. Only well-known extensions are
+/// recognised as needing this; use `ext add --preload` for others.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for ext"))]
+pub struct Ext {
+    #[clap(subcommand)]
+    command: ExtCommand,
+}
+
+impl Ext {
+    pub fn invoke(self) -> ExitResult {
+        match self.command {
+            ExtCommand::List { cluster, database, runtime, stop, installed } => {
+                run(cluster, runtime, stop, |cluster| {
+                    list(cluster, &database.name, installed)
+                })
+            }
+            ExtCommand::Add {
+                cluster,
+                database,
+                runtime,
+                stop,
+                extension,
+                version,
+                preload,
+            } => {
+                let preload = match preload {
+                    None => Preload::WellKnown,
+                    Some(None) => Preload::Library(&extension),
+                    Some(Some(ref library)) => Preload::Library(library),
+                };
+                add(
+                    cluster,
+                    runtime,
+                    stop,
+                    &database.name,
+                    &extension,
+                    version.as_deref(),
+                    preload,
+                )
+            }
+            ExtCommand::Remove { cluster, database, runtime, stop, extension: name } => {
+                run(cluster, runtime, stop, |cluster| {
+                    let term = console::Term::stdout();
+                    match cluster.remove_extension(Some(&database.name), &name)? {
+                        State::Modified => writeln!(&term, "Extension {name:?} removed."),
+                        State::Unmodified => {
+                            writeln!(&term, "Extension {name:?} is not installed.")
+                        }
+                    }
+                    .into_diagnostic()
+                })
+            }
+        }
+    }
+}
+
+impl From<Ext> for super::Command {
+    fn from(ext: Ext) -> Self {
+        Self::Ext(ext)
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum ExtCommand {
+    /// List available extensions. Those marked with `*` are installed.
+    #[clap(display_order = 1)]
+    List {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        database: args::DatabaseArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// Show only installed extensions.
+        #[clap(long = "installed", display_order = 100)]
+        installed: bool,
+    },
+
+    /// Install an extension, or update it to the given version.
+    #[clap(display_order = 2)]
+    Add {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        database: args::DatabaseArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The name of the extension.
+        #[clap(value_name = "EXTENSION")]
+        extension: String,
+
+        /// The version to install; the extension's default if not specified.
+        #[clap(long = "version", value_name = "VERSION", display_order = 100)]
+        version: Option<String>,
+
+        /// Add the extension's library – or the given library – to
+        /// `shared_preload_libraries` before installing. Well-known extensions
+        /// that need this are preloaded without asking.
+        #[clap(
+            long = "preload",
+            value_name = "LIBRARY",
+            num_args = 0..=1,
+            require_equals = true,
+            display_order = 110
+        )]
+        preload: Option<Option<String>>,
+    },
+
+    /// Remove an extension.
+    #[clap(display_order = 3)]
+    Remove {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        database: args::DatabaseArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The name of the extension.
+        #[clap(value_name = "EXTENSION")]
+        extension: String,
+    },
+}
+
+/// Run `action` against the cluster, creating and starting it as necessary.
+fn run<ACTION>(
+    cluster: args::ClusterArgs,
+    runtime: args::RuntimeArgs,
+    stop: args::StopArgs,
+    action: ACTION,
+) -> ExitResult
+where
+    ACTION: FnOnce(&pgdo::cluster::Cluster) -> miette::Result<()> + std::panic::UnwindSafe,
+{
+    runner::run(
+        runner::Runner::RunAndStop,
+        cluster,
+        args::ClusterModeArgs::default(),
+        runtime,
+        &[],
+        stop,
+        |cluster| {
+            action(cluster).wrap_err("Managing extensions failed")?;
+            Ok(ExitCode::SUCCESS)
+        },
+    )
+}
+
+/// Install the named extension.
+///
+/// If the extension must be preloaded, `shared_preload_libraries` is updated
+/// and the cluster stopped – if no one else is using it – so that the second
+/// attempt starts the cluster afresh.
+fn add(
+    cluster: args::ClusterArgs,
+    runtime: args::RuntimeArgs,
+    stop: args::StopArgs,
+    database: &str,
+    name: &str,
+    version: Option<&str>,
+    preload: Preload,
+) -> ExitResult {
+    let term = console::Term::stdout();
+    let restart_required = AtomicBool::new(false);
+    let attempt = |cluster: args::ClusterArgs, runtime: args::RuntimeArgs| {
+        run(cluster, runtime, stop, |cluster| {
+            runner::ensure_database(cluster, database)?;
+            match cluster.ensure_extension(Some(database), name, version, preload)? {
+                ExtensionState::Modified => {
+                    writeln!(&term, "Extension {name:?} installed.").into_diagnostic()?;
+                }
+                ExtensionState::Unmodified => {
+                    writeln!(&term, "Extension {name:?} is already installed.")
+                        .into_diagnostic()?;
+                }
+                ExtensionState::RestartRequired => {
+                    restart_required.store(true, Ordering::SeqCst);
+                }
+            }
+            Ok(())
+        })
+    };
+
+    let retry = (
+        args::ClusterArgs { dir: cluster.dir.clone() },
+        runtime.clone(),
+    );
+    let exit = attempt(cluster, runtime)?;
+    if !restart_required.swap(false, Ordering::SeqCst) {
+        return Ok(exit);
+    }
+    writeln!(&term, "Restarting cluster to preload {name:?}…").into_diagnostic()?;
+    let exit = attempt(retry.0, retry.1)?;
+    if restart_required.load(Ordering::SeqCst) {
+        bail!(concat!(
+            "The cluster has been configured to preload the extension but it is in use, ",
+            "and so cannot be restarted automatically. ",
+            "Please restart it manually then try again.",
+        ));
+    }
+    Ok(exit)
+}
+
+/// Print extensions in a table, one per line.
+fn list(cluster: &pgdo::cluster::Cluster, database: &str, installed: bool) -> miette::Result<()> {
+    let term = console::Term::stdout();
+    let extensions: Vec<_> = cluster
+        .extensions(Some(database))?
+        .into_iter()
+        .filter(|extension| !installed || extension.is_installed())
+        .collect();
+    let width = extensions
+        .iter()
+        .map(|extension| extension.name.len())
+        .max()
+        .unwrap_or(0);
+    for extension in extensions {
+        let marker = if extension.is_installed() { "*" } else { "" };
+        let version = extension
+            .installed_version
+            .or(extension.default_version)
+            .unwrap_or_default();
+        let comment = extension.comment.unwrap_or_default();
+        let line = format!(
+            "{marker:1} {name:width$}  {version:8}  {comment}",
+            name = extension.name
+        );
+        writeln!(&term, "{}", line.trim_end()).into_diagnostic()?;
+    }
+    Ok(())
+}
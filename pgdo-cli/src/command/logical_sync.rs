@@ -0,0 +1,494 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{ExitCode, Stdio},
+};
+
+use either::{Left, Right};
+use miette::{bail, IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{
+    cluster::{
+        self,
+        config::Parameter,
+        resource::{self, HeldResource},
+        sqlx,
+    },
+    coordinate::{finally::with_finally, State},
+    version::Version,
+};
+
+static WAL_LEVEL: Parameter = Parameter("wal_level");
+
+/// Replicate tables from one cluster into another with logical replication.
+///
+/// This copies the schema from the source cluster into the target cluster with
+/// `pg_dump --schema-only`, creates a publication in the source and a
+/// subscription to it in the target, then waits for the initial copy of data.
+/// After that, changes in the source are replicated into the target whenever
+/// both clusters are running.
+///
+/// The source must be configured with `wal_level = logical`. If it is not, this
+/// is set, and the source is restarted if it is not otherwise in use.
+///
+/// Use this to rehearse a major-version upgrade: create the target with a newer
+/// runtime (see `--runtime-default`), sync, then switch over to the target.
+///
+/// Requires PostgreSQL 10 or later.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for logical-sync"))]
+pub struct LogicalSync {
+    /// The directory in which the source cluster lives.
+    #[clap(long = "from", value_name = "SOURCE_DIR", display_order = 100)]
+    pub source_dir: PathBuf,
+
+    /// The directory in which the target cluster lives. It is created if it
+    /// does not exist.
+    #[clap(long = "to", value_name = "TARGET_DIR", display_order = 200)]
+    pub target_dir: PathBuf,
+
+    #[clap(flatten)]
+    pub database: args::DatabaseArgs,
+
+    /// The tables to replicate, e.g. `--tables foo,bar.baz`. By default all
+    /// tables in the database are replicated.
+    #[clap(
+        long = "tables",
+        value_name = "TABLE",
+        value_delimiter = ',',
+        display_order = 300
+    )]
+    pub tables: Vec<String>,
+
+    /// The name of the publication in the source and of the subscription in the
+    /// target.
+    #[clap(
+        long = "name",
+        value_name = "NAME",
+        default_value = "pgdo_sync",
+        display_order = 400
+    )]
+    pub sync_name: String,
+
+    #[clap(flatten)]
+    pub runtime: args::RuntimeArgs,
+}
+
+impl LogicalSync {
+    pub fn invoke(self) -> ExitResult {
+        let term = console::Term::stdout();
+        let Self { source_dir, target_dir, database, tables, sync_name, runtime } = self;
+
+        let (source_datadir, lock) = runner::lock_for(&source_dir)?;
+        // Check this before locking either cluster. The target need not exist
+        // yet, in which case it cannot be the source.
+        if target_dir
+            .canonicalize()
+            .is_ok_and(|target_datadir| target_datadir == source_datadir)
+        {
+            bail!("The source and target must be different clusters");
+        }
+        let strategy = runner::determine_strategy(None, &[])?;
+        let mut source = cluster::Cluster::new(&source_datadir, strategy)?;
+        source.strict_runtime = runtime.strict;
+        let (started, held) =
+            resource::startup_if_exists(resource::ResourceFree::new(lock, source), &[])?;
+
+        // Shuts down the source cluster if we started it.
+        let stop = || match (started, &held) {
+            (State::Modified, Right(resource)) => {
+                resource.facet().stop(cluster::StopOptions::default())
+            }
+            _ => Ok(State::Unmodified),
+        };
+
+        let exit = with_finally(stop, || -> ExitResult {
+            let source = held.as_ref().either(
+                |resource| resource.facet().runtime(),
+                |resource| resource.facet().runtime(),
+            )?;
+            if source.version < Version::Post10(10, 0) {
+                bail!(
+                    "Logical replication requires PostgreSQL 10 or later; the source is {}",
+                    source.version
+                );
+            }
+            ensure_wal_level_logical(&term, &held, &database.name)?;
+            runner::run(
+                runner::Runner::RunAndStop,
+                args::ClusterArgs { dir: target_dir.clone() },
+                args::ClusterModeArgs::default(),
+                runtime,
+                &["pg_dump", "psql"],
+                args::StopArgs::default(),
+                |target| {
+                    let version = target.runtime().into_diagnostic()?.version;
+                    if version < Version::Post10(10, 0) {
+                        bail!("Logical replication requires PostgreSQL 10 or later; the target is {version}");
+                    }
+                    runner::ensure_database(target, &database.name)?;
+                    let rt = tokio::runtime::Runtime::new().into_diagnostic()?;
+                    let source_pool = rt.block_on(async { source_pool(&held, &database.name) })?;
+                    let source = (source_datadir.as_path(), &source_pool);
+                    sync(
+                        &term,
+                        &rt,
+                        source,
+                        target,
+                        &database.name,
+                        &tables,
+                        &sync_name,
+                    )
+                },
+            )
+        })?;
+        held.either(
+            resource::ResourceShared::release,
+            resource::ResourceExclusive::release,
+        )?;
+
+        if exit == ExitCode::SUCCESS {
+            let code = console::Style::new().bold().cyan();
+            writeln!(
+                &term,
+                "Changes are replicated while both clusters are running, e.g. with {} and {}.",
+                code.apply_to(format!("pgdo -D {}", quote(&source_dir)?)),
+                code.apply_to(format!("pgdo -D {}", quote(&target_dir)?)),
+            )
+            .into_diagnostic()?;
+        }
+        Ok(exit)
+    }
+}
+
+impl From<LogicalSync> for super::Command {
+    fn from(logical_sync: LogicalSync) -> Self {
+        Self::LogicalSync(logical_sync)
+    }
+}
+
+/// A connection pool for the cluster held by the given resource.
+fn source_pool(held: &HeldResource, database: &str) -> Result<sqlx::PgPool, cluster::ClusterError> {
+    held.as_ref().either(
+        |resource| resource.facet().pool(Some(database)),
+        |resource| resource.facet().pool(Some(database)),
+    )
+}
+
+/// Set `wal_level = logical` in the source cluster. If this was not already in
+/// effect the source must be restarted, which is only possible when we hold it
+/// exclusively.
+fn ensure_wal_level_logical(
+    mut term: &console::Term,
+    held: &HeldResource,
+    database: &str,
+) -> miette::Result<()> {
+    let rt = tokio::runtime::Runtime::new().into_diagnostic()?;
+    let changed = rt.block_on(async {
+        let pool = source_pool(held, database)?;
+        let wal_level = WAL_LEVEL.get(&pool).await?;
+        if wal_level.is_some_and(|value| value.to_string() == "logical") {
+            Ok(false)
+        } else {
+            WAL_LEVEL.set(&pool, "logical").await?;
+            Ok::<_, cluster::ClusterError>(true)
+        }
+    })?;
+    if changed {
+        match held {
+            Left(_) => bail!(concat!(
+                "The source cluster has been configured with wal_level = logical but it is in use, ",
+                "and so cannot be restarted automatically. ",
+                "Please restart it manually then try again.",
+            )),
+            Right(resource) => {
+                write!(term, "Restarting source cluster so that wal_level = logical…")
+                    .into_diagnostic()?;
+                resource.facet().restart(cluster::StopOptions::default())?;
+                writeln!(term, " done.").into_diagnostic()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copy the schema from `source` into `target`, publish the given `tables` – or
+/// all tables – from `source`, and subscribe to them in `target`. The source is
+/// given as its data directory and a connection pool.
+fn sync(
+    mut term: &console::Term,
+    rt: &tokio::runtime::Runtime,
+    (source_datadir, source_pool): (&Path, &sqlx::PgPool),
+    target: &cluster::Cluster,
+    database: &str,
+    tables: &[String],
+    name: &str,
+) -> ExitResult {
+    let Some(source_host) = source_datadir.to_str() else {
+        bail!("The source cluster's directory must be valid UTF-8");
+    };
+    let conninfo = format!(
+        "host={} dbname={}",
+        conninfo_value(source_host),
+        conninfo_value(database)
+    );
+
+    let target_pool = rt.block_on(async { target.pool(Some(database)) })?;
+
+    let exists: bool = rt
+        .block_on(
+            sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM pg_catalog.pg_subscription WHERE subname = $1)",
+            )
+            .bind(name)
+            .fetch_one(&target_pool),
+        )
+        .into_diagnostic()?;
+    if exists {
+        bail!("Subscription {name:?} already exists in the target cluster");
+    }
+
+    writeln!(term, "Copying schema…").into_diagnostic()?;
+    let status = copy_schema(source_datadir, target, database, tables)?;
+    if !status.success() {
+        return runner::check_exit(status);
+    }
+
+    rt.block_on(async {
+        writeln!(term, "Creating publication {name:?} in source…")?;
+        let sql: String = if tables.is_empty() {
+            sqlx::query_scalar("SELECT format('CREATE PUBLICATION %I FOR ALL TABLES', $1)")
+                .bind(name)
+                .fetch_one(source_pool)
+                .await?
+        } else {
+            // Casting to `regclass` checks that each table exists, and
+            // produces a correctly quoted and qualified name.
+            sqlx::query_scalar(concat!(
+                "SELECT format('CREATE PUBLICATION %I FOR TABLE %s', $1, ",
+                "string_agg(t::regclass::text, ', ')) FROM unnest($2::text[]) AS t"
+            ))
+            .bind(name)
+            .bind(tables)
+            .fetch_one(source_pool)
+            .await?
+        };
+        sqlx::query(&sql).execute(source_pool).await?;
+
+        writeln!(term, "Creating subscription {name:?} in target…")?;
+        let sql: String = sqlx::query_scalar(
+            "SELECT format('CREATE SUBSCRIPTION %I CONNECTION %L PUBLICATION %I', $1, $2, $1)",
+        )
+        .bind(name)
+        .bind(&conninfo)
+        .fetch_one(&target_pool)
+        .await?;
+        sqlx::query(&sql).execute(&target_pool).await?;
+
+        Ok::<_, cluster::ClusterError>(())
+    })?;
+
+    write!(term, "Copying data…").into_diagnostic()?;
+    let synced = runner::heeding_interrupts(|| rt.block_on(wait_for_sync(&target_pool, name)))?;
+    if synced {
+        writeln!(term, " done.").into_diagnostic()?;
+    } else if runner::interrupted() {
+        writeln!(term).into_diagnostic()?;
+        bail!(concat!(
+            "Interrupted while waiting for the initial copy of data; ",
+            "it will continue when both clusters are next running",
+        ));
+    } else {
+        writeln!(term).into_diagnostic()?;
+        let warning = console::style("WARNING").bold().yellow();
+        writeln!(
+            term,
+            "{warning}: The initial copy of data has not yet finished; it will continue when both clusters are next running."
+        )
+        .into_diagnostic()?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Dump the schema from `source` with `pg_dump --schema-only` and restore it
+/// into `target` with `psql`. The target's runtime is used for both, since
+/// `pg_dump` can dump from older servers but not from newer ones.
+fn copy_schema(
+    source_datadir: &Path,
+    target: &cluster::Cluster,
+    database: &str,
+    tables: &[String],
+) -> miette::Result<std::process::ExitStatus> {
+    let runtime = target.runtime()?;
+    let mut dump = runtime
+        .execute("pg_dump")
+        .arg("--schema-only")
+        .arg("--no-publications")
+        .arg("--no-subscriptions")
+        .arg("--host")
+        .arg(source_datadir)
+        .arg("--dbname")
+        .arg(database)
+        .args(tables.iter().map(|table| format!("--table={table}")))
+        .stdout(Stdio::piped())
+        .spawn()
+        .into_diagnostic()
+        .wrap_err("Could not run pg_dump")?;
+    let Some(dump_out) = dump.stdout.take() else {
+        bail!("Could not capture output from pg_dump");
+    };
+    let restore = runtime
+        .execute("psql")
+        .arg("--quiet")
+        .arg("--no-psqlrc")
+        .arg("--set=ON_ERROR_STOP=1")
+        .arg("--host")
+        .arg(&target.datadir)
+        .arg("--dbname")
+        .arg(database)
+        .stdin(dump_out)
+        .stdout(Stdio::null())
+        .status()
+        .into_diagnostic()
+        .wrap_err("Could not run psql")?;
+    let dump = dump.wait().into_diagnostic()?;
+    Ok(if dump.success() { restore } else { dump })
+}
+
+/// Wait for the subscription's tables to be synchronised. Returns `false` if
+/// this takes too long, or if interrupted; see [`runner::interrupted`].
+async fn wait_for_sync(pool: &sqlx::PgPool, name: &str) -> Result<bool, cluster::ClusterError> {
+    for _ in 0..60 {
+        if runner::interrupted() {
+            return Ok(false);
+        }
+        let pending: i64 = sqlx::query_scalar(concat!(
+            "SELECT count(*) FROM pg_catalog.pg_subscription_rel r ",
+            "JOIN pg_catalog.pg_subscription s ON s.oid = r.srsubid ",
+            "WHERE s.subname = $1 AND r.srsubstate NOT IN ('r', 's')"
+        ))
+        .bind(name)
+        .fetch_one(pool)
+        .await?;
+        if pending == 0 {
+            return Ok(true);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    Ok(false)
+}
+
+/// Quote a value for use in a libpq connection string.
+fn conninfo_value(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Quote a path for use in a shell.
+fn quote(path: &Path) -> miette::Result<String> {
+    use shell_quote::{QuoteRefExt, Sh};
+    String::from_utf8(path.quoted(Sh)).into_diagnostic()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pgdo::cluster::{Cluster, StopOptions};
+
+    use super::{conninfo_value, LogicalSync};
+    use crate::{args, runner};
+
+    #[test]
+    fn conninfo_value_is_quoted() {
+        assert_eq!(conninfo_value("/some/where"), "'/some/where'");
+        assert_eq!(conninfo_value(r"it's\here"), r"'it\'s\\here'");
+    }
+
+    #[test]
+    fn logical_sync_replicates_into_new_cluster() -> miette::Result<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source_dir = temp_dir.path().join("source");
+        let target_dir = temp_dir.path().join("target");
+        let database = "sync";
+
+        // Create a source cluster with some data. It's left stopped, and with
+        // the default `wal_level`, so the sync must start and restart it.
+        let source = Cluster::new(&source_dir, runner::determine_strategy(None, &[])?)?;
+        source.start(&[])?;
+        source.createdb(database)?;
+        let mut client = source.connection().database(database).client()?;
+        client
+            .batch_execute(concat!(
+                "CREATE TABLE fruit (id int PRIMARY KEY, name text);",
+                "INSERT INTO fruit VALUES (1, 'apple'), (2, 'banana');",
+            ))
+            .unwrap();
+        drop(client);
+        source.stop(StopOptions::default())?;
+
+        let sync = |target_dir| LogicalSync {
+            source_dir: source_dir.clone(),
+            target_dir,
+            database: args::DatabaseArgs { name: database.into() },
+            tables: vec![],
+            sync_name: "pgdo_sync".into(),
+            runtime: args::RuntimeArgs::default(),
+        };
+
+        // The source and target must differ.
+        let err = sync(source_dir.clone()).invoke().unwrap_err();
+        assert!(err.to_string().contains("must be different clusters"));
+
+        sync(target_dir.clone()).invoke()?;
+
+        // Both clusters are left stopped.
+        let target = Cluster::new(&target_dir, runner::determine_strategy(None, &[])?)?;
+        assert!(!source.running()?);
+        assert!(!target.running()?);
+
+        source.start(&[])?;
+        target.start(&[])?;
+        let mut source_client = source.connection().database(database).client()?;
+        let mut target_client = target.connection().database(database).client()?;
+
+        let row = source_client.query_one("SHOW wal_level", &[]).unwrap();
+        assert_eq!(row.get::<_, String>(0), "logical");
+        let row = source_client
+            .query_one("SELECT count(*) FROM pg_publication_tables", &[])
+            .unwrap();
+        assert_eq!(row.get::<_, i64>(0), 1);
+
+        // The initial copy of data was waited for.
+        let row = target_client
+            .query_one("SELECT string_agg(name, ',' ORDER BY id) FROM fruit", &[])
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "apple,banana");
+
+        // Changes in the source are replicated into the target.
+        source_client
+            .execute("INSERT INTO fruit VALUES (3, 'cherry')", &[])
+            .unwrap();
+        let mut count = 0;
+        for _ in 0..60 {
+            let row = target_client
+                .query_one("SELECT count(*) FROM fruit", &[])
+                .unwrap();
+            count = row.get::<_, i64>(0);
+            if count == 3 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        assert_eq!(count, 3);
+
+        drop((source_client, target_client));
+        target.stop(StopOptions::default())?;
+        source.stop(StopOptions::default())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use std::{io::Write, process::ExitCode};
+
+use miette::{IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::coordinate::State;
+
+/// Promote a standby cluster so that it begins accepting writes.
+///
+/// Use this to fail over to a replica created with the `replica` command. The
+/// promoted cluster stops following its primary.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for promote"))]
+pub struct Promote {
+    #[clap(flatten)]
+    pub cluster: args::ClusterArgs,
+}
+
+impl Promote {
+    pub fn invoke(self) -> ExitResult {
+        let Self { cluster } = self;
+        runner::run(
+            runner::Runner::RunAndStopIfExists,
+            cluster,
+            args::ClusterModeArgs::default(),
+            args::RuntimeArgs::default(),
+            &[],
+            args::StopArgs::default(),
+            |cluster| {
+                let term = console::Term::stdout();
+                match cluster.promote().wrap_err("Promoting cluster failed")? {
+                    State::Modified => writeln!(&term, "Cluster promoted."),
+                    State::Unmodified => writeln!(&term, "Cluster is not a standby."),
+                }
+                .into_diagnostic()?;
+                Ok(ExitCode::SUCCESS)
+            },
+        )
+    }
+}
+
+impl From<Promote> for super::Command {
+    fn from(promote: Promote) -> Self {
+        Self::Promote(promote)
+    }
+}
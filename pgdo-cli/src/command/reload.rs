@@ -0,0 +1,57 @@
+use std::{io::Write, process::ExitCode};
+
+use miette::{IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{cluster::Cluster, coordinate::State};
+
+/// Reload a running cluster's configuration files.
+///
+/// This does not lock the cluster. Settings that only take effect when the
+/// server starts are listed afterwards; use the `restart` command to apply
+/// them.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for reload"))]
+pub struct Reload {
+    #[clap(flatten)]
+    pub cluster: args::ClusterArgs,
+}
+
+impl Reload {
+    pub fn invoke(self) -> ExitResult {
+        let Self { cluster: args::ClusterArgs { dir } } = self;
+        let strategy = runner::determine_strategy(None, &[])?;
+        let cluster = Cluster::new(dir, strategy)?;
+
+        let term = console::Term::stdout();
+        match cluster
+            .reload()
+            .wrap_err("Reloading configuration failed")?
+        {
+            State::Modified => {
+                writeln!(&term, "Configuration reloaded.").into_diagnostic()?;
+                let pending = cluster
+                    .pending_restart()
+                    .wrap_err("Could not check for settings pending a restart")?;
+                if !pending.is_empty() {
+                    writeln!(
+                        &term,
+                        "Changes to {} will take effect when the cluster is restarted.",
+                        pending.join(", "),
+                    )
+                    .into_diagnostic()?;
+                }
+            }
+            State::Unmodified => writeln!(&term, "Cluster is not running.").into_diagnostic()?,
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+impl From<Reload> for super::Command {
+    fn from(reload: Reload) -> Self {
+        Self::Reload(reload)
+    }
+}
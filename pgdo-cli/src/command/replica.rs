@@ -0,0 +1,193 @@
+use std::{
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use miette::{bail, IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{cluster, coordinate, version::Version};
+
+/// Create a streaming replica of an existing cluster.
+///
+/// The replica is copied from the primary with `pg_basebackup`. It streams WAL
+/// from the primary over the primary's Unix socket using a replication slot.
+/// Once created it is started to check that it comes up as a standby. After
+/// that it's a cluster like any other, e.g. use `pgdo -D REPLICA_DIR` to open a
+/// (read-only) shell in it, and `pgdo promote -D REPLICA_DIR` to fail over.
+///
+/// Requires PostgreSQL 12 or later.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for replica"))]
+pub struct Replica {
+    /// The directory in which the primary cluster lives.
+    #[clap(long = "of", value_name = "PRIMARY_DIR", display_order = 100)]
+    pub primary_dir: PathBuf,
+
+    /// The directory in which to create the replica. Should not exist or be
+    /// empty.
+    #[clap(long = "destination", value_name = "REPLICA_DIR", display_order = 200)]
+    pub replica_dir: PathBuf,
+
+    /// The name of the replication slot to create in the primary. By default
+    /// this is derived from the replica's directory name.
+    #[clap(long = "slot", value_name = "SLOT", display_order = 300)]
+    pub slot: Option<String>,
+}
+
+impl Replica {
+    pub fn invoke(self) -> ExitResult {
+        let Self { primary_dir, replica_dir, slot } = self;
+        let slot = match slot {
+            Some(slot) => slot,
+            None => default_slot_name(&replica_dir),
+        };
+        runner::run(
+            runner::Runner::RunAndStopIfExists,
+            args::ClusterArgs { dir: primary_dir },
+            args::ClusterModeArgs::default(),
+            args::RuntimeArgs::default(),
+            &["pg_basebackup"],
+            args::StopArgs::default(),
+            |primary| {
+                let version = primary.runtime().into_diagnostic()?.version;
+                if version < Version::Post10(12, 0) {
+                    bail!("Replicas require PostgreSQL 12 or later; the primary is {version}");
+                }
+                // pg_basebackup options:
+                //  --write-recovery-conf -- write `standby.signal` and set
+                //    `primary_conninfo` in `postgresql.auto.conf` from the
+                //    connection settings, i.e. the primary's Unix socket.
+                //  --wal-method=stream -- stream WAL while backing up, so
+                //    that the replica is consistent as soon as it starts.
+                let args: Vec<OsString> = vec![
+                    "--pgdata".into(),
+                    replica_dir.clone().into(),
+                    "--format".into(),
+                    "plain".into(),
+                    "--progress".into(),
+                    "--write-recovery-conf".into(),
+                    "--wal-method=stream".into(),
+                    "--create-slot".into(),
+                    format!("--slot={slot}").into(),
+                ];
+                let status = primary
+                    .exec(None, "pg_basebackup".into(), &args)
+                    .wrap_err("Executing pg_basebackup in primary cluster failed")?;
+                if !status.success() {
+                    return runner::check_exit(status);
+                }
+                runner::remove_socket_lock_files(&replica_dir)
+                    .into_diagnostic()
+                    .wrap_err("Could not remove socket lock files from replica")?;
+                start_replica(&replica_dir)
+            },
+        )
+    }
+}
+
+impl From<Replica> for super::Command {
+    fn from(replica: Replica) -> Self {
+        Self::Replica(replica)
+    }
+}
+
+/// Start the newly created replica, under its own lock, to check that it
+/// comes up as a standby and begins streaming from the primary.
+fn start_replica(replica_dir: &Path) -> ExitResult {
+    let term = console::Term::stdout();
+    let (datadir, lock) = runner::lock_for(replica_dir)?;
+    let strategy = runner::determine_strategy(None, &[])?;
+    let replica = cluster::Cluster::new(datadir, strategy)?;
+    let streaming =
+        coordinate::run_and_stop(&replica, &[], cluster::StopOptions::default(), lock, || {
+            if !replica.in_recovery()? {
+                bail!("Replica is not in recovery; it has not started as a standby");
+            }
+            let rt = tokio::runtime::Runtime::new().into_diagnostic()?;
+            let streaming = rt.block_on(async {
+                let pool = replica.pool(None)?;
+                for _ in 0..30 {
+                    let status: Option<String> = cluster::sqlx::query_scalar(
+                        "SELECT status FROM pg_catalog.pg_stat_wal_receiver",
+                    )
+                    .fetch_optional(&pool)
+                    .await?;
+                    if status.as_deref() == Some("streaming") {
+                        return Ok(true);
+                    }
+                    std::thread::sleep(std::time::Duration::from_secs(1));
+                }
+                Ok::<_, cluster::ClusterError>(false)
+            })?;
+            Ok(streaming)
+        })??;
+
+    let replica_dir_sh = {
+        use shell_quote::{QuoteRefExt, Sh};
+        String::from_utf8(replica_dir.quoted(Sh)).into_diagnostic()?
+    };
+    let code = console::Style::new().bold().cyan();
+    if streaming {
+        writeln!(&term, "Replica is streaming from the primary.").into_diagnostic()?;
+    } else {
+        let warning = console::style("WARNING").bold().yellow();
+        writeln!(
+            &term,
+            "{warning}: Replica started as a standby but is not yet streaming from the primary."
+        )
+        .into_diagnostic()?;
+    }
+    writeln!(
+        &term,
+        "Use {} to start the replica, and {} to promote it.",
+        code.apply_to(format!("pgdo -D {replica_dir_sh}")),
+        code.apply_to(format!("pgdo promote -D {replica_dir_sh}")),
+    )
+    .into_diagnostic()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Derive a replication slot name from the replica's directory name. Slot
+/// names may contain only lower case letters, numbers, and underscores, and
+/// must be no longer than 63 characters.
+fn default_slot_name(replica_dir: &Path) -> String {
+    let name = replica_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    format!("pgdo_replica_{name}")
+        .chars()
+        .map(|ch| match ch {
+            'a'..='z' | '0'..='9' | '_' => ch,
+            _ => '_',
+        })
+        .take(63)
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::default_slot_name;
+
+    #[test]
+    fn default_slot_name_is_valid() {
+        assert_eq!(
+            default_slot_name(Path::new("/some/where/Replica-1")),
+            "pgdo_replica_replica_1"
+        );
+        assert_eq!(default_slot_name(Path::new("/")), "pgdo_replica_");
+        assert_eq!(
+            default_slot_name(&Path::new("x").join("é".repeat(80))).len(),
+            63
+        );
+    }
+}
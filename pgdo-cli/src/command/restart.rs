@@ -0,0 +1,56 @@
+use std::{io::Write, process::ExitCode};
+
+use either::{Left, Right};
+use miette::{IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{
+    cluster::{self, resource},
+    coordinate::State,
+};
+
+/// Restart a running cluster, e.g. so that configuration changes take effect.
+///
+/// The cluster is restarted only when it has no other users, i.e. when it can
+/// be locked exclusively. It keeps the options it was started with. Changes to
+/// many settings need only a reload; see the `reload` command.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for restart"))]
+pub struct Restart {
+    #[clap(flatten)]
+    pub cluster: args::ClusterArgs,
+
+    #[clap(flatten)]
+    pub stop: args::StopArgs,
+}
+
+impl Restart {
+    pub fn invoke(self) -> ExitResult {
+        let Self { cluster, stop } = self;
+        let (datadir, lock) = runner::lock_for(cluster.dir)?;
+        let strategy = runner::determine_strategy(None, &[])?;
+        let cluster = cluster::Cluster::new(datadir, strategy)?;
+        let resource = match resource::ResourceFree::new(lock, cluster).try_exclusive()? {
+            Left(_) => Err(cluster::ClusterError::InUse)?,
+            Right(resource) => resource,
+        };
+        let state = resource.facet().restart(stop.into());
+        resource.release()?;
+
+        let term = console::Term::stdout();
+        match state.wrap_err("Restarting cluster failed")? {
+            State::Modified => writeln!(&term, "Cluster restarted."),
+            State::Unmodified => writeln!(&term, "Cluster is not running."),
+        }
+        .into_diagnostic()?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+impl From<Restart> for super::Command {
+    fn from(restart: Restart) -> Self {
+        Self::Restart(restart)
+    }
+}
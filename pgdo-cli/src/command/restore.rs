@@ -1,5 +1,6 @@
 use std::{
     borrow::Cow,
+    ffi::OsStr,
     io::Write,
     path::{Path, PathBuf},
     process::ExitCode,
@@ -14,18 +15,22 @@ use super::ExitResult;
 
 use pgdo::{
     cluster::{self, backup},
-    coordinate::{finally::with_finally, State},
+    coordinate::finally::with_finally,
+    runtime::strategy::StrategyLike,
 };
 
 /// Point-in-time restore/recovery from a backup made previously with the
 /// `backup` command.
 ///
-/// At present, this command only supports restoring the latest backup. In the
-/// future it will be able to restoring to a specific point in time. Indeed,
-/// since the `backup` command already records all the information necessary to
-/// do this, it is possible to follow PostgreSQL's Point-in-Time Recovery
-/// instructions to restore/recover your cluster and its data manually from a
-/// backup created with the `backup` command.
+/// This restores the latest base backup then recovers to the given target using
+/// the archived WAL. With `--inspect` recovery pauses at the target and a `psql`
+/// shell is opened so that you can check the data before deciding whether to
+/// keep the restored cluster, resume recovery to a later target, or abandon it.
+///
+/// At present, this command only supports restoring from the latest backup.
+/// Since the `backup` command records all the information necessary, it is
+/// possible to follow PostgreSQL's Point-in-Time Recovery instructions to
+/// restore/recover your cluster manually from an earlier backup.
 #[derive(clap::Args)]
 #[clap(next_help_heading = Some("Options for restore"))]
 pub struct Restore {
@@ -38,12 +43,44 @@ pub struct Restore {
     /// the restore is complete this will be a usable cluster like any other.
     #[clap(long = "to", value_name = "RESTORE_DIR", display_order = 200)]
     pub restore_dir: PathBuf,
+
+    /// Restore the tablespace originally at OLD into NEW instead. By default,
+    /// tablespaces are restored to their original locations, which must not
+    /// exist or be empty. Use `\=` for a literal `=` in either directory name.
+    /// May be given multiple times.
+    #[clap(
+        long = "tablespace-map",
+        value_name = "OLD=NEW",
+        value_parser = parse_tablespace_mapping,
+        display_order = 300
+    )]
+    pub tablespace_map: Vec<(PathBuf, PathBuf)>,
+
+    /// Where to stop recovery: `immediate` (as soon as the base backup is
+    /// consistent), `latest` (the end of archived WAL), `time:TIMESTAMP`,
+    /// `lsn:LSN`, `name:RESTORE_POINT`, or `xid:TRANSACTION_ID`.
+    #[clap(
+        long = "target",
+        value_name = "TARGET",
+        value_parser = parse_recovery_target,
+        default_value = "immediate",
+        display_order = 400
+    )]
+    pub target: RecoveryTarget,
+
+    /// Pause when recovery reaches its target and open a `psql` shell to
+    /// inspect the restored cluster. Afterwards you can choose to promote it,
+    /// resume recovery to a later target, or abandon the restore. Recovery
+    /// targets can only be set at server start, so resuming restarts the
+    /// cluster with the new target.
+    #[clap(long = "inspect", display_order = 500)]
+    pub inspect: bool,
 }
 
 impl Restore {
     pub fn invoke(self) -> ExitResult {
-        let Self { backup_dir, restore_dir } = self;
-        restore(backup_dir, restore_dir)?;
+        let Self { backup_dir, restore_dir, tablespace_map, target, inspect } = self;
+        restore(backup_dir, restore_dir, &tablespace_map, &target, inspect)?;
         Ok(ExitCode::SUCCESS)
     }
 }
@@ -65,6 +102,8 @@ enum RestoreError {
     #[error("Shell error")]
     ShellError(#[from] std::string::FromUtf8Error),
     #[error(transparent)]
+    BackupError(#[from] backup::BackupError),
+    #[error(transparent)]
     ClusterError(#[from] pgdo::cluster::ClusterError),
     #[error(transparent)]
     StrategyError(#[from] runner::StrategyError),
@@ -89,27 +128,33 @@ impl From<String> for RestoreError {
 }
 
 /// Restore the latest backup into the given `resource` from `backup_dir`.
-fn restore<D: AsRef<Path>>(backup_dir: D, restore_dir: D) -> Result<(), RestoreError> {
+fn restore<D: AsRef<Path>>(
+    backup_dir: D,
+    restore_dir: D,
+    tablespace_map: &[(PathBuf, PathBuf)],
+    target: &RecoveryTarget,
+    inspect: bool,
+) -> Result<(), RestoreError> {
     let term = console::Term::stdout();
+    if inspect && !term.is_term() {
+        Err("Cannot inspect a restore without an interactive terminal")?;
+    }
 
     let backup_dir = backup_dir.as_ref().canonicalize()?;
     let backup_wal_dir = backup_dir.join("wal");
 
     // Find latest backup.
-    let backup_data_dir = backup_dir
-        .read_dir()?
-        .filter_map(|entry| entry.ok()) // Ignore errors.
-        .filter_map(|entry| match entry.file_name().to_str() {
-            Some(name) if name.starts_with(backup::BACKUP_DATA_PREFIX) => name
-                [backup::BACKUP_DATA_PREFIX.len()..]
-                .parse::<u32>()
-                .ok()
-                .map(|n| (n, entry)),
-            Some(_) | None => None,
-        })
-        .max_by_key(|(n, _)| *n)
-        .map(|(_, entry)| entry.path())
+    let backup_data_dir = backup::list(&backup_dir)?
+        .pop()
         .ok_or_else(|| format!("No base backup found in {backup_dir:?}"))?;
+    match backup::Metadata::for_backup(&backup_data_dir)? {
+        Some(metadata) => writeln!(
+            &term,
+            "Restoring base backup {backup_data_dir:?} of PostgreSQL {}, taken at {}.",
+            metadata.server_version, metadata.end_time,
+        )?,
+        None => writeln!(&term, "Restoring base backup {backup_data_dir:?}.")?,
+    }
 
     // Check on the restore directory.
     std::fs::create_dir_all(&restore_dir)?;
@@ -122,46 +167,113 @@ fn restore<D: AsRef<Path>>(backup_dir: D, restore_dir: D) -> Result<(), RestoreE
         std::fs::set_permissions(&restore_dir, perms)?;
     }
 
-    // Copy base backup into place.
-    //
-    // BUGBUG: `copy_with_progress` converts the file name to a string and
-    // crashes if it doesn't convert, determining that it's an invalid file
-    // name. This is a misunderstanding. The file name is valid – the OS gave it
-    // to us! – but it's just not UTF-8. This is not likely to be a problem
-    // though; just noting it because it's one of my pet peeves.
-    {
-        let progress_bar = indicatif::ProgressBar::hidden();
-        progress_bar.set_draw_target(indicatif::ProgressDrawTarget::term(term.clone(), 20));
-        progress_bar.set_style(
-            indicatif::ProgressStyle::with_template(
-                "{wide_bar} {percent}% complete; {msg}; {eta} remaining",
-            )
-            .expect("invalid progress bar template"),
-        );
-        fs_extra::dir::copy_with_progress(
-            backup_data_dir,
-            &restore_dir,
-            &fs_extra::dir::CopyOptions::new().content_only(true),
-            |progress| match progress.state {
-                fs_extra::dir::TransitState::Exists => fs_extra::dir::TransitProcessResult::Abort,
-                fs_extra::dir::TransitState::NoAccess => fs_extra::dir::TransitProcessResult::Abort,
-                fs_extra::dir::TransitState::Normal => {
-                    progress_bar.set_length(progress.total_bytes);
-                    progress_bar.set_position(progress.copied_bytes);
-                    progress_bar.set_message(format!(
-                        "{count} of {total} copied",
-                        count = indicatif::HumanBytes(progress.copied_bytes),
-                        total = indicatif::HumanBytes(progress.total_bytes),
-                    ));
-                    fs_extra::dir::TransitProcessResult::ContinueOrAbort
-                }
-            },
+    // Check that the tablespace mappings refer to tablespaces in the backup.
+    let backup_tablespaces = backup::TablespaceMap::for_backup(&backup_data_dir)?;
+    if let Some((old, _)) = tablespace_map.iter().find(|(old, _)| {
+        !backup_tablespaces
+            .0
+            .values()
+            .any(|location| location == old)
+    }) {
+        Err(format!("No tablespace in backup was located at {old:?}"))?;
+    }
+
+    // Work out where each tablespace is to be restored, and check that these
+    // locations are suitable.
+    let restore_tablespaces = backup::TablespaceMap(
+        backup_tablespaces
+            .0
+            .iter()
+            .map(|(oid, location)| {
+                let location = tablespace_map
+                    .iter()
+                    .find_map(|(old, new)| (old == location).then_some(new))
+                    .unwrap_or(location);
+                (*oid, location.clone())
+            })
+            .collect(),
+    );
+    for location in restore_tablespaces.0.values() {
+        std::fs::create_dir_all(location)?;
+        if location.read_dir()?.next().is_some() {
+            Err(format!(
+                "Tablespace directory {location:?} is not empty; use --tablespace-map to relocate it"
+            ))?;
+        }
+        let mut perms = location.metadata()?.permissions();
+        std::os::unix::fs::PermissionsExt::set_mode(&mut perms, 0o700);
+        std::fs::set_permissions(location, perms)?;
+    }
+
+    let backup_chain = backup::chain(&backup_data_dir)?;
+    if backup_chain.len() > 1 {
+        // The latest backup is incremental, so reconstruct a full backup from
+        // the chain. This also restores tablespaces and links to them.
+        let runtime = {
+            let version = cluster::version(&backup_data_dir)?
+                .ok_or_else(|| format!("Could not determine version of {backup_data_dir:?}"))?;
+            runner::determine_strategy(None, &["pg_combinebackup"])?
+                .select(&version.into())
+                .ok_or(cluster::ClusterError::RuntimeNotFound(version))?
+        };
+        write!(
+            &term,
+            "Combining {} incremental backup(s) with full backup…",
+            backup_chain.len() - 1
         )?;
-        progress_bar.finish_and_clear();
+        backup::combine(&runtime, &backup_chain, &restore_dir, &restore_tablespaces)?;
+        writeln!(&term, " done.")?;
+    } else {
+        // Copy or unpack base backup into place.
+        match find_archive(&backup_data_dir, "base") {
+            Some(archive) => {
+                writeln!(&term, "Unpacking base backup…")?;
+                unpack_with_progress(&term, &archive, &restore_dir)?;
+            }
+            None => copy_dir_contents_with_progress(
+                &term,
+                &backup_data_dir,
+                &restore_dir,
+                &["pg_tblspc"],
+            )?,
+        }
+
+        // A tar-format base backup contains a `tablespace_map` file which
+        // refers to the tablespaces' original locations. PostgreSQL would
+        // recreate the links in `pg_tblspc` from this during recovery, so
+        // remove it.
+        match std::fs::remove_file(restore_dir.join("tablespace_map")) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)?,
+            Ok(()) | Err(_) => (),
+        }
+
+        // Copy tablespaces into place, and link to them from `pg_tblspc`.
+        let backup_tablespaces_dir = backup::tablespaces_dir(&backup_data_dir);
+        let restore_tblspc_dir = restore_dir.join("pg_tblspc");
+        std::fs::create_dir_all(&restore_tblspc_dir)?;
+        for (oid, location) in &restore_tablespaces.0 {
+            writeln!(&term, "Restoring tablespace {oid} into {location:?}…")?;
+            match find_archive(&backup_data_dir, &oid.to_string()) {
+                Some(archive) => unpack_with_progress(&term, &archive, location)?,
+                None => copy_dir_contents_with_progress(
+                    &term,
+                    &backup_tablespaces_dir.join(oid.to_string()),
+                    location,
+                    &[],
+                )?,
+            }
+            let link = restore_tblspc_dir.join(oid.to_string());
+            if link.symlink_metadata().is_ok() {
+                std::fs::remove_file(&link)?;
+            }
+            std::os::unix::fs::symlink(location, link)?;
+        }
     }
 
-    // Remove WAL from restored backup.
+    // Remove WAL from restored backup. In a tar-format backup, WAL is archived
+    // separately, and we do not unpack it, so `pg_wal` may not exist.
     write!(&term, "Removing WAL from restored cluster…")?;
+    std::fs::create_dir_all(restore_dir.join("pg_wal"))?;
     empty_out_dir(restore_dir.join("pg_wal"))?;
     writeln!(&term, " done.")?;
 
@@ -173,94 +285,112 @@ fn restore<D: AsRef<Path>>(backup_dir: D, restore_dir: D) -> Result<(), RestoreE
     // interactively inspect the cluster).
     let backup_wal_dir_sh = String::from_utf8(backup_wal_dir.quoted(Sh))?;
     let restore_command = format!("cp {backup_wal_dir_sh}/%f %p");
+    let recovery_target_action = if inspect { "pause" } else { "shutdown" };
+    let recovery_options = |target: &RecoveryTarget| {
+        let mut options = vec![
+            (runner::ARCHIVE_MODE, "off".into()),
+            (HOT_STANDBY, true.into()),
+            (RESTORE_COMMAND, restore_command.as_str().into()),
+            (RECOVERY_TARGET_ACTION, recovery_target_action.into()),
+        ];
+        options.extend(target.option());
+        options
+    };
 
     let (datadir, lock) = runner::lock_for(&restore_dir)?;
-    let strategy = runner::determine_strategy(None)?;
+    // Inspecting runs `psql` with the restored cluster's runtime.
+    let binaries: &[&str] = if inspect { &["psql"] } else { &[] };
+    let strategy = runner::determine_strategy(None, binaries)?;
     let cluster = cluster::Cluster::new(datadir, strategy)?;
     let resource = cluster::resource::ResourceFree::new(lock, cluster);
 
-    let (State::Modified, Right(resource)) = cluster::resource::startup(
-        resource,
-        &[
-            (ARCHIVE_MODE, "off".into()),
-            (RESTORE_COMMAND, restore_command.into()),
-            (RECOVERY_TARGET, "immediate".into()),
-            (RECOVERY_TARGET_ACTION, "shutdown".into()),
-        ],
-    )?
-    else {
+    let Right(resource) = resource.try_exclusive()? else {
+        Err(format!("Restored cluster is in use in {restore_dir:?}!"))?
+    };
+    if resource.facet().running()? {
         Err(format!(
             "Restored cluster is already running in {restore_dir:?}!"
-        ))?
-    };
-
-    // Wait for recovery to complete.
-    {
-        let start = std::time::Instant::now();
-        let interval = std::time::Duration::from_secs(1);
-        let message = "Waiting for database recovery…";
-        term.write_line(message)?;
-        while resource.facet().running()? {
-            std::thread::sleep(interval);
-            term.clear_last_lines(1)?;
-            writeln!(
-                &term,
-                "{message} ({} elapsed)",
-                indicatif::HumanDuration(start.elapsed())
-            )?;
-        }
-        term.clear_last_lines(1)?;
+        ))?;
+    }
+    match resource.facet().start(&recovery_options(target)) {
+        Ok(_) => (),
+        // When shutting down at the target, the server may get there before
+        // `pg_ctl` sees it running, and then `pg_ctl` reports that it failed to
+        // start. The control file tells us otherwise.
+        Err(_) if !inspect && shut_down_in_recovery(&restore_dir) => (),
+        Err(err) => Err(err)?,
     }
 
-    // Remove the `recovery.signal` file in the restore so that subsequent
-    // starts do not initiate database recovery.
-    std::fs::remove_file(restore_dir.join("recovery.signal"))?;
+    // Interrupting `psql` must not also interrupt the restore, but otherwise
+    // an interrupt stops the restored cluster and leaves it in place.
+    runner::handle_interrupts().map_err(|err| format!("Could not set signal handler: {err}"))?;
 
-    // Disable archiving.
-    writeln!(&term, "Disabling archiving…")?;
-    resource.facet().start(&[(ARCHIVE_MODE, "off".into())])?;
-    with_finally(
-        || resource.facet().stop(),
+    let keep = with_finally(
+        || resource.facet().stop(cluster::StopOptions::default()),
         || {
             let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(async {
-                let pool = resource.facet().pool(None)?;
-
-                write!(&term, "Resetting {ARCHIVE_MODE}…")?;
-                ARCHIVE_MODE.reset(&pool).await?;
-                writeln!(&term, " done.")?;
-
-                write!(&term, "Resetting {ARCHIVE_COMMAND}…")?;
-                ARCHIVE_COMMAND.reset(&pool).await?;
-                writeln!(&term, " done.")?;
-
-                write!(&term, "Resetting {ARCHIVE_LIBRARY}…")?;
-                match ARCHIVE_LIBRARY.reset(&pool).await {
-                    Ok(_) => writeln!(&term, " done.")?,
-                    Err(err) => {
-                        match err.as_database_error() {
-                            // 42704 means UNDEFINED_OBJECT, i.e. this parameter is
-                            // not supported in this version of PostgreSQL.
-                            Some(err) if err.code() == Some("42704".into()) => {
-                                writeln!(&term, " not supported.")?;
-                                Ok(())
-                            }
-                            _ => Err(err),
-                        }?;
+            loop {
+                let decision = match wait_for_recovery(&term, &rt, &resource)? {
+                    // Recovery has already ended, e.g. at the end of WAL.
+                    Recovery::Ended => Decision::Keep,
+                    Recovery::ShutDown if !inspect => {
+                        // Recovery reached its target and shut down. Remove the
+                        // `recovery.signal` file so that the cluster starts
+                        // afresh, without recovery, to reset archiving.
+                        std::fs::remove_file(restore_dir.join("recovery.signal"))?;
+                        resource
+                            .facet()
+                            .start(&[(runner::ARCHIVE_MODE, "off".into())])?;
+                        Decision::Keep
                     }
+                    Recovery::ShutDown => {
+                        Err("Cluster stopped during recovery; check its log for details")?
+                    }
+                    Recovery::Paused(position) => inspect_recovery(&term, &resource, &position)?,
                 };
-
-                Ok::<_, cluster::ClusterError>(())
-            })?;
-
-            Ok::<_, RestoreError>(())
+                match decision {
+                    Decision::Promote => {
+                        write!(&term, "Promoting restored cluster…")?;
+                        resource.facet().promote()?;
+                        writeln!(&term, " done.")?;
+                    }
+                    Decision::Keep => (),
+                    Decision::Resume(target) => {
+                        // `pg_wal_replay_resume()` at a target that has been
+                        // reached ends recovery there, and recovery targets
+                        // can only be set at server start, so restart with the
+                        // new target instead.
+                        write!(&term, "Resuming recovery to {target}…")?;
+                        resource.facet().stop(cluster::StopOptions::default())?;
+                        resource.facet().start(&recovery_options(&target))?;
+                        writeln!(&term, " done.")?;
+                        continue;
+                    }
+                    Decision::Abandon => break Ok(false),
+                }
+                rt.block_on(async {
+                    runner::reset_archiving(&term, &resource.facet().pool(None)?).await
+                })?;
+                writeln!(&term, "Archiving disabled in restored cluster.")?;
+                break Ok::<_, RestoreError>(true);
+            }
         },
     )?;
-    writeln!(&term, "Archiving disabled in restored cluster.")?;
 
     // We're finished with the resource, but we still need the cluster.
     let (_lock, cluster) = resource.release()?.into_parts();
 
+    if !keep {
+        write!(&term, "Removing restored cluster…")?;
+        empty_out_dir(&restore_dir)?;
+        for location in restore_tablespaces.0.values() {
+            empty_out_dir(location)?;
+        }
+        writeln!(&term, " done.")?;
+        writeln!(&term, "Restore abandoned.")?;
+        return Ok(());
+    }
+
     // Determine superusers in the restored cluster. This can help us give the
     // user more specific advice about how to start the cluster.
     let superusers = cluster::determine_superuser_role_names(&cluster)?;
@@ -311,16 +441,377 @@ fn restore<D: AsRef<Path>>(backup_dir: D, restore_dir: D) -> Result<(), RestoreE
 
 // ----------------------------------------------------------------------------
 
-static ARCHIVE_MODE: cluster::config::Parameter = cluster::config::Parameter("archive_mode");
-static ARCHIVE_COMMAND: cluster::config::Parameter = cluster::config::Parameter("archive_command");
-static ARCHIVE_LIBRARY: cluster::config::Parameter = cluster::config::Parameter("archive_library");
+static HOT_STANDBY: cluster::config::Parameter = cluster::config::Parameter("hot_standby");
 static RESTORE_COMMAND: cluster::config::Parameter = cluster::config::Parameter("restore_command");
 static RECOVERY_TARGET: cluster::config::Parameter = cluster::config::Parameter("recovery_target");
 static RECOVERY_TARGET_ACTION: cluster::config::Parameter =
     cluster::config::Parameter("recovery_target_action");
+static RECOVERY_TARGET_LSN: cluster::config::Parameter =
+    cluster::config::Parameter("recovery_target_lsn");
+static RECOVERY_TARGET_NAME: cluster::config::Parameter =
+    cluster::config::Parameter("recovery_target_name");
+static RECOVERY_TARGET_TIME: cluster::config::Parameter =
+    cluster::config::Parameter("recovery_target_time");
+static RECOVERY_TARGET_XID: cluster::config::Parameter =
+    cluster::config::Parameter("recovery_target_xid");
+
+// ----------------------------------------------------------------------------
+
+/// Where recovery should stop. These correspond to PostgreSQL's `recovery_target`
+/// and `recovery_target_*` settings, except for [`RecoveryTarget::Latest`],
+/// which means no target at all, i.e. recover to the end of archived WAL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecoveryTarget {
+    Immediate,
+    Latest,
+    Time(String),
+    Lsn(String),
+    Name(String),
+    Xid(String),
+}
+
+impl RecoveryTarget {
+    /// The setting with which to start the cluster to recover to this target.
+    fn option(&self) -> Option<(cluster::config::Parameter<'static>, cluster::config::Value)> {
+        match self {
+            Self::Immediate => Some((RECOVERY_TARGET, "immediate".into())),
+            Self::Latest => None,
+            Self::Time(time) => Some((RECOVERY_TARGET_TIME, time.into())),
+            Self::Lsn(lsn) => Some((RECOVERY_TARGET_LSN, lsn.into())),
+            Self::Name(name) => Some((RECOVERY_TARGET_NAME, name.into())),
+            Self::Xid(xid) => Some((RECOVERY_TARGET_XID, xid.into())),
+        }
+    }
+}
+
+impl std::fmt::Display for RecoveryTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Immediate => write!(f, "immediate"),
+            Self::Latest => write!(f, "latest"),
+            Self::Time(time) => write!(f, "time:{time}"),
+            Self::Lsn(lsn) => write!(f, "lsn:{lsn}"),
+            Self::Name(name) => write!(f, "name:{name}"),
+            Self::Xid(xid) => write!(f, "xid:{xid}"),
+        }
+    }
+}
+
+/// Parse a recovery target, e.g. `immediate`, `latest`, or `KIND:VALUE` where
+/// `KIND` is one of `time`, `lsn`, `name`, or `xid`.
+fn parse_recovery_target(target: &str) -> Result<RecoveryTarget, String> {
+    match target.split_once(':') {
+        None if target == "immediate" => Ok(RecoveryTarget::Immediate),
+        None if target == "latest" => Ok(RecoveryTarget::Latest),
+        Some((_, "")) => Err("recovery target value must not be empty".into()),
+        Some(("time", time)) => Ok(RecoveryTarget::Time(time.into())),
+        Some(("lsn", lsn)) => Ok(RecoveryTarget::Lsn(lsn.into())),
+        Some(("name", name)) => Ok(RecoveryTarget::Name(name.into())),
+        Some(("xid", xid)) => Ok(RecoveryTarget::Xid(xid.into())),
+        Some(_) | None => Err(
+            "recovery target must be \"immediate\", \"latest\", or one of \"time:…\", \"lsn:…\", \"name:…\", or \"xid:…\"".into(),
+        ),
+    }
+}
+
+/// The error when interrupted, e.g. by Ctrl-C, while waiting for recovery or
+/// for the user to decide what to do.
+const INTERRUPTED: &str = "Restore interrupted; the restored cluster has been stopped";
+
+/// What to do with a cluster once recovery has paused at its target.
+enum Decision {
+    /// Promote the cluster, i.e. end recovery here and keep it.
+    Promote,
+    /// Recovery has already ended; keep the cluster.
+    Keep,
+    /// Resume recovery to the given target. This restarts the cluster since
+    /// PostgreSQL reads recovery targets only at server start, and
+    /// `pg_wal_replay_resume()` ends recovery once a target has been reached.
+    Resume(RecoveryTarget),
+    /// Discard the restored cluster.
+    Abandon,
+}
+
+/// Where recovery has paused.
+struct RecoveryPosition {
+    lsn: Option<String>,
+    time: Option<String>,
+}
+
+/// How recovery stopped; see [`wait_for_recovery`].
+enum Recovery {
+    /// Recovery paused at its target.
+    Paused(RecoveryPosition),
+    /// Recovery ended, e.g. on reaching the end of archived WAL, and the
+    /// cluster has been promoted.
+    Ended,
+    /// The cluster shut down, e.g. on reaching its target.
+    ShutDown,
+}
+
+/// Wait for recovery to pause at its target, to end, or for the cluster to shut
+/// down, showing progress on the given terminal.
+fn wait_for_recovery(
+    mut term: &console::Term,
+    rt: &tokio::runtime::Runtime,
+    resource: &cluster::resource::ResourceExclusive,
+) -> Result<Recovery, RestoreError> {
+    let start = std::time::Instant::now();
+    let interval = std::time::Duration::from_secs(1);
+    let message = "Waiting for database recovery…";
+    term.write_line(message)?;
+    let state = rt.block_on(async {
+        let pool = resource.facet().pool(None)?;
+        loop {
+            if runner::interrupted() {
+                break Err(RestoreError::from(INTERRUPTED));
+            }
+            if !resource.facet().running()? {
+                break Ok(None);
+            }
+            let state: Result<(Option<bool>, Option<String>, Option<String>), _> =
+                cluster::sqlx::query_as(
+                    "SELECT CASE WHEN pg_is_in_recovery() THEN pg_is_wal_replay_paused() END, \
+                     pg_last_wal_replay_lsn()::text, pg_last_xact_replay_timestamp()::text",
+                )
+                .fetch_one(&pool)
+                .await;
+            let state = match state {
+                Ok(state) => state,
+                // The server may be shutting down, e.g. at the recovery
+                // target; see if it has stopped on the next iteration.
+                Err(err) if unavailable(&err) => (Some(false), None, None),
+                Err(err) => Err(cluster::ClusterError::from(err))?,
+            };
+            if let (Some(false), _, _) = state {
+                tokio::time::sleep(interval).await;
+                term.clear_last_lines(1)?;
+                writeln!(
+                    term,
+                    "{message} ({} elapsed)",
+                    indicatif::HumanDuration(start.elapsed())
+                )?;
+            } else {
+                break Ok(Some(state));
+            }
+        }
+    })?;
+    term.clear_last_lines(1)?;
+    match state {
+        None => Ok(Recovery::ShutDown),
+        Some((Some(true), lsn, time)) => Ok(Recovery::Paused(RecoveryPosition { lsn, time })),
+        Some(_) => {
+            writeln!(term, "Recovery complete.")?;
+            Ok(Recovery::Ended)
+        }
+    }
+}
+
+/// Did the cluster in `datadir` shut down cleanly while in recovery, according
+/// to its control file?
+fn shut_down_in_recovery(datadir: &Path) -> bool {
+    matches!(
+        cluster::control::DatabaseState::read(datadir),
+        Ok(Some(cluster::control::DatabaseState::ShutDownInRecovery))
+    )
+}
+
+/// Is this error from the server refusing or dropping connections, e.g. while
+/// it shuts down?
+fn unavailable(err: &cluster::sqlx::Error) -> bool {
+    match err {
+        cluster::sqlx::Error::Io(_) | cluster::sqlx::Error::PoolTimedOut => true,
+        // 57P01 is ADMIN_SHUTDOWN, 57P03 is CANNOT_CONNECT_NOW.
+        cluster::sqlx::Error::Database(err) => {
+            matches!(err.code().as_deref(), Some("57P01" | "57P03"))
+        }
+        _ => false,
+    }
+}
+
+/// Open a `psql` shell in the paused cluster, then ask the user what to do.
+fn inspect_recovery(
+    mut term: &console::Term,
+    resource: &cluster::resource::ResourceExclusive,
+    position: &RecoveryPosition,
+) -> Result<Decision, RestoreError> {
+    let code = console::Style::new().bold().cyan();
+    writeln!(
+        term,
+        "Recovery paused at LSN {}; last transaction replayed at {}.",
+        position.lsn.as_deref().unwrap_or("(unknown)"),
+        position.time.as_deref().unwrap_or("(none)"),
+    )?;
+    loop {
+        writeln!(
+            term,
+            "Opening {} in the restored cluster; it is read-only until promoted. Exit to continue.",
+            code.apply_to("psql"),
+        )?;
+        runner::ignoring_interrupts(|| resource.facet().shell(None))?;
+        loop {
+            write!(
+                term,
+                "[p]romote and keep, [r]esume to a later target, open [s]hell again, or [a]bandon? "
+            )?;
+            let answer = term.read_line()?;
+            if runner::interrupted() {
+                Err(INTERRUPTED)?;
+            }
+            match answer.trim() {
+                "p" | "promote" => return Ok(Decision::Promote),
+                "a" | "abandon" => return Ok(Decision::Abandon),
+                "s" | "shell" => break,
+                "r" | "resume" => loop {
+                    write!(
+                        term,
+                        "Recovery target (time:…, lsn:…, name:…, xid:…, or latest)? "
+                    )?;
+                    let answer = term.read_line()?;
+                    if runner::interrupted() {
+                        Err(INTERRUPTED)?;
+                    }
+                    match parse_recovery_target(answer.trim()) {
+                        Ok(target) => return Ok(Decision::Resume(target)),
+                        Err(err) => writeln!(term, "Sorry, {err}.")?,
+                    }
+                },
+                _ => continue,
+            }
+        }
+    }
+}
 
 // ----------------------------------------------------------------------------
 
+/// Create a progress bar drawing to the given terminal.
+fn progress_bar(term: &console::Term, template: &str) -> indicatif::ProgressBar {
+    let progress_bar = indicatif::ProgressBar::hidden();
+    progress_bar.set_draw_target(indicatif::ProgressDrawTarget::term(term.clone(), 20));
+    progress_bar.set_style(
+        indicatif::ProgressStyle::with_template(template).expect("invalid progress bar template"),
+    );
+    progress_bar
+}
+
+/// Copy the contents of directory `source` into directory `target`, except for
+/// entries named in `exclude`, showing progress on the given terminal.
+///
+/// BUGBUG: `copy_items_with_progress` converts the file name to a string and
+/// crashes if it doesn't convert, determining that it's an invalid file name.
+/// This is a misunderstanding. The file name is valid – the OS gave it to us! –
+/// but it's just not UTF-8. This is not likely to be a problem though; just
+/// noting it because it's one of my pet peeves.
+fn copy_dir_contents_with_progress(
+    term: &console::Term,
+    source: &Path,
+    target: &Path,
+    exclude: &[&str],
+) -> Result<(), RestoreError> {
+    let items = source
+        .read_dir()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| match path {
+            Ok(path) => !path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| exclude.contains(&name)),
+            Err(_) => true,
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let progress_bar = progress_bar(
+        term,
+        "{wide_bar} {percent}% complete; {msg}; {eta} remaining",
+    );
+    fs_extra::copy_items_with_progress(
+        &items,
+        target,
+        &fs_extra::dir::CopyOptions::new(),
+        |progress| match progress.state {
+            fs_extra::dir::TransitState::Exists => fs_extra::dir::TransitProcessResult::Abort,
+            fs_extra::dir::TransitState::NoAccess => fs_extra::dir::TransitProcessResult::Abort,
+            fs_extra::dir::TransitState::Normal => {
+                progress_bar.set_length(progress.total_bytes);
+                progress_bar.set_position(progress.copied_bytes);
+                progress_bar.set_message(format!(
+                    "{count} of {total} copied",
+                    count = indicatif::HumanBytes(progress.copied_bytes),
+                    total = indicatif::HumanBytes(progress.total_bytes),
+                ));
+                fs_extra::dir::TransitProcessResult::ContinueOrAbort
+            }
+        },
+    )?;
+    progress_bar.finish_and_clear();
+    Ok(())
+}
+
+/// Find the tar archive named `{name}.tar` in `dir`, with or without one of the
+/// compression suffixes that `pg_basebackup` uses.
+fn find_archive(dir: &Path, name: &str) -> Option<PathBuf> {
+    [None, Some("gz"), Some("lz4"), Some("zst")]
+        .into_iter()
+        .map(|suffix| match suffix {
+            Some(suffix) => dir.join(format!("{name}.tar.{suffix}")),
+            None => dir.join(format!("{name}.tar")),
+        })
+        .find(|path| path.is_file())
+}
+
+/// Unpack the tar archive at `archive` into directory `target`, decompressing
+/// it according to its file name suffix, and showing progress on the given
+/// terminal.
+fn unpack_with_progress(
+    term: &console::Term,
+    archive: &Path,
+    target: &Path,
+) -> Result<(), RestoreError> {
+    let file = std::fs::File::open(archive)?;
+    let progress_bar = progress_bar(
+        term,
+        "{wide_bar} {percent}% complete; {bytes} of {total_bytes} unpacked; {eta} remaining",
+    );
+    progress_bar.set_length(file.metadata()?.len());
+    let reader = progress_bar.wrap_read(std::io::BufReader::new(file));
+    let reader: Box<dyn std::io::Read> = match archive.extension().and_then(OsStr::to_str) {
+        Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Some("lz4") => Box::new(lz4_flex::frame::FrameDecoder::new(reader)),
+        Some("zst") => Box::new(zstd::Decoder::new(reader)?),
+        Some(_) | None => Box::new(reader),
+    };
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.unpack(target)?;
+    progress_bar.finish_and_clear();
+    Ok(())
+}
+
+/// Parse an `OLD=NEW` tablespace mapping. As with `pg_basebackup`, a literal
+/// `=` in either directory name can be escaped with a backslash.
+fn parse_tablespace_mapping(mapping: &str) -> Result<(PathBuf, PathBuf), String> {
+    let mut parts = vec![String::new()];
+    let mut chars = mapping.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.next_if_eq(&'=').is_some() => parts.last_mut().unwrap().push('='),
+            '=' => parts.push(String::new()),
+            ch => parts.last_mut().unwrap().push(ch),
+        }
+    }
+    match parts.as_slice() {
+        [old, new] if !old.is_empty() && !new.is_empty() => {
+            let (old, new) = (PathBuf::from(old), PathBuf::from(new));
+            if old.is_absolute() && new.is_absolute() {
+                Ok((old, new))
+            } else {
+                Err("tablespace directories must be absolute paths".into())
+            }
+        }
+        [_, _, _, ..] => Err("multiple \"=\" signs in tablespace mapping".into()),
+        _ => Err("invalid tablespace mapping format, must be \"OLD=NEW\"".into()),
+    }
+}
+
 /// Remove the contents of the given directory, but leave the directory itself.
 fn empty_out_dir<P: AsRef<Path>>(dir: P) -> Result<(), std::io::Error> {
     dir.as_ref().read_dir()?.try_for_each(|entry| {
@@ -333,3 +824,41 @@ fn empty_out_dir<P: AsRef<Path>>(dir: P) -> Result<(), std::io::Error> {
         Ok::<_, std::io::Error>(())
     })
 }
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse_recovery_target, parse_tablespace_mapping, RecoveryTarget};
+
+    #[test]
+    fn parse_tablespace_mapping_splits_on_unescaped_equals() {
+        assert_eq!(
+            parse_tablespace_mapping("/old/a\\=b=/new/c"),
+            Ok((PathBuf::from("/old/a=b"), PathBuf::from("/new/c")))
+        );
+        assert!(parse_tablespace_mapping("/old").is_err());
+        assert!(parse_tablespace_mapping("/old=/new=/other").is_err());
+        assert!(parse_tablespace_mapping("old=/new").is_err());
+        assert!(parse_tablespace_mapping("=/new").is_err());
+    }
+
+    #[test]
+    fn parse_recovery_target_round_trips() {
+        for target in [
+            RecoveryTarget::Immediate,
+            RecoveryTarget::Latest,
+            RecoveryTarget::Time("2024-01-02 03:04:05+00".into()),
+            RecoveryTarget::Lsn("0/3000000".into()),
+            RecoveryTarget::Name("before-migration".into()),
+            RecoveryTarget::Xid("1234".into()),
+        ] {
+            assert_eq!(parse_recovery_target(&target.to_string()), Ok(target));
+        }
+        assert!(parse_recovery_target("").is_err());
+        assert!(parse_recovery_target("lsn:").is_err());
+        assert!(parse_recovery_target("epoch:0").is_err());
+    }
+}
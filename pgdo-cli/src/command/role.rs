@@ -0,0 +1,527 @@
+use std::{
+    io::{BufRead, Write},
+    process::ExitCode,
+};
+
+use miette::{bail, IntoDiagnostic, WrapErr};
+
+use super::ExitResult;
+use crate::{args, runner};
+
+use pgdo::{
+    cluster::roles::{self, Object, Password, Privilege, RoleOptions},
+    coordinate::State,
+};
+
+/// Manage roles (users and groups) and their privileges.
+///
+/// The cluster is created and started as necessary.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for role"))]
+pub struct Role {
+    #[clap(subcommand)]
+    command: RoleCommand,
+}
+
+impl Role {
+    pub fn invoke(self) -> ExitResult {
+        match self.command {
+            RoleCommand::List { cluster, runtime, stop, all } => {
+                with_cluster(cluster, runtime, stop, |cluster, term| {
+                    list(cluster, term, all)
+                })
+            }
+            RoleCommand::Create { cluster, runtime, stop, name, attributes } => {
+                let options = attributes.into_options(&name)?;
+                with_cluster(cluster, runtime, stop, |cluster, term| {
+                    match roles::create(cluster, &name, &options)? {
+                        State::Modified => writeln!(&term, "Role {name:?} created."),
+                        State::Unmodified => writeln!(&term, "Role {name:?} already exists."),
+                    }
+                    .into_diagnostic()
+                })
+            }
+            RoleCommand::Alter { cluster, runtime, stop, name, attributes, not_member_of } => {
+                let options = attributes.into_options(&name)?;
+                with_cluster(cluster, runtime, stop, |cluster, term| {
+                    roles::alter(cluster, &name, &options)?;
+                    for role in &not_member_of {
+                        roles::revoke_membership(cluster, role, &name)?;
+                    }
+                    writeln!(&term, "Role {name:?} altered.").into_diagnostic()
+                })
+            }
+            RoleCommand::Drop { cluster, runtime, stop, name } => {
+                with_cluster(cluster, runtime, stop, |cluster, term| {
+                    match roles::drop(cluster, &name)? {
+                        State::Modified => writeln!(&term, "Role {name:?} dropped."),
+                        State::Unmodified => writeln!(&term, "Role {name:?} does not exist."),
+                    }
+                    .into_diagnostic()
+                })
+            }
+            RoleCommand::Grant { cluster, runtime, stop, database, privileges, object, role } => {
+                with_cluster(cluster, runtime, stop, |cluster, _| {
+                    let privileges: Vec<_> = privileges.into_iter().map(Into::into).collect();
+                    roles::grant(
+                        cluster,
+                        database.as_deref(),
+                        &privileges,
+                        object.object(),
+                        &role,
+                    )?;
+                    Ok(())
+                })
+            }
+            RoleCommand::Revoke { cluster, runtime, stop, database, privileges, object, role } => {
+                with_cluster(cluster, runtime, stop, |cluster, _| {
+                    let privileges: Vec<_> = privileges.into_iter().map(Into::into).collect();
+                    roles::revoke(
+                        cluster,
+                        database.as_deref(),
+                        &privileges,
+                        object.object(),
+                        &role,
+                    )?;
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
+impl From<Role> for super::Command {
+    fn from(role: Role) -> Self {
+        Self::Role(role)
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum RoleCommand {
+    /// List roles.
+    #[clap(display_order = 1)]
+    List {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// Include PostgreSQL's predefined roles, e.g. `pg_monitor`.
+        #[clap(long = "all", display_order = 100)]
+        all: bool,
+    },
+
+    /// Create a role.
+    #[clap(display_order = 2)]
+    Create {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The name of the role.
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        #[clap(flatten)]
+        attributes: AttributeArgs,
+    },
+
+    /// Alter a role's attributes and memberships.
+    #[clap(display_order = 3)]
+    Alter {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The name of the role.
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        #[clap(flatten)]
+        attributes: AttributeArgs,
+
+        /// Remove the role from these roles.
+        #[clap(
+            long = "not-member-of",
+            value_name = "ROLE",
+            value_delimiter = ',',
+            display_order = 900
+        )]
+        not_member_of: Vec<String>,
+    },
+
+    /// Drop a role.
+    #[clap(display_order = 4)]
+    Drop {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The name of the role.
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Grant privileges on a database, schema, or table to a role.
+    #[clap(display_order = 5)]
+    Grant {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The database in which to find the schema or table.
+        #[clap(short = 'd', long = "database", display_order = 2)]
+        database: Option<String>,
+
+        /// The privileges to grant.
+        #[clap(value_name = "PRIVILEGE", value_delimiter = ',', required = true)]
+        privileges: Vec<PrivilegeArg>,
+
+        #[clap(flatten)]
+        object: ObjectArgs,
+
+        /// The role to which to grant privileges.
+        #[clap(long = "to", value_name = "ROLE", display_order = 200)]
+        role: String,
+    },
+
+    /// Revoke privileges on a database, schema, or table from a role.
+    #[clap(display_order = 6)]
+    Revoke {
+        #[clap(flatten)]
+        cluster: args::ClusterArgs,
+
+        #[clap(flatten)]
+        runtime: args::RuntimeArgs,
+
+        #[clap(flatten)]
+        stop: args::StopArgs,
+
+        /// The database in which to find the schema or table.
+        #[clap(short = 'd', long = "database", display_order = 2)]
+        database: Option<String>,
+
+        /// The privileges to revoke.
+        #[clap(value_name = "PRIVILEGE", value_delimiter = ',', required = true)]
+        privileges: Vec<PrivilegeArg>,
+
+        #[clap(flatten)]
+        object: ObjectArgs,
+
+        /// The role from which to revoke privileges.
+        #[clap(long = "from", value_name = "ROLE", display_order = 200)]
+        role: String,
+    },
+}
+
+/// Role attributes. Each can be switched on or off; when neither is given, the
+/// attribute is left alone or, when creating, PostgreSQL's default is used.
+#[derive(clap::Args)]
+struct AttributeArgs {
+    /// Allow the role to log in.
+    #[clap(long = "login", overrides_with = "no_login", display_order = 100)]
+    login: bool,
+    /// Do not allow the role to log in.
+    #[clap(long = "no-login", display_order = 101)]
+    no_login: bool,
+
+    /// Make the role a superuser.
+    #[clap(
+        long = "superuser",
+        overrides_with = "no_superuser",
+        display_order = 110
+    )]
+    superuser: bool,
+    /// Make the role an ordinary (non-superuser) role.
+    #[clap(long = "no-superuser", display_order = 111)]
+    no_superuser: bool,
+
+    /// Allow the role to create databases.
+    #[clap(long = "createdb", overrides_with = "no_createdb", display_order = 120)]
+    createdb: bool,
+    /// Do not allow the role to create databases.
+    #[clap(long = "no-createdb", display_order = 121)]
+    no_createdb: bool,
+
+    /// Allow the role to create, alter, and drop other roles.
+    #[clap(
+        long = "createrole",
+        overrides_with = "no_createrole",
+        display_order = 130
+    )]
+    createrole: bool,
+    /// Do not allow the role to manage other roles.
+    #[clap(long = "no-createrole", display_order = 131)]
+    no_createrole: bool,
+
+    /// Allow the role to initiate replication.
+    #[clap(
+        long = "replication",
+        overrides_with = "no_replication",
+        display_order = 140
+    )]
+    replication: bool,
+    /// Do not allow the role to initiate replication.
+    #[clap(long = "no-replication", display_order = 141)]
+    no_replication: bool,
+
+    /// Set the role's password, prompting for it on the terminal. When
+    /// `PGDO_ROLE_PASSWORD` is set, its value is used instead.
+    #[clap(
+        long = "password",
+        conflicts_with_all = ["password_stdin", "no_password"],
+        display_order = 150
+    )]
+    password: bool,
+    /// Set the role's password, reading it from the first line of standard
+    /// input.
+    #[clap(
+        long = "password-stdin",
+        conflicts_with = "no_password",
+        display_order = 151
+    )]
+    password_stdin: bool,
+    /// Remove the role's password.
+    #[clap(long = "no-password", display_order = 160)]
+    no_password: bool,
+
+    /// Make the role a member of these roles.
+    #[clap(
+        long = "member-of",
+        value_name = "ROLE",
+        value_delimiter = ',',
+        display_order = 800
+    )]
+    member_of: Vec<String>,
+}
+
+/// Environment variable from which `--password` takes the password, rather
+/// than prompting for it.
+const PASSWORD_VAR: &str = "PGDO_ROLE_PASSWORD";
+
+impl AttributeArgs {
+    /// Convert into [`RoleOptions`] for the role `name`, reading its password
+    /// if one is to be set.
+    fn into_options(self, name: &str) -> miette::Result<RoleOptions> {
+        fn flag(on: bool, off: bool) -> Option<bool> {
+            match (on, off) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            }
+        }
+        Ok(RoleOptions {
+            login: flag(self.login, self.no_login),
+            superuser: flag(self.superuser, self.no_superuser),
+            create_db: flag(self.createdb, self.no_createdb),
+            create_role: flag(self.createrole, self.no_createrole),
+            inherit: None,
+            replication: flag(self.replication, self.no_replication),
+            password: if self.password {
+                Some(Password::Set(
+                    match std::env::var(PASSWORD_VAR).ok().filter(|p| !p.is_empty()) {
+                        Some(password) => password,
+                        None => prompt_for_password(name)?,
+                    },
+                ))
+            } else if self.password_stdin {
+                Some(Password::Set(read_password_from_stdin()?))
+            } else if self.no_password {
+                Some(Password::Null)
+            } else {
+                None
+            },
+            member_of: self.member_of,
+        })
+    }
+}
+
+/// Prompt for the password for role `name` on the terminal, twice.
+fn prompt_for_password(name: &str) -> miette::Result<String> {
+    let term = console::Term::stderr();
+    if !term.is_term() {
+        bail!("Cannot prompt for a password without a terminal; use --password-stdin");
+    }
+    write!(&term, "Password for role {name:?}: ").into_diagnostic()?;
+    let password = term.read_secure_line().into_diagnostic()?;
+    write!(&term, "Enter it again: ").into_diagnostic()?;
+    if term.read_secure_line().into_diagnostic()? != password {
+        bail!("Passwords do not match");
+    }
+    if password.is_empty() {
+        bail!("Password must not be empty; use --no-password to remove it");
+    }
+    Ok(password)
+}
+
+/// Read a password from the first line of standard input.
+fn read_password_from_stdin() -> miette::Result<String> {
+    let mut password = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut password)
+        .into_diagnostic()
+        .wrap_err("Could not read password from standard input")?;
+    let password = password.trim_end_matches(['\n', '\r']);
+    if password.is_empty() {
+        bail!("Password must not be empty; use --no-password to remove it");
+    }
+    Ok(password.to_owned())
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum PrivilegeArg {
+    All,
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+    References,
+    Trigger,
+    Create,
+    Connect,
+    Temporary,
+    Usage,
+}
+
+impl From<PrivilegeArg> for Privilege {
+    fn from(privilege: PrivilegeArg) -> Self {
+        match privilege {
+            PrivilegeArg::All => Privilege::All,
+            PrivilegeArg::Select => Privilege::Select,
+            PrivilegeArg::Insert => Privilege::Insert,
+            PrivilegeArg::Update => Privilege::Update,
+            PrivilegeArg::Delete => Privilege::Delete,
+            PrivilegeArg::Truncate => Privilege::Truncate,
+            PrivilegeArg::References => Privilege::References,
+            PrivilegeArg::Trigger => Privilege::Trigger,
+            PrivilegeArg::Create => Privilege::Create,
+            PrivilegeArg::Connect => Privilege::Connect,
+            PrivilegeArg::Temporary => Privilege::Temporary,
+            PrivilegeArg::Usage => Privilege::Usage,
+        }
+    }
+}
+
+/// The object on which to grant or revoke privileges; exactly one is required.
+#[derive(clap::Args)]
+#[group(required = true, multiple = false)]
+struct ObjectArgs {
+    /// A database.
+    #[clap(long = "on-database", value_name = "NAME", display_order = 300)]
+    on_database: Option<String>,
+
+    /// A schema.
+    #[clap(long = "on-schema", value_name = "NAME", display_order = 310)]
+    on_schema: Option<String>,
+
+    /// A table.
+    #[clap(long = "on-table", value_name = "NAME", display_order = 320)]
+    on_table: Option<String>,
+
+    /// All (existing) tables in a schema.
+    #[clap(
+        long = "on-all-tables-in-schema",
+        value_name = "NAME",
+        display_order = 330
+    )]
+    on_all_tables_in_schema: Option<String>,
+}
+
+impl ObjectArgs {
+    fn object(&self) -> Object<'_> {
+        match self {
+            Self { on_database: Some(name), .. } => Object::Database(name),
+            Self { on_schema: Some(name), .. } => Object::Schema(name),
+            Self { on_table: Some(name), .. } => Object::Table(name),
+            Self { on_all_tables_in_schema: Some(name), .. } => Object::AllTablesInSchema(name),
+            // clap ensures that exactly one of the above is present.
+            _ => unreachable!("no object specified"),
+        }
+    }
+}
+
+/// Run `action` against the cluster, creating and starting it as necessary.
+fn with_cluster<ACTION>(
+    cluster: args::ClusterArgs,
+    runtime: args::RuntimeArgs,
+    stop: args::StopArgs,
+    action: ACTION,
+) -> ExitResult
+where
+    ACTION: FnOnce(&pgdo::cluster::Cluster, console::Term) -> miette::Result<()>
+        + std::panic::UnwindSafe,
+{
+    runner::run(
+        runner::Runner::RunAndStop,
+        cluster,
+        args::ClusterModeArgs::default(),
+        runtime,
+        &[],
+        stop,
+        |cluster| {
+            let term = console::Term::stdout();
+            action(cluster, term).wrap_err("Managing roles failed")?;
+            Ok(ExitCode::SUCCESS)
+        },
+    )
+}
+
+/// Print roles in a table, one per line.
+fn list(cluster: &pgdo::cluster::Cluster, term: console::Term, all: bool) -> miette::Result<()> {
+    let rows: Vec<_> = roles::list(cluster)?
+        .into_iter()
+        .filter(|role| all || !role.is_predefined())
+        .map(|role| {
+            let attributes = [
+                (role.superuser, "superuser"),
+                (role.login, "login"),
+                (role.create_db, "createdb"),
+                (role.create_role, "createrole"),
+                (role.replication, "replication"),
+            ]
+            .into_iter()
+            .filter_map(|(enabled, name)| enabled.then_some(name))
+            .collect::<Vec<_>>()
+            .join(",");
+            let member_of = if role.member_of.is_empty() {
+                String::new()
+            } else {
+                format!("member of {}", role.member_of.join(","))
+            };
+            (role.name, attributes, member_of)
+        })
+        .collect();
+    let width_name = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let width_attributes = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+    for (name, attributes, member_of) in rows {
+        let line = format!("{name:width_name$}  {attributes:width_attributes$}  {member_of}");
+        writeln!(&term, "{}", line.trim_end()).into_diagnostic()?;
+    }
+    Ok(())
+}
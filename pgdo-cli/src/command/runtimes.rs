@@ -1,6 +1,12 @@
+use std::io::Write;
 use std::process::ExitCode;
 
-use pgdo::runtime::strategy::StrategyLike;
+use miette::IntoDiagnostic;
+use pgdo::runtime::{
+    health::Health,
+    strategy::{Strategy, StrategyLike},
+    Runtime, RuntimeDetails, RuntimeError,
+};
 
 use super::ExitResult;
 use crate::{args, runner};
@@ -8,38 +14,210 @@ use crate::{args, runner};
 /// List discovered PostgreSQL runtimes.
 ///
 /// The runtime shown on the line beginning with `=>` is the default, i.e. the
-/// runtime that will be used when creating a new cluster.
+/// runtime that will be used when creating a new cluster. Each runtime is shown
+/// with the source that found it, e.g. `PATH`. Set `PGDO_RUNTIME_PATH` to find
+/// runtimes in other directories.
+///
+/// Set `PGDO_RUNTIME_PREFER` to constraints in order of preference, separated
+/// by `;`, e.g. `/usr/lib/**;/usr/local/**`, and `PGDO_RUNTIME_EXCLUDE` to a
+/// constraint matching runtimes that should never be chosen. Set
+/// `PGDO_RUNTIME_VERSIONS` to `newest-minor` to prefer the newest minor release
+/// over preferred runtimes, or `exact` to insist on exact version matches.
+/// These can also be set in `~/.config/pgdo/runtime-policy.json`, e.g. `{"prefer":
+/// […], "exclude": "…", "versions": "…"}`, or another file named by
+/// `PGDO_RUNTIME_POLICY`.
 #[derive(clap::Args)]
 #[clap(next_help_heading = Some("Options for runtimes"))]
 pub struct Runtimes {
     #[clap(flatten)]
     pub runtime: args::RuntimeArgs,
+
+    /// Show details of each runtime from its `pg_config`, e.g. how it was
+    /// configured, and which extensions and modules it comes with. Exits with a
+    /// non-zero status if `pg_config` fails for any runtime.
+    #[clap(long = "verbose", display_order = 100)]
+    pub verbose: bool,
+
+    /// Check that each runtime has the binaries it needs, e.g. `initdb` and
+    /// `postgres`, and that they report the same version. Exits with a non-zero
+    /// status if any runtime is missing a required binary.
+    #[clap(long = "check", display_order = 101)]
+    pub check: bool,
+
+    /// The output format.
+    #[clap(
+        long = "format",
+        value_name = "FORMAT",
+        default_value = "text",
+        display_order = 102
+    )]
+    pub format: Format,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
 }
 
 impl Runtimes {
     pub fn invoke(self) -> ExitResult {
-        let Self { runtime } = self;
-        let strategy = runner::determine_strategy(runtime.fallback)?;
-        let mut runtimes: Vec<_> = strategy.runtimes().collect();
-        let fallback = strategy.fallback();
+        let Self { runtime, verbose, check, format } = self;
+        let fallback = runner::determine_strategy(runtime.fallback, &[])?.fallback();
+        let strategy = Strategy::default();
+        let mut runtimes: Vec<_> = strategy.runtimes_with_source().collect();
 
         // Sort by version. Higher versions will sort last.
-        runtimes.sort_by_key(|ra| ra.version);
-
-        for runtime in runtimes {
-            let default = match fallback {
-                Some(ref default) if default == &runtime => "=>",
-                _ => "",
-            };
-            println!(
-                "{default:2} {version:10} {bindir}",
-                bindir = runtime.bindir.display(),
-                version = runtime.version,
-            )
+        runtimes.sort_by_key(|(_, ra)| ra.version);
+
+        // Errors getting details are shown with the runtime concerned rather
+        // than abandoning the whole listing.
+        let rows: Vec<_> = runtimes
+            .into_iter()
+            .map(|(source, runtime)| Row {
+                source,
+                default: fallback.as_ref() == Some(&runtime),
+                details: if verbose {
+                    runtime.details().transpose()
+                } else {
+                    None
+                },
+                health: check.then(|| runtime.check()),
+                runtime,
+            })
+            .collect();
+
+        match format {
+            Format::Text => print_text(&rows),
+            Format::Json => print_json(&rows, verbose),
         }
+        .into_diagnostic()?;
+
+        let complete = rows
+            .iter()
+            .filter_map(|row| row.health.as_ref())
+            .all(Health::is_complete);
+        let detailed = rows.iter().all(|row| !matches!(row.details, Some(Err(_))));
+        Ok(if complete && detailed {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+}
 
-        Ok(ExitCode::SUCCESS)
+struct Row<'a> {
+    source: &'a str,
+    runtime: Runtime,
+    default: bool,
+    details: Option<Result<RuntimeDetails, RuntimeError>>,
+    health: Option<Health>,
+}
+
+/// Describe an error getting a runtime's details, including its sources.
+fn describe_error(err: &RuntimeError) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        message = format!("{message}: {err}");
+        source = err.source();
     }
+    message
+}
+
+/// Print runtimes in a table, one per line, with details and checks indented
+/// below.
+fn print_text(rows: &[Row]) -> std::io::Result<()> {
+    let term = console::Term::stdout();
+    let width = rows.iter().map(|row| row.source.len()).max().unwrap_or(0);
+    for Row { source, runtime, default, details, health } in rows {
+        writeln!(
+            &term,
+            "{default:2} {version:10} {source:width$}  {bindir}",
+            default = if *default { "=>" } else { "" },
+            bindir = runtime.bindir.display(),
+            version = runtime.version,
+        )?;
+        let mut fields = Vec::new();
+        if let Some(Err(err)) = details {
+            fields.push(("error", describe_error(err)));
+        }
+        if let Some(Ok(details)) = details {
+            fields.extend([
+                ("sharedir", details.sharedir.display().to_string()),
+                ("pkglibdir", details.pkglibdir.display().to_string()),
+                ("includedir", details.includedir.display().to_string()),
+                (
+                    "includedir-server",
+                    details.includedir_server.display().to_string(),
+                ),
+                ("cc", details.cc.clone()),
+                ("configure", details.configure.join(" ")),
+                ("extensions", details.extensions.join(" ")),
+                ("modules", details.modules.join(" ")),
+            ]);
+        }
+        if let Some(Health(checks)) = health {
+            fields.extend(checks.iter().map(|check| {
+                let status = match check.required {
+                    false if !check.status.is_ok() => format!("{} (optional)", check.status),
+                    _ => check.status.to_string(),
+                };
+                (check.name.as_str(), status)
+            }));
+        }
+        for (name, value) in fields {
+            writeln!(
+                &term,
+                "{:14}{name:18} {value}",
+                "",
+                name = format!("{name}:")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print runtimes as a JSON array.
+fn print_json(rows: &[Row], verbose: bool) -> std::io::Result<()> {
+    let rows: Vec<_> = rows
+        .iter()
+        .map(|Row { source, runtime, default, details, health }| {
+            let mut value = serde_json::json!({
+                "version": runtime.version.to_string(),
+                "bindir": runtime.bindir,
+                "source": source,
+                "default": default,
+            });
+            if verbose {
+                value["details"] = match details {
+                    Some(Ok(details)) => serde_json::json!(details),
+                    Some(Err(_)) | None => serde_json::Value::Null,
+                };
+                if let Some(Err(err)) = details {
+                    value["error"] = describe_error(err).into();
+                }
+            }
+            if let Some(health) = health {
+                value["complete"] = health.is_complete().into();
+                value["check"] = health
+                    .0
+                    .iter()
+                    .map(|check| {
+                        serde_json::json!({
+                            "binary": check.name,
+                            "required": check.required,
+                            "status": check.status.to_string(),
+                        })
+                    })
+                    .collect();
+            }
+            value
+        })
+        .collect();
+    let term = console::Term::stdout();
+    serde_json::to_writer_pretty(&term, &rows)?;
+    writeln!(&term)
 }
 
 impl From<Runtimes> for super::Command {
@@ -25,11 +25,14 @@ pub struct Shell {
 
     #[clap(flatten)]
     pub runtime: args::RuntimeArgs,
+
+    #[clap(flatten)]
+    pub stop: args::StopArgs,
 }
 
 impl Shell {
     pub fn invoke(self) -> ExitResult {
-        let Self { cluster, cluster_mode, database, lifecycle, runtime } = self;
+        let Self { cluster, cluster_mode, database, lifecycle, runtime, stop } = self;
         runner::run(
             if lifecycle.destroy {
                 Runner::RunAndDestroy
@@ -39,6 +42,8 @@ impl Shell {
             cluster,
             cluster_mode,
             runtime,
+            &["psql"],
+            stop,
             |cluster| {
                 runner::ensure_database(cluster, &database.name)?;
                 runner::check_exit(
@@ -0,0 +1,119 @@
+use std::{io::Write, process::ExitCode};
+
+use miette::IntoDiagnostic;
+
+use super::ExitResult;
+use crate::args;
+
+use pgdo::{
+    cluster::{pidfile::PidFile, Cluster, ClusterStatus},
+    runtime::strategy::Strategy,
+};
+
+/// Show whether a cluster is running, and what it's doing.
+///
+/// This does not start or lock the cluster. Exits with status 0 when the
+/// server is running – including when it is starting up or shutting down – 3
+/// when it is not running, and 4 when there is no cluster, like `pg_ctl
+/// status`.
+#[derive(clap::Args)]
+#[clap(next_help_heading = Some("Options for status"))]
+pub struct Status {
+    #[clap(flatten)]
+    pub cluster: args::ClusterArgs,
+}
+
+impl Status {
+    pub fn invoke(self) -> ExitResult {
+        let Self { cluster: args::ClusterArgs { dir } } = self;
+        let cluster = Cluster::new(&dir, Strategy::default())?;
+        let status = cluster.status()?;
+        let pidfile = match status {
+            ClusterStatus::Missing | ClusterStatus::Stopped => None,
+            _ => match cluster.read_pidfile() {
+                Ok(pidfile) => pidfile,
+                // The file is being written, or was left incomplete by a crash.
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => None,
+                Err(err) => Err(err).into_diagnostic()?,
+            },
+        };
+
+        let term = console::Term::stdout();
+        writeln!(&term, "Cluster in {} is {status}.", dir.display()).into_diagnostic()?;
+        if let Some(pidfile) = &pidfile {
+            print_pidfile(pidfile).into_diagnostic()?;
+        }
+        if let Some(guidance) = guidance(&status, pidfile.as_ref()) {
+            writeln!(&term, "{guidance}").into_diagnostic()?;
+        }
+
+        Ok(match status {
+            status if status.is_running() => ExitCode::SUCCESS,
+            ClusterStatus::Missing => ExitCode::from(4),
+            _ => ExitCode::from(3),
+        })
+    }
+}
+
+/// Print the interesting parts of `postmaster.pid`.
+fn print_pidfile(pidfile: &PidFile) -> std::io::Result<()> {
+    let mut fields = vec![("pid", pidfile.pid.to_string())];
+    if let Some(start_time) = pidfile.start_time {
+        fields.push(("started", start_time.to_string()));
+    }
+    if let Some(port) = pidfile.port {
+        fields.push(("port", port.to_string()));
+    }
+    if let Some(socket_dir) = &pidfile.socket_dir {
+        fields.push(("socket dir", socket_dir.display().to_string()));
+    }
+    if let Some(listen_address) = &pidfile.listen_address {
+        fields.push(("listening on", listen_address.clone()));
+    }
+    let term = console::Term::stdout();
+    for (name, value) in fields {
+        writeln!(&term, "  {name:13} {value}", name = format!("{name}:"))?;
+    }
+    Ok(())
+}
+
+/// What, if anything, should the user do next?
+fn guidance(status: &ClusterStatus, pidfile: Option<&PidFile>) -> Option<String> {
+    match status {
+        ClusterStatus::InRecovery => Some(
+            "The cluster is a standby, or is recovering from a backup; use `pgdo promote` to \
+             promote it."
+                .into(),
+        ),
+        ClusterStatus::Starting => Some(
+            "The cluster is starting; check `postmaster.log` in the cluster directory if this \
+             takes a while."
+                .into(),
+        ),
+        ClusterStatus::CrashRecovery => Some(
+            "The cluster did not shut down cleanly and is performing crash recovery, which can \
+             take a while; check `postmaster.log` in the cluster directory for progress."
+                .into(),
+        ),
+        ClusterStatus::ShuttingDown => Some(
+            "The cluster is waiting for sessions to end or for a checkpoint to complete.".into(),
+        ),
+        ClusterStatus::StalePidFile => Some(format!(
+            "The server{} is no longer running, but did not shut down cleanly; it may have \
+             crashed or been killed. It will perform crash recovery when next started.",
+            pidfile
+                .map(|pidfile| format!(" (pid {})", pidfile.pid))
+                .unwrap_or_default(),
+        )),
+        ClusterStatus::Missing => {
+            Some("There is no cluster here; it will be created when first used.".into())
+        }
+        ClusterStatus::Running | ClusterStatus::Stopped => None,
+    }
+}
+
+impl From<Status> for super::Command {
+    fn from(status: Status) -> Self {
+        Self::Status(status)
+    }
+}
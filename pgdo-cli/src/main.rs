@@ -42,3 +42,13 @@ struct Options {
     #[clap(flatten)]
     default: command::Default,
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    #[test]
+    fn options_are_valid() {
+        super::Options::command().debug_assert();
+    }
+}
@@ -4,6 +4,7 @@ use std::os::unix::prelude::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use miette::{bail, IntoDiagnostic, Result, WrapErr};
 
@@ -30,14 +31,31 @@ pub(crate) fn check_exit(status: ExitStatus) -> ExitResult {
 
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 pub(crate) enum StrategyError {
-    #[error("No runtime matches constraint {0:?}")]
+    #[error("No runtime matches constraint `{0}`")]
     #[diagnostic(help("Use `runtimes` to see available runtimes"))]
     ConstraintNotSatisfied(runtime::constraint::Constraint),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicyError(#[from] runtime::strategy::PolicyError),
 }
 
 /// Determine the strategy to use for a cluster, given an optional constraint.
-pub(crate) fn determine_strategy(fallback: Option<Constraint>) -> Result<Strategy, StrategyError> {
-    let strategy = runtime::strategy::Strategy::default();
+///
+/// Runtimes that are missing binaries needed to run a cluster, or any of the
+/// other `binaries` needed by the operation at hand, e.g. `pg_basebackup`, are
+/// skipped. Runtimes are chosen according to the selection policy in the
+/// environment, if there is one. See
+/// [`runtime::strategy::SelectionPolicy::from_env`].
+pub(crate) fn determine_strategy(
+    fallback: Option<Constraint>,
+    binaries: &[&str],
+) -> Result<Strategy, StrategyError> {
+    let required = runtime::health::REQUIRED.iter().chain(binaries);
+    let strategy = Strategy::default().requiring(required.copied());
+    let strategy = match runtime::strategy::SelectionPolicy::from_env()? {
+        Some(policy) => strategy.with_policy(policy),
+        None => strategy,
+    };
     let fallback: Option<_> = match fallback {
         Some(constraint) => match strategy.select(&constraint) {
             Some(runtime) => Some(runtime),
@@ -54,8 +72,15 @@ pub(crate) fn determine_strategy(fallback: Option<Constraint>) -> Result<Strateg
 
 /// Ensure that a given named database exists in a cluster.
 ///
-/// The cluster should be running.
+/// The cluster should be running. Databases cannot be created in a standby,
+/// e.g. a replica, so this does nothing if the cluster is in recovery.
 pub(crate) fn ensure_database(cluster: &cluster::Cluster, database_name: &str) -> Result<()> {
+    if cluster
+        .in_recovery()
+        .wrap_err("Could not determine if cluster is in recovery")?
+    {
+        return Ok(());
+    }
     cluster
         .createdb(database_name)
         .wrap_err_with(|| "Could not create database")
@@ -99,12 +124,15 @@ pub(crate) enum Runner {
 ///
 /// This is the main entry point for most `pgdo` commands (though not all). It
 /// takes care of creating, locking, starting, stopping, and destroying the
-/// cluster, and running the given action.
+/// cluster, and running the given action. The cluster's runtime must have the
+/// given `binaries`, as well as those needed to run the cluster.
 pub(crate) fn run<ACTION>(
     runner: Runner,
     args::ClusterArgs { dir: cluster_dir }: args::ClusterArgs,
     args::ClusterModeArgs { mode: cluster_mode }: args::ClusterModeArgs,
-    args::RuntimeArgs { fallback }: args::RuntimeArgs,
+    args::RuntimeArgs { fallback, strict }: args::RuntimeArgs,
+    binaries: &[&str],
+    stop: args::StopArgs,
     action: ACTION,
 ) -> ExitResult
 where
@@ -129,8 +157,9 @@ where
     };
 
     let (datadir, lock) = lock_for(&cluster_dir)?;
-    let strategy = determine_strategy(fallback)?;
-    let cluster = cluster::Cluster::new(datadir, strategy)?;
+    let strategy = determine_strategy(fallback, binaries)?;
+    let mut cluster = cluster::Cluster::new(datadir, strategy)?;
+    cluster.strict_runtime = strict;
 
     let act = || {
         if let Some(cluster_mode) = cluster_mode {
@@ -138,25 +167,69 @@ where
             rt.block_on(set_cluster_mode(cluster_mode, &cluster))?;
         }
 
-        // Ignore SIGINT, TERM, and HUP (with ctrlc feature "termination"). The
-        // child process will receive the signal, presumably terminate, then
-        // we'll tidy up.
-        ctrlc::set_handler(|| ())
+        // The child process will receive the signal, presumably terminate,
+        // then we'll tidy up.
+        handle_interrupts()
             .into_diagnostic()
             .context("Could not set signal handler")?;
 
         // Finally, run the given action.
-        action(&cluster)
+        ignoring_interrupts(|| action(&cluster))
     };
 
     use coordinate::{run_and_destroy, run_and_stop, run_and_stop_if_exists};
     match runner {
-        Runner::RunAndStop => run_and_stop(&cluster, &[], lock, act),
-        Runner::RunAndStopIfExists => run_and_stop_if_exists(&cluster, &[], lock, act),
+        Runner::RunAndStop => run_and_stop(&cluster, &[], stop.into(), lock, act),
+        Runner::RunAndStopIfExists => run_and_stop_if_exists(&cluster, &[], stop.into(), lock, act),
         Runner::RunAndDestroy => run_and_destroy(&cluster, &[], lock, act),
     }?
 }
 
+/// Set by the signal handler when interrupted, except while ignoring interrupts.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set while running a child process that should receive interrupts instead.
+static IGNORING_INTERRUPTS: AtomicBool = AtomicBool::new(false);
+
+/// Handle SIGINT, TERM, and HUP (with ctrlc feature "termination"): rather than
+/// terminating, note the interrupt so that long-running operations can check
+/// [`interrupted`] and tidy up. The handler may already be in place if an
+/// earlier action was run in this process.
+pub(crate) fn handle_interrupts() -> Result<(), ctrlc::Error> {
+    let handler = || {
+        if !IGNORING_INTERRUPTS.load(Ordering::SeqCst) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+    };
+    match ctrlc::set_handler(handler) {
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Have we been interrupted since calling [`handle_interrupts`]?
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Ignore interrupts while calling `f`, e.g. to run a child process in the
+/// foreground that should receive them, like `psql`.
+pub(crate) fn ignoring_interrupts<T>(f: impl FnOnce() -> T) -> T {
+    let ignoring = IGNORING_INTERRUPTS.swap(true, Ordering::SeqCst);
+    let result = f();
+    IGNORING_INTERRUPTS.store(ignoring, Ordering::SeqCst);
+    result
+}
+
+/// Heed interrupts while calling `f`, even within [`ignoring_interrupts`], e.g.
+/// for an action passed to [`run`] that waits and checks [`interrupted`].
+pub(crate) fn heeding_interrupts<T>(f: impl FnOnce() -> T) -> T {
+    let ignoring = IGNORING_INTERRUPTS.swap(false, Ordering::SeqCst);
+    let result = f();
+    IGNORING_INTERRUPTS.store(ignoring, Ordering::SeqCst);
+    result
+}
+
 /// Set the cluster's "mode", i.e. configure appropriate PostgreSQL settings,
 /// e.g. `fsync`, `full_page_writes`, etc. that need to be set early.
 async fn set_cluster_mode(
@@ -190,3 +263,62 @@ async fn set_cluster_mode(
         }
     }
 }
+
+pub(crate) static ARCHIVE_MODE: cluster::config::Parameter =
+    cluster::config::Parameter("archive_mode");
+pub(crate) static ARCHIVE_COMMAND: cluster::config::Parameter =
+    cluster::config::Parameter("archive_command");
+pub(crate) static ARCHIVE_LIBRARY: cluster::config::Parameter =
+    cluster::config::Parameter("archive_library");
+
+/// Reset archiving settings in a cluster, e.g. one that has been restored or
+/// cloned, so that it does not write into the backup of the cluster from which
+/// it came. The cluster should be running; restart it to apply the changes.
+pub(crate) async fn reset_archiving(
+    mut term: &console::Term,
+    pool: &cluster::sqlx::PgPool,
+) -> Result<(), cluster::ClusterError> {
+    use std::io::Write;
+
+    write!(term, "Resetting {ARCHIVE_MODE}…")?;
+    ARCHIVE_MODE.reset(pool).await?;
+    writeln!(term, " done.")?;
+
+    write!(term, "Resetting {ARCHIVE_COMMAND}…")?;
+    ARCHIVE_COMMAND.reset(pool).await?;
+    writeln!(term, " done.")?;
+
+    write!(term, "Resetting {ARCHIVE_LIBRARY}…")?;
+    match ARCHIVE_LIBRARY.reset(pool).await {
+        Ok(_) => writeln!(term, " done.")?,
+        Err(err) => {
+            match err.as_database_error() {
+                // 42704 means UNDEFINED_OBJECT, i.e. this parameter is not
+                // supported in this version of PostgreSQL.
+                Some(err) if err.code() == Some("42704".into()) => {
+                    writeln!(term, " not supported.")?;
+                    Ok(())
+                }
+                _ => Err(err),
+            }?;
+        }
+    };
+
+    Ok(())
+}
+
+/// A cluster's Unix socket lives in its data directory, so `pg_basebackup`
+/// copies the socket's lock file, e.g. `.s.PGSQL.5432.lock`, into a clone or
+/// replica. This names the source's postmaster, so the copy would refuse to
+/// start. Remove them.
+pub(crate) fn remove_socket_lock_files(datadir: &Path) -> io::Result<()> {
+    for entry in datadir.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.as_encoded_bytes();
+        if name.starts_with(b".s.PGSQL.") && name.ends_with(b".lock") {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
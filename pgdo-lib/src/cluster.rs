@@ -2,22 +2,35 @@
 
 pub mod backup;
 pub mod config;
+pub mod control;
+pub mod extension;
+pub mod metadata;
+pub mod pidfile;
 pub mod resource;
+pub mod roles;
 
+#[cfg(feature = "async")]
+mod asynchronous;
+mod connection;
 mod error;
+mod stop;
 
+use std::collections::HashSet;
 use std::ffi::{OsStr, OsString};
-use std::io::{self, Read, Write};
+use std::io;
 use std::os::unix::prelude::{OsStrExt, OsStringExt};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus, Output};
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
 use std::{fmt, fs};
 
-use postgres;
+pub use postgres;
 use shell_quote::{QuoteExt, Sh};
 pub use sqlx;
+pub use tokio_postgres;
 
 use crate::runtime::{
+    constraint::Constraint,
     strategy::{Strategy, StrategyLike},
     Runtime,
 };
@@ -28,7 +41,12 @@ use crate::{
     },
     version,
 };
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncCluster;
+pub use connection::{ConnectionBuilder, APPLICATION_NAME};
 pub use error::ClusterError;
+use stop::ShutdownAction;
+pub use stop::{ShutdownMode, StopOptions};
 
 /// `template0` is always present in a PostgreSQL cluster.
 ///
@@ -72,18 +90,69 @@ pub static DATABASE_TEMPLATE1: &str = "template1";
 /// possible.
 pub static DATABASE_POSTGRES: &str = "postgres";
 
+/// How long [`Cluster::reload`] waits for the server to reload its
+/// configuration files.
+pub const RELOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// When this session last loaded the configuration files.
+const CONF_LOAD_TIME_SQL: &str = "SELECT pg_conf_load_time()::text";
+
+/// The names of settings that need a restart to take effect.
+const PENDING_RESTART_SQL: &str =
+    "SELECT name FROM pg_catalog.pg_settings WHERE pending_restart ORDER BY name";
+
+/// The status of a cluster; see [`Cluster::status`].
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ClusterStatus {
+    /// The server is running and accepting connections.
     Running,
+    /// The server is running in recovery, e.g. as a standby, or while
+    /// restoring from a backup.
+    InRecovery,
+    /// The server is starting up.
+    Starting,
+    /// The server is starting up and performing crash recovery, after it
+    /// crashed or was killed. This can take a while. Before PostgreSQL 10 this
+    /// is reported as [`Running`][`Self::Running`].
+    CrashRecovery,
+    /// The server is shutting down.
+    ShuttingDown,
+    /// The server is not running.
     Stopped,
+    /// The server is not running, but its `postmaster.pid` file remains, naming
+    /// a process that has exited or is not the server, or left unreadable for
+    /// longer than [`pidfile::WRITE_GRACE_PERIOD`]. The server probably crashed
+    /// or was killed; see [`Cluster::remove_stale_pidfile`].
+    StalePidFile,
+    /// The data directory does not exist, or is not a cluster.
     Missing,
 }
 
+impl ClusterStatus {
+    /// Is the server process alive? This is `true` when starting up and
+    /// shutting down too.
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Self::Running
+                | Self::InRecovery
+                | Self::Starting
+                | Self::CrashRecovery
+                | Self::ShuttingDown
+        )
+    }
+}
+
 impl fmt::Display for ClusterStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ClusterStatus::Running => write!(f, "running"),
+            ClusterStatus::InRecovery => write!(f, "running (in recovery)"),
+            ClusterStatus::Starting => write!(f, "starting"),
+            ClusterStatus::CrashRecovery => write!(f, "starting (crash recovery)"),
+            ClusterStatus::ShuttingDown => write!(f, "shutting down"),
             ClusterStatus::Stopped => write!(f, "stopped"),
+            ClusterStatus::StalePidFile => write!(f, "stopped (stale postmaster.pid)"),
             ClusterStatus::Missing => write!(f, "missing"),
         }
     }
@@ -104,6 +173,9 @@ pub struct Cluster {
     pub datadir: PathBuf,
     /// How to select the PostgreSQL installation to use with this cluster.
     pub strategy: Strategy,
+    /// Fail, rather than warn, when the runtime that created this cluster has
+    /// disappeared or changed. See [`Cluster::runtime`].
+    pub strict_runtime: bool,
 }
 
 impl Cluster {
@@ -115,139 +187,378 @@ impl Cluster {
         Ok(Self {
             datadir: datadir.as_ref().to_owned(),
             strategy: strategy.into(),
+            strict_runtime: false,
         })
     }
 
     /// Determine the runtime to use with this cluster.
-    fn runtime(&self) -> Result<Runtime, ClusterError> {
+    ///
+    /// When the cluster exists this selects a runtime compatible with the
+    /// cluster's version, preferring the runtime that created it, as recorded
+    /// in its [metadata][`metadata::Metadata`]. Both go through the strategy,
+    /// so its policies apply, e.g. exclusions or required binaries. If the
+    /// pinned runtime has disappeared or changed, or the strategy will not
+    /// select it, this logs a warning – or, when the runtime has disappeared
+    /// or changed and [`strict_runtime`][`Self::strict_runtime`] is set,
+    /// returns an error – then selects another. When the cluster does not
+    /// exist it uses the strategy's fallback.
+    pub fn runtime(&self) -> Result<Runtime, ClusterError> {
         match version(self)? {
             None => self
                 .strategy
                 .fallback()
                 .ok_or_else(|| ClusterError::RuntimeDefaultNotFound),
-            Some(version) => self
-                .strategy
-                .select(&version.into())
-                .ok_or_else(|| ClusterError::RuntimeNotFound(version)),
+            Some(version) => match self.runtime_pinned(version)? {
+                Some(runtime) => Ok(runtime),
+                None => self
+                    .strategy
+                    .select(&version.into())
+                    .ok_or_else(|| ClusterError::RuntimeNotFound(version)),
+            },
+        }
+    }
+
+    /// The runtime that created this cluster, if known and the strategy will
+    /// select it.
+    fn runtime_pinned(
+        &self,
+        version: version::PartialVersion,
+    ) -> Result<Option<Runtime>, ClusterError> {
+        let Some(pin) = metadata::Metadata::read(&self.datadir)?.and_then(|meta| meta.runtime)
+        else {
+            return Ok(None);
+        };
+        let pinned = pin
+            .bindir
+            .to_str()
+            .and_then(|bindir| Constraint::path(&globset::escape(bindir)).ok())
+            .and_then(|bindir| {
+                self.strategy
+                    .select(&(Constraint::Version(version) & bindir))
+            });
+        if let Some(runtime) = pinned {
+            return if runtime.version == pin.version {
+                Ok(Some(runtime))
+            } else if self.strict_runtime {
+                Err(ClusterError::RuntimePinChanged {
+                    bindir: pin.bindir,
+                    pinned: pin.version,
+                    found: runtime.version,
+                })
+            } else {
+                self.warn_once(format!(
+                    "Runtime at {} has changed from {} to {}; using it anyway",
+                    pin.bindir.display(),
+                    pin.version,
+                    runtime.version,
+                ));
+                Ok(Some(runtime))
+            };
+        }
+        // The strategy won't select the pinned runtime. Find out why.
+        match Runtime::new(&pin.bindir) {
+            Ok(runtime) if runtime.version != pin.version && self.strict_runtime => {
+                Err(ClusterError::RuntimePinChanged {
+                    bindir: pin.bindir,
+                    pinned: pin.version,
+                    found: runtime.version,
+                })
+            }
+            Err(_) if self.strict_runtime => {
+                Err(ClusterError::RuntimePinMissing { bindir: pin.bindir, pinned: pin.version })
+            }
+            Ok(runtime) if !version.compatible(runtime.version) => {
+                self.warn_once(format!(
+                    "Runtime at {} has changed from {} to {}; selecting another runtime",
+                    pin.bindir.display(),
+                    pin.version,
+                    runtime.version,
+                ));
+                Ok(None)
+            }
+            Ok(_) => {
+                self.warn_once(format!(
+                    "Runtime {} at {} is not selectable, e.g. it is excluded by the runtime \
+                     selection policy or is missing binaries; selecting another runtime",
+                    pin.version,
+                    pin.bindir.display(),
+                ));
+                Ok(None)
+            }
+            Err(_) => {
+                self.warn_once(format!(
+                    "Runtime {} at {} has disappeared; selecting another runtime",
+                    pin.version,
+                    pin.bindir.display(),
+                ));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Log a warning about this cluster, but only once per process.
+    /// [`Self::runtime`] is called often, and repeating warnings is noisy.
+    fn warn_once(&self, message: String) {
+        static WARNED: std::sync::LazyLock<std::sync::Mutex<HashSet<(PathBuf, String)>>> =
+            std::sync::LazyLock::new(Default::default);
+        let key = (self.datadir.clone(), message);
+        if let Ok(mut warned) = WARNED.lock() {
+            if !warned.contains(&key) {
+                log::warn!("Cluster {}: {}", key.0.display(), key.1);
+                warned.insert(key);
+            }
         }
     }
 
-    /// Return a [`Command`] that will invoke `pg_ctl` with the environment
-    /// referring to this cluster.
-    fn ctl(&self) -> Result<Command, ClusterError> {
-        let mut command = self.runtime()?.execute("pg_ctl");
+    /// Record `runtime` in this cluster's metadata as the runtime to prefer
+    /// when selecting a runtime for this cluster, e.g. after upgrading
+    /// PostgreSQL in place. This is done automatically when creating a
+    /// cluster.
+    pub fn pin_runtime(&self, runtime: &Runtime) -> Result<(), ClusterError> {
+        let mut metadata = metadata::Metadata::read(&self.datadir)?.unwrap_or_default();
+        metadata.runtime = Some(runtime.into());
+        metadata.write(&self.datadir)
+    }
+
+    /// Return a [`Command`] that will invoke `pg_ctl` from `runtime` with the
+    /// environment referring to this cluster.
+    fn ctl(&self, runtime: &Runtime) -> Command {
+        let mut command = runtime.execute("pg_ctl");
         command.env("PGDATA", &self.datadir);
         command.env("PGHOST", &self.datadir);
-        Ok(command)
+        command
     }
 
     /// Check if this cluster is running.
     ///
-    /// Convenient call-through to [`status`][`Self::status`]; only returns
-    /// `true` when the cluster is definitely running.
+    /// Convenient call-through to [`status`][`Self::status`]; returns `true`
+    /// when the server process is alive, i.e. [`ClusterStatus::is_running`].
     pub fn running(&self) -> Result<bool, ClusterError> {
-        self.status().map(|status| status == ClusterStatus::Running)
+        self.status().map(|status| status.is_running())
     }
 
     /// Check the status of this cluster.
     ///
-    /// Tries to distinguish carefully between "definitely running", "definitely
-    /// not running", "missing", and "don't know". The latter results in
-    /// [`ClusterError`].
+    /// This reads the cluster's `postmaster.pid` file – see
+    /// [`read_pidfile`][`Self::read_pidfile`] – and checks that the process it
+    /// names is the server for this cluster. From PostgreSQL 10 the file also
+    /// records whether the server is starting, ready, in recovery, or shutting
+    /// down.
     pub fn status(&self) -> Result<ClusterStatus, ClusterError> {
-        let output = self.ctl()?.arg("status").output()?;
-        let code = match output.status.code() {
-            // Killed by signal; return early.
-            None => return Err(ClusterError::CommandError(output)),
-            // Success; return early (the server is running).
-            Some(0) => return Ok(ClusterStatus::Running),
-            // More work required to decode what this means.
-            Some(code) => code,
+        if exists(self) {
+            self.decode_status(self.read_pidfile())
+        } else {
+            Ok(ClusterStatus::Missing)
+        }
+    }
+
+    /// Decode the result of reading `postmaster.pid` into a [`ClusterStatus`].
+    fn decode_status(
+        &self,
+        pidfile: io::Result<Option<pidfile::PidFile>>,
+    ) -> Result<ClusterStatus, ClusterError> {
+        let pidfile = match pidfile {
+            Ok(Some(pidfile)) => pidfile,
+            Ok(None) => return Ok(ClusterStatus::Stopped),
+            // The server creates the file then writes its contents, so there's
+            // a brief moment during which it is empty. Left like that for
+            // longer, the server must have crashed while writing it.
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                return Ok(if self.pidfile_being_written()? {
+                    ClusterStatus::Starting
+                } else {
+                    ClusterStatus::StalePidFile
+                });
+            }
+            Err(err) => return Err(err)?,
         };
-        let runtime = self.runtime()?;
-        // PostgreSQL has evolved to return different error codes in
-        // later versions, so here we check for specific codes to avoid
-        // masking errors from insufficient permissions or missing
-        // executables, for example.
-        let status = match runtime.version {
-            // PostgreSQL 10.x and later.
-            version::Version::Post10(_major, _minor) => {
-                // PostgreSQL 10
-                // https://www.postgresql.org/docs/10/static/app-pg-ctl.html
-                match code {
-                    // 3 means that the data directory is present and
-                    // accessible but that the server is not running.
-                    3 => Some(ClusterStatus::Stopped),
-                    // 4 means that the data directory is not present or is
-                    // not accessible. If it's missing, then the server is
-                    // not running. If it is present but not accessible
-                    // then crash because we can't know if the server is
-                    // running or not.
-                    4 if !exists(self) => Some(ClusterStatus::Missing),
-                    // For anything else we don't know.
-                    _ => None,
-                }
+        if !pidfile.is_server_for(&self.datadir) {
+            return Ok(ClusterStatus::StalePidFile);
+        }
+        Ok(match pidfile.status {
+            Some(pidfile::PostmasterStatus::Starting) if self.in_crash_recovery() => {
+                ClusterStatus::CrashRecovery
             }
-            // PostgreSQL 9.x only.
-            version::Version::Pre10(9, point, _minor) => {
-                // PostgreSQL 9.4+
-                // https://www.postgresql.org/docs/9.4/static/app-pg-ctl.html
-                // https://www.postgresql.org/docs/9.5/static/app-pg-ctl.html
-                // https://www.postgresql.org/docs/9.6/static/app-pg-ctl.html
-                if point >= 4 {
-                    match code {
-                        // 3 means that the data directory is present and
-                        // accessible but that the server is not running.
-                        3 => Some(ClusterStatus::Stopped),
-                        // 4 means that the data directory is not present or is
-                        // not accessible. If it's missing, then the server is
-                        // not running. If it is present but not accessible
-                        // then crash because we can't know if the server is
-                        // running or not.
-                        4 if !exists(self) => Some(ClusterStatus::Missing),
-                        // For anything else we don't know.
-                        _ => None,
-                    }
+            Some(pidfile::PostmasterStatus::Starting) => ClusterStatus::Starting,
+            Some(pidfile::PostmasterStatus::Stopping) => ClusterStatus::ShuttingDown,
+            Some(pidfile::PostmasterStatus::Standby) => ClusterStatus::InRecovery,
+            // A hot standby is "ready" too, so look for the files that put the
+            // server into recovery; these are removed when recovery ends.
+            _ if self.recovery_requested() => ClusterStatus::InRecovery,
+            // Before PostgreSQL 10 there is no status line.
+            Some(pidfile::PostmasterStatus::Ready | pidfile::PostmasterStatus::Other(_)) | None => {
+                ClusterStatus::Running
+            }
+        })
+    }
+
+    /// Does the data directory contain `standby.signal` or `recovery.signal`,
+    /// or, before PostgreSQL 12, `recovery.conf`?
+    fn recovery_requested(&self) -> bool {
+        ["standby.signal", "recovery.signal", "recovery.conf"]
+            .iter()
+            .any(|name| self.datadir.join(name).exists())
+    }
+
+    /// Read this cluster's `postmaster.pid` file. Returns [`None`] if the file
+    /// does not exist, i.e. the server is not running.
+    pub fn read_pidfile(&self) -> io::Result<Option<pidfile::PidFile>> {
+        pidfile::PidFile::read(self.pidfile())
+    }
+
+    /// Was this cluster's `postmaster.pid` file modified so recently that a
+    /// server may still be writing it? See [`pidfile::WRITE_GRACE_PERIOD`].
+    fn pidfile_being_written(&self) -> io::Result<bool> {
+        match fs::metadata(self.pidfile()) {
+            Ok(metadata) => Ok(pidfile::within_grace_period(metadata.modified()?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Remove a stale `postmaster.pid` file, i.e. one left behind by a server
+    /// that crashed or was killed. Returns [`Modified`] if a file was removed.
+    ///
+    /// The file is removed only if the process it names is not the server for
+    /// this cluster, and no processes remain attached to the server's shared
+    /// memory, or if it cannot be parsed and has not been modified within
+    /// [`pidfile::WRITE_GRACE_PERIOD`]. PostgreSQL itself cleans up after a
+    /// crash in many cases, but refuses to start when the PID has since been
+    /// reused, e.g. after a reboot, or when the file is empty.
+    ///
+    /// **Note** that this must only be called while holding an exclusive lock
+    /// on the cluster. [`start`][`Self::start`] calls this, and the functions
+    /// in [`coordinate`] only start the cluster while holding such a lock.
+    pub fn remove_stale_pidfile(&self) -> Result<State, ClusterError> {
+        let pidfile = match self.read_pidfile() {
+            Ok(Some(pidfile)) if !pidfile.is_server_for(&self.datadir) => {
+                if pidfile.shmem_in_use() {
+                    log::warn!(
+                        "Cluster {}: server (PID {}) has exited but its shared memory is still \
+                         in use; leaving postmaster.pid in place",
+                        self.datadir.display(),
+                        pidfile.pid,
+                    );
+                    return Ok(Unmodified);
                 }
-                // PostgreSQL 9.2+
-                // https://www.postgresql.org/docs/9.2/static/app-pg-ctl.html
-                // https://www.postgresql.org/docs/9.3/static/app-pg-ctl.html
-                else if point >= 2 {
-                    match code {
-                        // 3 means that the data directory is present and
-                        // accessible but that the server is not running OR
-                        // that the data directory is not present.
-                        3 if !exists(self) => Some(ClusterStatus::Missing),
-                        3 => Some(ClusterStatus::Stopped),
-                        // For anything else we don't know.
-                        _ => None,
-                    }
+                Some(pidfile)
+            }
+            Ok(_) => return Ok(Unmodified),
+            // An incomplete file may be being written by a server now. If not,
+            // the server crashed while writing it.
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                if self.pidfile_being_written()? {
+                    return Ok(Unmodified);
                 }
-                // PostgreSQL 9.0+
-                // https://www.postgresql.org/docs/9.0/static/app-pg-ctl.html
-                // https://www.postgresql.org/docs/9.1/static/app-pg-ctl.html
-                else {
-                    match code {
-                        // 1 means that the server is not running OR the data
-                        // directory is not present OR that the data directory
-                        // is not accessible.
-                        1 if !exists(self) => Some(ClusterStatus::Missing),
-                        1 => Some(ClusterStatus::Stopped),
-                        // For anything else we don't know.
-                        _ => None,
+                None
+            }
+            Err(err) => return Err(err)?,
+        };
+        match fs::remove_file(self.pidfile()) {
+            Ok(()) => {
+                match pidfile {
+                    Some(pidfile) => {
+                        log::warn!(
+                            "Cluster {}: removed stale postmaster.pid left by server (PID {}), \
+                             which did not shut down cleanly",
+                            self.datadir.display(),
+                            pidfile.pid,
+                        );
+                        match &pidfile.socket_dir {
+                            Some(socket_dir) if socket_dir != &self.datadir => {
+                                self.remove_stale_socket_lockfiles(socket_dir)?;
+                            }
+                            _ => (),
+                        }
                     }
+                    None => log::warn!(
+                        "Cluster {}: removed unreadable postmaster.pid left by a server that did \
+                         not shut down cleanly",
+                        self.datadir.display(),
+                    ),
                 }
+                // This is where `start` puts the server's socket.
+                self.remove_stale_socket_lockfiles(&self.datadir)?;
+                Ok(Modified)
             }
-            // All other versions.
-            version::Version::Pre10(_major, _point, _minor) => None,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Unmodified),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Remove lock files for Unix sockets in `socket_dir` left behind by this
+    /// cluster's server when it crashed. The server refuses to start while one
+    /// of these names a process that exists, e.g. when the PID has been
+    /// reused. Lock files belonging to other clusters are left alone.
+    fn remove_stale_socket_lockfiles(&self, socket_dir: &Path) -> io::Result<()> {
+        let datadir = self.datadir.canonicalize()?;
+        let entries = match fs::read_dir(socket_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
         };
+        for entry in entries {
+            let path = entry?.path();
+            let is_lockfile = path.extension() == Some(OsStr::new("lock"))
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.as_bytes().starts_with(b".s.PGSQL."));
+            if !is_lockfile {
+                continue;
+            }
+            // A socket lock file has the same layout as `postmaster.pid`.
+            let stale = match pidfile::PidFile::read(&path) {
+                Ok(Some(lock)) => {
+                    lock.datadir.canonicalize().is_ok_and(|dir| dir == datadir)
+                        && !lock.is_server_for(&datadir)
+                }
+                Ok(None) => false,
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => false,
+                Err(err) => return Err(err),
+            };
+            if stale {
+                match fs::remove_file(&path) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => log::warn!(
+                        "Cluster {}: removed stale socket lock file {}",
+                        self.datadir.display(),
+                        path.display(),
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
 
-        match status {
-            Some(running) => Ok(running),
-            // TODO: Perhaps include the exit code from `pg_ctl status` in the
-            // error message, and whatever it printed out.
-            None => Err(ClusterError::UnsupportedVersion(runtime.version)),
+    /// Prepare to start the server: remove a stale `postmaster.pid` file, and
+    /// say so if the server is going to perform crash recovery. This can take a
+    /// while, and `pg_ctl start` gives no indication of it. A restored base
+    /// backup also looks like it needs crash recovery, but it is recovered
+    /// from its `backup_label` or archived WAL instead.
+    fn prepare_start(&self) -> Result<(), ClusterError> {
+        self.remove_stale_pidfile()?;
+        if self.recovery_requested() || self.datadir.join("backup_label").exists() {
+            return Ok(());
+        }
+        if let Ok(Some(state)) = control::DatabaseState::read(&self.datadir) {
+            if state.needs_crash_recovery() {
+                log::warn!(
+                    "Cluster {}: server did not shut down cleanly; performing crash recovery \
+                     before accepting connections, which may take a while",
+                    self.datadir.display(),
+                );
+            }
         }
+        Ok(())
+    }
+
+    /// Is the server performing crash recovery, according to the cluster's
+    /// control file?
+    fn in_crash_recovery(&self) -> bool {
+        matches!(
+            control::DatabaseState::read(&self.datadir),
+            Ok(Some(control::DatabaseState::InCrashRecovery))
+        )
     }
 
     /// Return the path to the PID file used in this cluster.
@@ -272,29 +583,41 @@ impl Cluster {
         } else {
             // Create the cluster and report back that we did so.
             fs::create_dir_all(&self.datadir)?;
-
-            // Construct the `pg_ctl init` command.
-            let mut command = self.ctl()?;
-            #[allow(clippy::suspicious_command_arg_space)]
-            command
-                .arg("init")
-                // Silent; `--silent` flag accepted only in PostgreSQL >=9.2.
-                .arg("-s")
-                // Options for `initdb`; `--options` flag accepted only in PostgreSQL >=10.
-                .arg("-o")
-                // Passing multiple flags in a single `arg(...)` is intentional.
-                // These constitute the single value for the `-o` flag above.
-                .arg("-E utf8 --locale C -A trust")
-                .env("TZ", "UTC");
-
-            bugs::retry_pg_ctl(&mut command, |_| Ok(()))
+            let runtime = self.runtime()?;
+            let state = bugs::retry_pg_ctl(&mut self.init_command(&runtime), |_| Ok(()))?;
+            self.pin_runtime(&runtime)?;
+            Ok(state)
         }
     }
 
+    /// Construct the `pg_ctl init` command.
+    fn init_command(&self, runtime: &Runtime) -> Command {
+        let mut command = self.ctl(runtime);
+        #[allow(clippy::suspicious_command_arg_space)]
+        command
+            .arg("init")
+            // Silent; `--silent` flag accepted only in PostgreSQL >=9.2.
+            .arg("-s")
+            // Options for `initdb`; `--options` flag accepted only in PostgreSQL >=10.
+            .arg("-o")
+            // Passing multiple flags in a single `arg(...)` is intentional.
+            // These constitute the single value for the `-o` flag above.
+            .arg("-E utf8 --locale C -A trust")
+            .env("TZ", "UTC");
+        command
+    }
+
     /// Start the cluster if it's not already running, with the given options.
     ///
     /// Returns [`State::Unmodified`] if the cluster is already running, meaning
     /// the given options were **NOT** applied.
+    ///
+    /// A stale `postmaster.pid` file is removed first – see
+    /// [`remove_stale_pidfile`][`Self::remove_stale_pidfile`] – so this must
+    /// only be called while holding an exclusive lock on the cluster. If the
+    /// server did not shut down cleanly, this logs that it is performing crash
+    /// recovery, during which [`status`][`Self::status`] reports
+    /// [`ClusterStatus::CrashRecovery`].
     pub fn start(
         &self,
         options: &[(config::Parameter, config::Value)],
@@ -306,6 +629,21 @@ impl Cluster {
             // We didn't start this cluster; say so.
             return Ok(Unmodified);
         }
+        self.prepare_start()?;
+        // Track the logs so that we can see if there's a retryable failure.
+        let mut log: logfile::LogFile = self.logfile().as_path().try_into()?;
+        let mut command = self.start_command(&self.runtime()?, options);
+        // Append new logs to the command's `Output` so that the retry machinery
+        // has visibility of it.
+        bugs::retry_pg_ctl(&mut command, |output| Ok(log.append_to_stderr(output)?))
+    }
+
+    /// Construct the `pg_ctl start` command, with the given options.
+    fn start_command(
+        &self,
+        runtime: &Runtime,
+        options: &[(config::Parameter, config::Value)],
+    ) -> Command {
         // Construct the options that `pg_ctl` will pass through to `postgres`.
         // These have to be carefully escaped for the target shell – which is
         // likely to be `sh`. Here's what they mean:
@@ -322,16 +660,8 @@ impl Cluster {
             OsString::from_vec(arg)
         };
 
-        // Track the logs so that we can see if there's a retryable failure.
-        let logfile = self.logfile();
-        let logfile_name = logfile
-            .file_name()
-            .unwrap_or_else(|| "<unknown.log>".as_ref())
-            .display();
-        let mut log: logfile::LogFile = logfile.as_path().try_into()?;
-
         // Next, invoke `pg_ctl` to start the cluster.
-        let mut command = self.ctl()?;
+        let mut command = self.ctl(runtime);
         //  -l <file> -- log file.
         //  -s -- no informational messages.
         //  -w -- wait until startup is complete.
@@ -339,36 +669,41 @@ impl Cluster {
         command
             .arg("start")
             .arg("-l")
-            .arg(&logfile)
+            .arg(self.logfile())
             .arg("-s")
             .arg("-w")
             .arg("-o")
             .arg(options);
 
-        // Append new logs to the command's `Output` so that the retry machinery
-        // has visibility of it.
-        let append_logs_to_stderr = |output: &mut Output| {
-            writeln!(&mut output.stderr)?;
-            writeln!(&mut output.stderr, "-- {logfile_name} --")?;
-            log.read_to_end(&mut output.stderr)?;
-            Ok(())
-        };
+        command
+    }
 
-        bugs::retry_pg_ctl(&mut command, append_logs_to_stderr)
+    /// Build a connection, or a pool of connections, to this cluster.
+    ///
+    /// ```rust,no_run
+    /// # use pgdo::cluster::ClusterError;
+    /// # let runtime = pgdo::runtime::strategy::Strategy::default();
+    /// # let cluster = pgdo::cluster::Cluster::new("some/where", runtime)?;
+    /// let mut client = cluster
+    ///     .connection()
+    ///     .database("example")
+    ///     .role("alice")
+    ///     .statement_timeout(std::time::Duration::from_secs(5))
+    ///     .client()?;
+    /// client.execute("SELECT 1", &[])?;
+    /// # Ok::<(), ClusterError>(())
+    /// ```
+    pub fn connection(&self) -> ConnectionBuilder<'_> {
+        ConnectionBuilder::new(self)
     }
 
     /// Connect to this cluster.
     ///
     /// When the database is not specified, connects to [`DATABASE_POSTGRES`].
     fn connect(&self, database: Option<&str>) -> Result<postgres::Client, ClusterError> {
-        let user = crate::util::current_user()?;
-        let host = self.datadir.to_string_lossy(); // postgres crate API limitation.
-        let client = postgres::Client::configure()
-            .host(&host)
-            .dbname(database.unwrap_or(DATABASE_POSTGRES))
-            .user(&user)
-            .connect(postgres::NoTls)?;
-        Ok(client)
+        self.connection()
+            .database(database.unwrap_or(DATABASE_POSTGRES))
+            .client()
     }
 
     /// Create a lazy SQLx pool for this cluster.
@@ -390,14 +725,11 @@ impl Cluster {
     /// ```
     ///
     /// When the database is not specified, connects to [`DATABASE_POSTGRES`].
+    /// Use [`connection`][Self::connection] for more control.
     pub fn pool(&self, database: Option<&str>) -> Result<sqlx::PgPool, ClusterError> {
-        Ok(sqlx::PgPool::connect_lazy_with(
-            sqlx::postgres::PgConnectOptions::new()
-                .socket(&self.datadir)
-                .database(database.unwrap_or(DATABASE_POSTGRES))
-                .username(&crate::util::current_user()?)
-                .application_name("pgdo"),
-        ))
+        self.connection()
+            .database(database.unwrap_or(DATABASE_POSTGRES))
+            .pool()
     }
 
     /// Return a URL for this cluster, if possible.
@@ -477,10 +809,7 @@ impl Cluster {
     /// returns [`Modified`].
     pub fn createdb(&self, database: &str) -> Result<State, ClusterError> {
         use postgres::error::SqlState;
-        let statement = format!(
-            "CREATE DATABASE {}",
-            postgres_protocol::escape::escape_identifier(database)
-        );
+        let statement = createdb_statement(database);
         match self.connect(None)?.execute(statement.as_str(), &[]) {
             Err(err) if err.code() == Some(&SqlState::DUPLICATE_DATABASE) => Ok(Unmodified),
             Err(err) => Err(err)?,
@@ -494,10 +823,7 @@ impl Cluster {
     /// returns [`Modified`].
     pub fn dropdb(&self, database: &str) -> Result<State, ClusterError> {
         use postgres::error::SqlState;
-        let statement = format!(
-            "DROP DATABASE {}",
-            postgres_protocol::escape::escape_identifier(database)
-        );
+        let statement = dropdb_statement(database);
         match self.connect(None)?.execute(statement.as_str(), &[]) {
             Err(err) if err.code() == Some(&SqlState::UNDEFINED_DATABASE) => Ok(Unmodified),
             Err(err) => Err(err)?,
@@ -505,34 +831,159 @@ impl Cluster {
         }
     }
 
-    /// Stop the cluster if it's running.
-    pub fn stop(&self) -> Result<State, ClusterError> {
+    /// Stop the cluster if it's running, with the given options.
+    ///
+    /// If the server is still running when the timeout expires, this escalates
+    /// to the next faster [`ShutdownMode`]; see [`StopOptions`].
+    pub fn stop(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.shutdown(ShutdownAction::Stop, options)
+    }
+
+    /// Restart the cluster if it's running. The server is shut down with the
+    /// given options – escalating as [`stop`][`Self::stop`] does – then started
+    /// again with the same options it was started with before.
+    ///
+    /// Returns [`Unmodified`] if the cluster is not running, otherwise it
+    /// returns [`Modified`].
+    ///
+    /// **Note** that this must only be called while holding an exclusive lock
+    /// on the cluster.
+    pub fn restart(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.shutdown(ShutdownAction::Restart, options)
+    }
+
+    /// Stop or restart the cluster if it's running; see [`stop::Shutdown`].
+    fn shutdown(
+        &self,
+        action: ShutdownAction,
+        options: StopOptions,
+    ) -> Result<State, ClusterError> {
         // If the cluster's not already running, don't do anything.
         if !self.running()? {
             return Ok(Unmodified);
         }
+        let runtime = self.runtime()?;
+        let mut shutdown = stop::Shutdown::new(action, options, self.server_pid()?);
+        loop {
+            let mut log: logfile::LogFile = self.logfile().as_path().try_into()?;
+            let mut output = shutdown.command(self, &runtime).output()?;
+            if output.status.success() {
+                return Ok(Modified); // We did actually stop the cluster; say so.
+            }
+            if !shutdown.escalate(self, &output)? {
+                log.append_to_stderr(&mut output)?;
+                return Err(ClusterError::CommandError(output));
+            }
+        }
+    }
+
+    /// The PID of this cluster's server, if it is running.
+    fn server_pid(&self) -> Result<Option<i32>, ClusterError> {
+        match self.read_pidfile() {
+            Ok(Some(pidfile)) if pidfile.is_server_for(&self.datadir) => Ok(Some(pidfile.pid)),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Reload the cluster's configuration files if it's running.
+    ///
+    /// When a connection can be made, this calls `pg_reload_conf()` then waits
+    /// – for up to [`RELOAD_TIMEOUT`] – until the reload has happened, so that
+    /// new connections see the new configuration. Otherwise, e.g. when the
+    /// server is not yet accepting connections, this uses `pg_ctl reload`.
+    ///
+    /// Returns [`Unmodified`] if the cluster is not running, otherwise it
+    /// returns [`Modified`]. Settings that can only change at server start are
+    /// not applied by reloading; see [`pending_restart`][`Self::pending_restart`].
+    pub fn reload(&self) -> Result<State, ClusterError> {
+        if !self.running()? {
+            return Ok(Unmodified);
+        }
+        let Ok(mut conn) = self.connect(None) else {
+            let output = self.reload_command(&self.runtime()?).output()?;
+            return if output.status.success() {
+                Ok(Modified)
+            } else {
+                Err(ClusterError::CommandError(output))
+            };
+        };
+        let loaded: String = conn.query_one(CONF_LOAD_TIME_SQL, &[])?.get(0);
+        conn.execute("SELECT pg_reload_conf()", &[])?;
+        // The server reloads, then signals each backend to reload too; this one
+        // does so between queries. New backends start with the configuration
+        // the server has at the time.
+        let deadline = Instant::now() + RELOAD_TIMEOUT;
+        while conn.query_one(CONF_LOAD_TIME_SQL, &[])?.get::<_, String>(0) == loaded
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok(Modified)
+    }
+
+    /// Construct the `pg_ctl reload` command.
+    fn reload_command(&self, runtime: &Runtime) -> Command {
         // pg_ctl options:
-        //  -w -- wait for shutdown to complete.
-        //  -m <mode> -- shutdown mode.
-        let output = self
-            .ctl()?
-            .arg("stop")
-            .arg("-s")
-            .arg("-w")
-            .arg("-m")
-            .arg("fast")
-            .output()?;
+        //  -s -- no informational messages.
+        let mut command = self.ctl(runtime);
+        command.arg("reload").arg("-s");
+        command
+    }
+
+    /// Return the names of the settings that have changed but will only take
+    /// effect once the cluster is restarted, i.e. those for which
+    /// [`config::Setting::pending_restart`] is `true`.
+    ///
+    /// This reflects the configuration files as of the last reload; call
+    /// [`reload`][`Self::reload`] first to pick up recent changes, e.g. those
+    /// made with `ALTER SYSTEM`. The cluster must be running.
+    pub fn pending_restart(&self) -> Result<Vec<String>, ClusterError> {
+        let mut conn = self.connect(None)?;
+        let rows = conn.query(PENDING_RESTART_SQL, &[])?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Is this cluster in recovery, e.g. is it a standby?
+    ///
+    /// The cluster must be running.
+    pub fn in_recovery(&self) -> Result<bool, ClusterError> {
+        let mut conn = self.connect(None)?;
+        let row = conn.query_one("SELECT pg_is_in_recovery()", &[])?;
+        Ok(row.get(0))
+    }
 
+    /// Promote the cluster if it's a standby, i.e. end recovery so that it
+    /// begins accepting writes.
+    ///
+    /// Returns [`Unmodified`] if the cluster is not in recovery, otherwise it
+    /// returns [`Modified`]. The cluster must be running.
+    pub fn promote(&self) -> Result<State, ClusterError> {
+        if !self.in_recovery()? {
+            return Ok(Unmodified);
+        }
+        let output = self.promote_command(&self.runtime()?).output()?;
         if output.status.success() {
-            Ok(Modified) // We did actually stop the cluster; say so.
+            Ok(Modified)
         } else {
             Err(ClusterError::CommandError(output))
         }
     }
 
+    /// Construct the `pg_ctl promote` command.
+    fn promote_command(&self, runtime: &Runtime) -> Command {
+        // pg_ctl options:
+        //  -s -- no informational messages.
+        //  -w -- wait for promotion to complete.
+        let mut command = self.ctl(runtime);
+        command.arg("promote").arg("-s").arg("-w");
+        command
+    }
+
     /// Destroy the cluster if it exists, after stopping it.
     pub fn destroy(&self) -> Result<State, ClusterError> {
-        self.stop()?;
+        self.stop(StopOptions::default())?;
         match fs::remove_dir_all(&self.datadir) {
             Ok(()) => Ok(Modified),
             Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Unmodified),
@@ -547,6 +998,22 @@ impl AsRef<Path> for Cluster {
     }
 }
 
+/// The statement to create the named database.
+fn createdb_statement(database: &str) -> String {
+    format!(
+        "CREATE DATABASE {}",
+        postgres_protocol::escape::escape_identifier(database)
+    )
+}
+
+/// The statement to drop the named database.
+fn dropdb_statement(database: &str) -> String {
+    format!(
+        "DROP DATABASE {}",
+        postgres_protocol::escape::escape_identifier(database)
+    )
+}
+
 /// A fairly simplistic but quick check: does the directory exist and does it
 /// look like a PostgreSQL cluster data directory, i.e. does it contain a file
 /// named `PG_VERSION`?
@@ -652,13 +1119,14 @@ pub type Options<'a> = &'a [(config::Parameter<'a>, config::Value)];
 impl coordinate::Subject for Cluster {
     type Error = ClusterError;
     type Options<'a> = Options<'a>;
+    type StopOptions = StopOptions;
 
     fn start(&self, options: Self::Options<'_>) -> Result<State, Self::Error> {
         self.start(options)
     }
 
-    fn stop(&self) -> Result<State, Self::Error> {
-        self.stop()
+    fn stop(&self, options: Self::StopOptions) -> Result<State, Self::Error> {
+        self.stop(options)
     }
 
     fn destroy(&self) -> Result<State, Self::Error> {
@@ -705,12 +1173,64 @@ pub fn run<P: AsRef<Path>>(
     ClusterGuard::startup(lock, cluster, options)
 }
 
+/// [`AsyncCluster`] can be coordinated asynchronously.
+#[cfg(feature = "async")]
+impl coordinate::AsyncSubject for AsyncCluster {
+    type Error = ClusterError;
+    type Options<'a> = Options<'a>;
+    type StopOptions = StopOptions;
+
+    async fn start(&self, options: Self::Options<'_>) -> Result<State, Self::Error> {
+        self.start(options).await
+    }
+
+    async fn stop(&self, options: Self::StopOptions) -> Result<State, Self::Error> {
+        self.stop(options).await
+    }
+
+    async fn destroy(&self) -> Result<State, Self::Error> {
+        self.destroy().await
+    }
+
+    async fn exists(&self) -> Result<bool, Self::Error> {
+        self.exists().await
+    }
+
+    async fn running(&self) -> Result<bool, Self::Error> {
+        self.running().await
+    }
+}
+
+#[cfg(feature = "async")]
+pub type AsyncClusterGuard = coordinate::guard::AsyncGuard<AsyncCluster>;
+
+/// Asynchronous version of [`run`].
+#[cfg(feature = "async")]
+pub async fn run_async<P: AsRef<Path>>(
+    path: P,
+    options: Options<'_>,
+) -> Result<AsyncClusterGuard, coordinate::CoordinateError<ClusterError>> {
+    let path = path.as_ref();
+    tokio::fs::create_dir_all(path).await?;
+    let path = tokio::fs::canonicalize(path).await?;
+
+    let strategy = crate::runtime::strategy::Strategy::default();
+    let cluster = AsyncCluster::new(&path, strategy)?;
+
+    let lock_name = path.as_os_str().as_bytes();
+    let lock_uuid = uuid::Uuid::new_v5(&UUID_NS, lock_name);
+    let lock = crate::lock::UnlockedFile::try_from(&lock_uuid)?;
+
+    AsyncClusterGuard::startup(lock, cluster, options).await
+}
+
 // ----------------------------------------------------------------------
 
 mod logfile {
     use std::fs::File;
-    use std::io::{self, ErrorKind::NotFound, Read, Seek};
+    use std::io::{self, ErrorKind::NotFound, Read, Seek, Write};
     use std::path::{Path, PathBuf};
+    use std::process::Output;
 
     /// Abstraction for reading a log file that may be appended to repeatedly.
     ///
@@ -751,6 +1271,23 @@ mod logfile {
         }
     }
 
+    impl LogFile {
+        /// Append new logs to the given command output's `stderr`, headed with
+        /// the log file's name.
+        pub fn append_to_stderr(&mut self, output: &mut Output) -> io::Result<()> {
+            let name = self
+                .path
+                .file_name()
+                .unwrap_or_else(|| "<unknown.log>".as_ref())
+                .display()
+                .to_string();
+            writeln!(&mut output.stderr)?;
+            writeln!(&mut output.stderr, "-- {name} --")?;
+            self.read_to_end(&mut output.stderr)?;
+            Ok(())
+        }
+    }
+
     impl Read for LogFile {
         fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
             if let Some(ref mut file) = self.file {
@@ -776,6 +1313,77 @@ mod bugs {
     use std::process::{Command, Output};
     use std::{fmt::Write, sync, time::Duration};
 
+    static SEMGET_BUG_LOG: sync::Once = sync::Once::new();
+    static SEMGET_BUG_RE1: sync::LazyLock<Regex> = sync::LazyLock::new(|| {
+        Regex::new(r"\bFATAL:\s+could not create semaphores: Invalid argument\b")
+            .expect("invalid regex (for matching semaphore errors #1)")
+    });
+    static SEMGET_BUG_RE2: sync::LazyLock<Regex> = sync::LazyLock::new(|| {
+        Regex::new(r"\bDETAIL:\s+Failed system call was semget\b")
+            .expect("invalid regex (for matching semaphore errors #2)")
+    });
+
+    fn is_retryable(logs: &[u8]) -> bool {
+        SEMGET_BUG_RE1.is_match(logs) && SEMGET_BUG_RE2.is_match(logs)
+    }
+
+    /// Capture which `pg_ctl` command we're running – assume it's the first
+    /// argument – for use when notifying of failures.
+    fn summarize(command: &Command) -> String {
+        command.get_args().take(1).fold(
+            command.get_program().display().to_string(),
+            |mut summary, arg| {
+                write!(&mut summary, " {}", arg.display()).ok();
+                summary
+            },
+        )
+    }
+
+    /// Notify that `pg_ctl` failed transiently.
+    fn notify(command_summary: &str, delay: Duration) {
+        SEMGET_BUG_LOG.call_once(|| {
+            log::info!(concat!(
+                "In all presently released versions of PostgreSQL, `pg_ctl` can fail on ",
+                "some platforms (macOS, some BSDs) due to a semget(2) failure. This is a ",
+                "particular problem in PostgreSQL 17.x (the linked bug report has more ",
+                "information). As an imperfect workaround, `pgdo` retries the command a ",
+                "few times when it detects this specific error. Original bug report: ",
+                "https://www.postgresql.org/message-id/CALL7chmzY3eXHA7zHnODUVGZLSvK3wYCSP0RmcDFHJY8f28Q3g@mail.gmail.com.",
+            ));
+        });
+        log::warn!("`{command_summary}` failed; retrying in {delay:?}…");
+    }
+
+    /// Retry with exponential backoff + jitter.
+    fn backoff() -> backoff::ExponentialBackoff {
+        backoff::ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_millis(200))
+            .with_max_elapsed_time(Some(Duration::from_mins(1)))
+            .with_max_interval(Duration::from_secs(10))
+            .build()
+    }
+
+    /// Classify the outcome of running `pg_ctl`.
+    fn classify(
+        output: std::io::Result<Output>,
+        supplement: &mut impl FnMut(&mut Output) -> Result<(), ClusterError>,
+    ) -> Result<State, backoff::Error<ClusterError>> {
+        use backoff::Error;
+        use ClusterError::{CommandError, IoError};
+        match output {
+            Ok(output) if output.status.success() => Ok(Modified),
+            Ok(mut output) => {
+                supplement(&mut output)?;
+                if is_retryable(output.stderr.as_slice()) {
+                    Err(Error::transient(CommandError(output)))
+                } else {
+                    Err(Error::permanent(CommandError(output)))
+                }
+            }
+            Err(err) => Err(Error::permanent(IoError(err))),
+        }
+    }
+
     /// Work around bugs in `pg_ctl`.
     ///
     /// In all presently released versions of PostgreSQL, `pg_ctl init` can fail
@@ -797,78 +1405,36 @@ mod bugs {
         command: &mut Command,
         mut supplement: impl FnMut(&mut Output) -> Result<(), ClusterError>,
     ) -> Result<State, ClusterError> {
-        static SEMGET_BUG_LOG: sync::Once = sync::Once::new();
-        static SEMGET_BUG_RE1: sync::LazyLock<Regex> = sync::LazyLock::new(|| {
-            Regex::new(r"\bFATAL:\s+could not create semaphores: Invalid argument\b")
-                .expect("invalid regex (for matching semaphore errors #1)")
-        });
-        static SEMGET_BUG_RE2: sync::LazyLock<Regex> = sync::LazyLock::new(|| {
-            Regex::new(r"\bDETAIL:\s+Failed system call was semget\b")
-                .expect("invalid regex (for matching semaphore errors #2)")
-        });
-
-        fn is_retryable(logs: &[u8]) -> bool {
-            SEMGET_BUG_RE1.is_match(logs) && SEMGET_BUG_RE2.is_match(logs)
-        }
-
-        // Capture which `pg_ctl` command we're running – assume it's the first
-        // argument – for use with `notify` later on.
-        let command_summary = command.get_args().take(1).fold(
-            command.get_program().display().to_string(),
-            |mut summary, arg| {
-                write!(&mut summary, " {}", arg.display()).ok();
-                summary
-            },
-        );
-
-        // Function that attempts to run `command`.
-        let run = || {
-            use backoff::Error;
-            use ClusterError::{CommandError, IoError};
-            match command.output() {
-                Ok(output) if output.status.success() => Ok(Modified),
-                Ok(mut output) => {
-                    supplement(&mut output)?;
-                    if is_retryable(output.stderr.as_slice()) {
-                        Err(Error::transient(CommandError(output)))
-                    } else {
-                        Err(Error::permanent(CommandError(output)))
-                    }
-                }
-                Err(err) => Err(Error::permanent(IoError(err))),
-            }
-        };
-
-        // Function that notifies when `pg_ctl` fails transiently.
-        let notify = move |_, delay: Duration| {
-            SEMGET_BUG_LOG.call_once(|| {
-                log::info!(concat!(
-                    "In all presently released versions of PostgreSQL, `pg_ctl` can fail on ",
-                    "some platforms (macOS, some BSDs) due to a semget(2) failure. This is a ",
-                    "particular problem in PostgreSQL 17.x (the linked bug report has more ",
-                    "information). As an imperfect workaround, `pgdo` retries the command a ",
-                    "few times when it detects this specific error. Original bug report: ",
-                    "https://www.postgresql.org/message-id/CALL7chmzY3eXHA7zHnODUVGZLSvK3wYCSP0RmcDFHJY8f28Q3g@mail.gmail.com.",
-                ));
-            });
-            log::warn!("`{command_summary}` failed; retrying in {delay:?}…");
-        };
-
-        // Retry with exponential backoff + jitter.
-        let state = backoff::retry_notify(
-            backoff::ExponentialBackoffBuilder::new()
-                .with_initial_interval(Duration::from_millis(200))
-                .with_max_elapsed_time(Some(Duration::from_mins(1)))
-                .with_max_interval(Duration::from_secs(10))
-                .build(),
-            run,
-            notify,
-        )
-        .map_err(|err| match err {
+        let command_summary = summarize(command);
+        let run = || classify(command.output(), &mut supplement);
+        let notify = |_, delay: Duration| notify(&command_summary, delay);
+        backoff::retry_notify(backoff(), run, notify).map_err(|err| match err {
             backoff::Error::Permanent(err) => err,
             backoff::Error::Transient { err, .. } => err,
-        })?;
+        })
+    }
 
-        Ok(state)
+    /// Asynchronous version of [`retry_pg_ctl`].
+    #[cfg(feature = "async")]
+    pub async fn retry_pg_ctl_async(
+        command: &mut tokio::process::Command,
+        mut supplement: impl FnMut(&mut Output) -> Result<(), ClusterError>,
+    ) -> Result<State, ClusterError> {
+        use backoff::backoff::Backoff;
+        let command_summary = summarize(command.as_std());
+        let mut backoff = backoff();
+        loop {
+            match classify(command.output().await, &mut supplement) {
+                Ok(state) => return Ok(state),
+                Err(backoff::Error::Permanent(err)) => return Err(err),
+                Err(backoff::Error::Transient { err, .. }) => match backoff.next_backoff() {
+                    Some(delay) => {
+                        notify(&command_summary, delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(err),
+                },
+            }
+        }
     }
 }
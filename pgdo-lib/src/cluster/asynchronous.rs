@@ -0,0 +1,332 @@
+//! An asynchronous interface to a [`Cluster`], for use with Tokio.
+//!
+//! External commands are run with [`tokio::process`] and queries go through
+//! [`sqlx`]. Everything else that touches the filesystem – selecting a runtime,
+//! which may run `pg_ctl --version` and update the runtime cache, checking the
+//! server's `postmaster.pid` file, and so on – is done with
+//! [`spawn_blocking`][`tokio::task::spawn_blocking`]. None of these methods
+//! block the calling thread.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use super::{
+    bugs, config, createdb_statement, dropdb_statement, exists, logfile,
+    stop::{Shutdown, ShutdownAction},
+    Cluster, ClusterError, ClusterStatus, ConnectionBuilder, StopOptions, CONF_LOAD_TIME_SQL,
+    PENDING_RESTART_SQL, RELOAD_TIMEOUT,
+};
+use crate::{
+    coordinate::State::{self, *},
+    runtime::strategy::Strategy,
+};
+
+/// SQLSTATE for `duplicate_database`.
+const DUPLICATE_DATABASE: &str = "42P04";
+
+/// SQLSTATE for `invalid_catalog_name`, e.g. database does not exist.
+const UNDEFINED_DATABASE: &str = "3D000";
+
+/// A [`Cluster`] with asynchronous lifecycle methods.
+///
+/// This does not dereference to [`Cluster`] because many of its methods block.
+/// Use [`into_inner`][`Self::into_inner`] to get at them anyway.
+pub struct AsyncCluster {
+    cluster: Arc<Cluster>,
+}
+
+impl AsyncCluster {
+    /// Represent a cluster at the given path.
+    pub fn new<P: AsRef<Path>, S: Into<Strategy>>(
+        datadir: P,
+        strategy: S,
+    ) -> Result<Self, ClusterError> {
+        Ok(Cluster::new(datadir, strategy)?.into())
+    }
+
+    /// Unwrap the underlying [`Cluster`].
+    ///
+    /// The cluster is shared with blocking tasks that this type's methods
+    /// spawn. Those tasks finish even if the calling future is dropped, hence
+    /// the [`Arc`].
+    pub fn into_inner(self) -> Arc<Cluster> {
+        self.cluster
+    }
+
+    /// The data directory of the cluster; see [`Cluster::datadir`].
+    pub fn datadir(&self) -> &Path {
+        &self.cluster.datadir
+    }
+
+    /// See [`Cluster::connection`].
+    pub fn connection(&self) -> ConnectionBuilder<'_> {
+        self.cluster.connection()
+    }
+
+    /// See [`Cluster::pool`].
+    pub fn pool(&self, database: Option<&str>) -> Result<sqlx::PgPool, ClusterError> {
+        self.cluster.pool(database)
+    }
+
+    /// Call `f` with the cluster on a thread where blocking is acceptable.
+    async fn blocking<F, T>(&self, f: F) -> Result<T, ClusterError>
+    where
+        F: FnOnce(&Cluster) -> Result<T, ClusterError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let cluster = Arc::clone(&self.cluster);
+        match tokio::task::spawn_blocking(move || f(&cluster)).await {
+            Ok(result) => result,
+            Err(err) => match err.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(err) => Err(io::Error::other(err))?,
+            },
+        }
+    }
+
+    /// Asynchronous version of [`exists`].
+    pub async fn exists(&self) -> Result<bool, ClusterError> {
+        self.blocking(|cluster| Ok(exists(cluster))).await
+    }
+
+    /// Asynchronous version of [`Cluster::running`].
+    pub async fn running(&self) -> Result<bool, ClusterError> {
+        Ok(self.status().await?.is_running())
+    }
+
+    /// Asynchronous version of [`Cluster::status`].
+    pub async fn status(&self) -> Result<ClusterStatus, ClusterError> {
+        self.blocking(Cluster::status).await
+    }
+
+    /// Asynchronous version of [`Cluster::create`].
+    pub async fn create(&self) -> Result<State, ClusterError> {
+        let runtime = self
+            .blocking(|cluster| {
+                if exists(cluster) {
+                    Ok(None)
+                } else {
+                    std::fs::create_dir_all(&cluster.datadir)?;
+                    cluster.runtime().map(Some)
+                }
+            })
+            .await?;
+        let Some(runtime) = runtime else {
+            return Ok(Unmodified);
+        };
+        let mut command: Command = self.cluster.init_command(&runtime).into();
+        let state = bugs::retry_pg_ctl_async(&mut command, |_| Ok(())).await?;
+        self.blocking(move |cluster| cluster.pin_runtime(&runtime))
+            .await?;
+        Ok(state)
+    }
+
+    /// Asynchronous version of [`Cluster::start`].
+    pub async fn start(
+        &self,
+        options: &[(config::Parameter<'_>, config::Value)],
+    ) -> Result<State, ClusterError> {
+        self.create().await?;
+        if self.running().await? {
+            return Ok(Unmodified);
+        }
+        let (runtime, mut log) = self
+            .blocking(|cluster| {
+                cluster.prepare_start()?;
+                let log: logfile::LogFile = cluster.logfile().as_path().try_into()?;
+                Ok((cluster.runtime()?, log))
+            })
+            .await?;
+        let mut command: Command = self.cluster.start_command(&runtime, options).into();
+        bugs::retry_pg_ctl_async(&mut command, |output| Ok(log.append_to_stderr(output)?)).await
+    }
+
+    /// Asynchronous version of [`Cluster::stop`].
+    pub async fn stop(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.shutdown(ShutdownAction::Stop, options).await
+    }
+
+    /// Asynchronous version of [`Cluster::restart`].
+    pub async fn restart(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.shutdown(ShutdownAction::Restart, options).await
+    }
+
+    /// Asynchronous version of [`Cluster::shutdown`].
+    async fn shutdown(
+        &self,
+        action: ShutdownAction,
+        options: StopOptions,
+    ) -> Result<State, ClusterError> {
+        if !self.running().await? {
+            return Ok(Unmodified);
+        }
+        let (runtime, pid) = self
+            .blocking(|cluster| Ok((cluster.runtime()?, cluster.server_pid()?)))
+            .await?;
+        let mut shutdown = Shutdown::new(action, options, pid);
+        loop {
+            let mut log: logfile::LogFile = self
+                .blocking(|cluster| Ok(cluster.logfile().as_path().try_into()?))
+                .await?;
+            let mut command: Command = shutdown.command(&self.cluster, &runtime).into();
+            let mut output = command.output().await?;
+            if output.status.success() {
+                return Ok(Modified);
+            }
+            shutdown = self
+                .blocking(move |cluster| {
+                    if shutdown.escalate(cluster, &output)? {
+                        Ok(shutdown)
+                    } else {
+                        log.append_to_stderr(&mut output)?;
+                        Err(ClusterError::CommandError(output))
+                    }
+                })
+                .await?;
+        }
+    }
+
+    /// Asynchronous version of [`Cluster::reload`].
+    pub async fn reload(&self) -> Result<State, ClusterError> {
+        if !self.running().await? {
+            return Ok(Unmodified);
+        }
+        let pool = self.cluster.pool(None)?;
+        let result = if let Ok(mut conn) = pool.acquire().await {
+            reload_conf(&mut conn).await
+        } else {
+            // Not accepting connections; signal the server instead.
+            let runtime = self.blocking(Cluster::runtime).await?;
+            let mut command: Command = self.cluster.reload_command(&runtime).into();
+            let output = command.output().await?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(ClusterError::CommandError(output))
+            }
+        };
+        pool.close().await;
+        result.map(|()| Modified)
+    }
+
+    /// Asynchronous version of [`Cluster::pending_restart`].
+    pub async fn pending_restart(&self) -> Result<Vec<String>, ClusterError> {
+        let pool = self.cluster.pool(None)?;
+        let result = sqlx::query_scalar(PENDING_RESTART_SQL)
+            .fetch_all(&pool)
+            .await;
+        pool.close().await;
+        Ok(result?)
+    }
+
+    /// Asynchronous version of [`Cluster::destroy`].
+    pub async fn destroy(&self) -> Result<State, ClusterError> {
+        self.stop(StopOptions::default()).await?;
+        match tokio::fs::remove_dir_all(&self.cluster.datadir).await {
+            Ok(()) => Ok(Modified),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Unmodified),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Asynchronous version of [`Cluster::in_recovery`].
+    pub async fn in_recovery(&self) -> Result<bool, ClusterError> {
+        let pool = self.cluster.pool(None)?;
+        let result = sqlx::query_scalar("SELECT pg_is_in_recovery()")
+            .fetch_one(&pool)
+            .await;
+        pool.close().await;
+        Ok(result?)
+    }
+
+    /// Asynchronous version of [`Cluster::promote`].
+    pub async fn promote(&self) -> Result<State, ClusterError> {
+        if !self.in_recovery().await? {
+            return Ok(Unmodified);
+        }
+        let runtime = self.blocking(Cluster::runtime).await?;
+        let mut command: Command = self.cluster.promote_command(&runtime).into();
+        let output = command.output().await?;
+        if output.status.success() {
+            Ok(Modified)
+        } else {
+            Err(ClusterError::CommandError(output))
+        }
+    }
+
+    /// Asynchronous version of [`Cluster::databases`].
+    pub async fn databases(&self) -> Result<Vec<String>, ClusterError> {
+        let pool = self.cluster.pool(None)?;
+        let result =
+            sqlx::query_scalar("SELECT datname FROM pg_catalog.pg_database ORDER BY datname")
+                .fetch_all(&pool)
+                .await;
+        pool.close().await;
+        Ok(result?)
+    }
+
+    /// Asynchronous version of [`Cluster::createdb`].
+    pub async fn createdb(&self, database: &str) -> Result<State, ClusterError> {
+        let statement = createdb_statement(database);
+        self.execute_unless(&statement, DUPLICATE_DATABASE).await
+    }
+
+    /// Asynchronous version of [`Cluster::dropdb`].
+    pub async fn dropdb(&self, database: &str) -> Result<State, ClusterError> {
+        let statement = dropdb_statement(database);
+        self.execute_unless(&statement, UNDEFINED_DATABASE).await
+    }
+
+    /// Execute `statement` in the `postgres` database. Returns [`Unmodified`]
+    /// if it fails with the given SQLSTATE `code`, otherwise [`Modified`].
+    async fn execute_unless(&self, statement: &str, code: &str) -> Result<State, ClusterError> {
+        let pool = self.cluster.pool(None)?;
+        let result = sqlx::raw_sql(statement).execute(&pool).await;
+        pool.close().await;
+        match result {
+            Err(sqlx::Error::Database(err)) if err.code().as_deref() == Some(code) => {
+                Ok(Unmodified)
+            }
+            Err(err) => Err(err)?,
+            Ok(_) => Ok(Modified),
+        }
+    }
+}
+
+impl From<Cluster> for AsyncCluster {
+    fn from(cluster: Cluster) -> Self {
+        Self { cluster: Arc::new(cluster) }
+    }
+}
+
+impl AsRef<Path> for AsyncCluster {
+    fn as_ref(&self) -> &Path {
+        &self.cluster.datadir
+    }
+}
+
+/// Call `pg_reload_conf()` then wait until `conn` has reloaded; see
+/// [`Cluster::reload`].
+async fn reload_conf(conn: &mut sqlx::PgConnection) -> Result<(), ClusterError> {
+    let loaded: String = sqlx::query_scalar(CONF_LOAD_TIME_SQL)
+        .fetch_one(&mut *conn)
+        .await?;
+    sqlx::query("SELECT pg_reload_conf()")
+        .execute(&mut *conn)
+        .await?;
+    let deadline = tokio::time::Instant::now() + RELOAD_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        let load_time: String = sqlx::query_scalar(CONF_LOAD_TIME_SQL)
+            .fetch_one(&mut *conn)
+            .await?;
+        if load_time != loaded {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use std::{
-    ffi::OsStr,
-    io,
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fmt, io,
+    os::unix::ffi::{OsStrExt, OsStringExt},
     path::{Path, PathBuf},
     process::ExitStatus,
 };
@@ -10,8 +12,8 @@ use tempfile::TempDir;
 use tokio::{fs, task::block_in_place};
 use tokio_stream::{wrappers::ReadDirStream, StreamExt};
 
-use super::{config, resource::HeldResource};
-use crate::{cluster, coordinate, lock};
+use super::{config, resource::HeldResource, sqlx};
+use crate::{cluster, coordinate, lock, runtime::Runtime, version};
 
 // ----------------------------------------------------------------------------
 
@@ -32,22 +34,22 @@ impl Backup {
         Ok(Self { backup_dir, backup_wal_dir })
     }
 
-    /// Configures the cluster for continuous archiving.
+    /// Configures the cluster for continuous archiving. If the cluster is
+    /// already configured appropriately, this does nothing.
     ///
-    /// Returns a flag indicating if the cluster must be restarted for changes
-    /// to take effect. If the cluster is already configured appropriately, this
-    /// does nothing (and returns `false`).
+    /// Changes take effect only once the configuration is reloaded, and some
+    /// need the cluster to be restarted; see [`ensure_applied`].
+    ///
+    /// [`ensure_applied`]: crate::cluster::resource::ensure_applied
     pub async fn do_configure_archiving(
         &self,
         resource: &'_ HeldResource,
         archive_command: &str,
-    ) -> Result<bool, BackupError> {
+    ) -> Result<(), BackupError> {
         let pool = match resource {
             Left(resource) => resource.facet().pool(None),
             Right(resource) => resource.facet().pool(None),
         }?;
-        let mut restart: bool = false;
-
         // Ensure that `wal_level` is set to `replica` or `logical`. If not,
         // set it to `replica`.
         match WAL_LEVEL.get(&pool).await? {
@@ -57,7 +59,6 @@ impl Backup {
             Some(_) => {
                 log::info!("Setting {WAL_LEVEL:?} to 'replica'");
                 WAL_LEVEL.set(&pool, "replica").await?;
-                restart = true;
             }
             None => {
                 return Err(BackupError::ConfigError(
@@ -75,7 +76,6 @@ impl Backup {
             Some(_) => {
                 log::info!("Setting {ARCHIVE_MODE:?} to 'on'");
                 ARCHIVE_MODE.set(&pool, "on").await?;
-                restart = true;
             }
             None => {
                 return Err(BackupError::ConfigError(
@@ -122,7 +122,7 @@ impl Backup {
             }
         }
 
-        Ok(restart)
+        Ok(())
     }
 
     /// Performs a "base backup" of the cluster.
@@ -132,25 +132,86 @@ impl Backup {
     ///
     /// This must be performed _after_ configuring continuous archiving (see
     /// [`Backup::do_configure_archiving`]).
-    pub async fn do_base_backup(&self, resource: &'_ HeldResource) -> Result<PathBuf, BackupError> {
+    pub async fn do_base_backup(
+        &self,
+        resource: &'_ HeldResource,
+        options: &BaseBackupOptions,
+    ) -> Result<PathBuf, BackupError> {
+        let (runtime, pool) = match resource {
+            Left(resource) => (resource.facet().runtime()?, resource.facet().pool(None)?),
+            Right(resource) => (resource.facet().runtime()?, resource.facet().pool(None)?),
+        };
+
         // Temporary location into which we'll make the base backup.
         let backup_tmp_dir =
             block_in_place(|| TempDir::with_prefix_in(BACKUP_DATA_PREFIX_TMP, &self.backup_dir))?;
 
-        let args: &[&OsStr] = &[
-            "--pgdata".as_ref(),
-            backup_tmp_dir.path().as_ref(),
-            "--format".as_ref(),
-            "plain".as_ref(),
-            "--progress".as_ref(),
+        // Tablespaces other than `pg_default` and `pg_global` live outside of
+        // the cluster's data directory. These are mapped into a separate
+        // temporary location, and the mapping is recorded alongside.
+        let tablespaces = Tablespace::list(&pool).await?;
+        let tablespaces_tmp_dir = if tablespaces.is_empty() || options.format != Format::Plain {
+            None
+        } else {
+            Some(block_in_place(|| {
+                TempDir::with_prefix_in(BACKUP_TABLESPACES_PREFIX_TMP, &self.backup_dir)
+            })?)
+        };
+
+        let mut args: Vec<OsString> = vec![
+            "--pgdata".into(),
+            backup_tmp_dir.path().into(),
+            "--format".into(),
+            options.format.as_arg().into(),
+            "--progress".into(),
         ];
+        if let Format::Tar(Some(compression)) = options.format {
+            args.extend(compression.args(&runtime.version)?);
+        }
+        let incremental = if options.incremental {
+            self.prepare_incremental(resource, options).await?
+        } else {
+            None
+        };
+        if let Some(ref manifest) = incremental {
+            let mut arg = OsString::from("--incremental=");
+            arg.push(manifest);
+            args.push(arg);
+        }
+        if let Some(ref tablespaces_tmp_dir) = tablespaces_tmp_dir {
+            args.extend(tablespaces.iter().map(|tablespace| {
+                tablespace_mapping_arg(
+                    &tablespace.location,
+                    &tablespaces_tmp_dir.path().join(tablespace.oid.to_string()),
+                )
+            }));
+        }
+        let args: Vec<&OsStr> = args.iter().map(OsString::as_os_str).collect();
+        let (server_version, system_identifier) = server_details(&pool, &runtime).await?;
+        let start_time = jiff::Timestamp::now();
         let status = block_in_place(|| match resource {
-            Left(resource) => resource.facet().exec(None, "pg_basebackup".as_ref(), args),
-            Right(resource) => resource.facet().exec(None, "pg_basebackup".as_ref(), args),
+            Left(resource) => resource.facet().exec(None, "pg_basebackup".as_ref(), &args),
+            Right(resource) => resource.facet().exec(None, "pg_basebackup".as_ref(), &args),
         })?;
         if !status.success() {
             Err(status)?;
         }
+        let end_time = jiff::Timestamp::now();
+        let wal = block_in_place(|| wal_range(backup_tmp_dir.path()))?;
+        let metadata = Metadata {
+            start_time,
+            end_time,
+            start_lsn: wal.start_lsn,
+            end_lsn: wal.end_lsn,
+            timeline: wal.timeline,
+            server_version,
+            bindir: runtime.bindir.clone(),
+            system_identifier,
+            format: options.format,
+            incremental: incremental.is_some(),
+            tablespaces: TablespaceMap::from(tablespaces.as_slice()),
+            pgdo_version: env!("CARGO_PKG_VERSION").into(),
+        };
         // Before calculating the target directory name or doing the actual
         // rename, take out a coordinating lock in `backup_dir`.
         let backup_lock = block_in_place(|| {
@@ -176,6 +237,19 @@ impl Backup {
                 + 1
         ));
 
+        // Move tablespaces into place first, next to where the data directory
+        // will be moved.
+        place_tablespaces(
+            &tablespaces,
+            tablespaces_tmp_dir,
+            backup_tmp_dir.path(),
+            &backup_data_dir,
+        )
+        .await?;
+
+        // Record metadata next to where the data directory will be moved.
+        metadata.write(&backup_data_dir).await?;
+
         // Do the rename.
         fs::rename(&backup_tmp_dir, &backup_data_dir).await?;
         drop(backup_lock);
@@ -184,11 +258,709 @@ impl Backup {
     }
 }
 
+/// Move the tablespaces of a base backup into place and record the mapping.
+///
+/// In plain format, the symlinks in `pg_tblspc` point to the temporary location
+/// of the tablespaces, so we point them at the new location. This keeps the
+/// backup usable by `pg_combinebackup`, for example; `restore` does not copy
+/// these links but recreates them from the recorded mapping.
+///
+/// In tar format, tablespaces are archived into the backup's data directory as
+/// `{oid}.tar` (with a compression suffix, maybe), but we record the mapping
+/// all the same.
+async fn place_tablespaces(
+    tablespaces: &[Tablespace],
+    tablespaces_tmp_dir: Option<TempDir>,
+    backup_tmp_dir: &Path,
+    backup_data_dir: &Path,
+) -> Result<(), io::Error> {
+    let backup_tablespaces_dir = tablespaces_dir(backup_data_dir);
+    if let Some(tablespaces_tmp_dir) = tablespaces_tmp_dir {
+        TablespaceMap::from(tablespaces)
+            .write(tablespaces_tmp_dir.path().join(TABLESPACE_MAP_NAME))
+            .await?;
+        fs::rename(&tablespaces_tmp_dir, &backup_tablespaces_dir).await?;
+        for tablespace in tablespaces {
+            let oid = tablespace.oid.to_string();
+            let link = backup_tmp_dir.join("pg_tblspc").join(&oid);
+            fs::remove_file(&link).await?;
+            fs::symlink(backup_tablespaces_dir.join(&oid), &link).await?;
+        }
+    } else if !tablespaces.is_empty() {
+        fs::create_dir(&backup_tablespaces_dir).await?;
+        TablespaceMap::from(tablespaces)
+            .write(backup_tablespaces_dir.join(TABLESPACE_MAP_NAME))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Query the server's version and, from PostgreSQL 9.6 onwards, its system
+/// identifier.
+async fn server_details(
+    pool: &sqlx::PgPool,
+    runtime: &Runtime,
+) -> Result<(version::Version, Option<u64>), BackupError> {
+    let server_version: String = sqlx::query_scalar("SHOW server_version")
+        .fetch_one(pool)
+        .await?;
+    let system_identifier = if runtime.version >= version::Version::Pre10(9, 6, 0) {
+        let system_identifier: i64 =
+            sqlx::query_scalar("SELECT system_identifier FROM pg_control_system()")
+                .fetch_one(pool)
+                .await?;
+        // PostgreSQL's system identifier is unsigned, but it's returned as a
+        // signed `bigint`.
+        Some(u64::from_ne_bytes(system_identifier.to_ne_bytes()))
+    } else {
+        None
+    };
+    Ok((server_version.parse()?, system_identifier))
+}
+
+#[derive(Debug, Default)]
+struct WalRange {
+    start_lsn: Option<Lsn>,
+    end_lsn: Option<Lsn>,
+    timeline: Option<u32>,
+}
+
+/// Determine the start LSN, end LSN, and timeline of the base backup in
+/// `backup_data_dir`.
+///
+/// From PostgreSQL 13, `pg_basebackup` writes a `backup_manifest` which records
+/// all of these. Before that, the `backup_label` of a plain-format backup
+/// records the start LSN and timeline.
+fn wal_range(backup_data_dir: &Path) -> Result<WalRange, BackupError> {
+    #[derive(serde::Deserialize)]
+    struct Manifest {
+        #[serde(rename = "WAL-Ranges")]
+        wal_ranges: Vec<ManifestWalRange>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ManifestWalRange {
+        #[serde(rename = "Timeline")]
+        timeline: u32,
+        #[serde(rename = "Start-LSN")]
+        start_lsn: Lsn,
+        #[serde(rename = "End-LSN")]
+        end_lsn: Lsn,
+    }
+
+    match std::fs::File::open(backup_data_dir.join(BACKUP_MANIFEST_NAME)) {
+        Ok(file) => {
+            let manifest: Manifest = serde_json::from_reader(io::BufReader::new(file))?;
+            return Ok(WalRange {
+                start_lsn: manifest.wal_ranges.first().map(|range| range.start_lsn),
+                end_lsn: manifest.wal_ranges.last().map(|range| range.end_lsn),
+                timeline: manifest.wal_ranges.last().map(|range| range.timeline),
+            });
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+        Err(err) => Err(err)?,
+    }
+
+    match std::fs::read_to_string(backup_data_dir.join("backup_label")) {
+        Ok(label) => {
+            let field = |name: &str| {
+                label
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .and_then(|value| value.split_whitespace().next())
+            };
+            Ok(WalRange {
+                start_lsn: field("START WAL LOCATION:").and_then(|lsn| lsn.parse().ok()),
+                end_lsn: None,
+                timeline: field("START TIMELINE:").and_then(|timeline| timeline.parse().ok()),
+            })
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(WalRange::default()),
+        Err(err) => Err(err)?,
+    }
+}
+
+/// The file in which metadata for the base backup in `backup_data_dir` is
+/// recorded. This is next to the backup, named after it.
+pub fn metadata_file<P: AsRef<Path>>(backup_data_dir: P) -> PathBuf {
+    let backup_data_dir = backup_data_dir.as_ref();
+    let mut name = backup_data_dir.file_name().unwrap_or_default().to_owned();
+    name.push(BACKUP_METADATA_SUFFIX);
+    backup_data_dir.with_file_name(name)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Metadata about a base backup, recorded by pgdo next to the backup.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Metadata {
+    /// When `pg_basebackup` was started.
+    pub start_time: jiff::Timestamp,
+    /// When `pg_basebackup` finished.
+    pub end_time: jiff::Timestamp,
+    /// The WAL location at which the backup started.
+    pub start_lsn: Option<Lsn>,
+    /// The WAL location at which the backup ended. Only known from PostgreSQL
+    /// 13 onwards.
+    pub end_lsn: Option<Lsn>,
+    /// The timeline on which the backup ended.
+    pub timeline: Option<u32>,
+    /// The version of the server that was backed up.
+    #[serde(with = "crate::util::serde_display")]
+    pub server_version: version::Version,
+    /// The `bindir` of the runtime used to make the backup.
+    pub bindir: PathBuf,
+    /// The system identifier of the cluster that was backed up. Only known
+    /// from PostgreSQL 9.6 onwards.
+    pub system_identifier: Option<u64>,
+    /// The format of the backup, including any compression.
+    pub format: Format,
+    /// Whether this is an incremental backup.
+    pub incremental: bool,
+    /// The cluster's tablespaces and their original locations.
+    pub tablespaces: TablespaceMap,
+    /// The version of pgdo used to make the backup.
+    pub pgdo_version: String,
+}
+
+impl Metadata {
+    /// Read the metadata recorded for the base backup in `backup_data_dir`.
+    /// Returns `None` if no metadata was recorded, e.g. the backup was made
+    /// with an earlier version of pgdo.
+    pub fn for_backup<P: AsRef<Path>>(backup_data_dir: P) -> Result<Option<Self>, BackupError> {
+        match std::fs::File::open(metadata_file(backup_data_dir)) {
+            Ok(file) => Ok(Some(serde_json::from_reader(io::BufReader::new(file))?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Record this metadata for the base backup in `backup_data_dir`. This is
+    /// written to a temporary file first so that it appears complete.
+    async fn write(&self, backup_data_dir: &Path) -> Result<(), BackupError> {
+        let metadata_file = metadata_file(backup_data_dir);
+        let metadata_file_tmp = metadata_file.with_extension("json.tmp");
+        fs::write(&metadata_file_tmp, serde_json::to_vec_pretty(self)?).await?;
+        fs::rename(&metadata_file_tmp, &metadata_file).await?;
+        Ok(())
+    }
+}
+
+/// A location in the write-ahead log, a.k.a. a Log Sequence Number. This is
+/// formatted as PostgreSQL does, e.g. `16/B374D848`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u64);
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xFFFF_FFFF)
+    }
+}
+
+impl std::str::FromStr for Lsn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid LSN: {s:?}");
+        let (hi, lo) = s.split_once('/').ok_or_else(invalid)?;
+        let hi = u32::from_str_radix(hi, 16).map_err(|_| invalid())?;
+        let lo = u32::from_str_radix(lo, 16).map_err(|_| invalid())?;
+        Ok(Self((u64::from(hi) << 32) | u64::from(lo)))
+    }
+}
+
+impl serde::Serialize for Lsn {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::util::serde_display::serialize(self, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Lsn {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::util::serde_display::deserialize(deserializer)
+    }
+}
+
+impl Backup {
+    /// Prepare for an incremental backup.
+    ///
+    /// Returns the manifest of the latest base backup, upon which the new
+    /// incremental backup can be based, or `None` if a full backup must be
+    /// taken instead. This turns on WAL summarization if necessary, but then
+    /// the latest base backup is not covered by WAL summaries, so a full backup
+    /// is needed this time around.
+    async fn prepare_incremental(
+        &self,
+        resource: &'_ HeldResource,
+        options: &BaseBackupOptions,
+    ) -> Result<Option<PathBuf>, BackupError> {
+        let (runtime, pool) = match resource {
+            Left(resource) => (resource.facet().runtime()?, resource.facet().pool(None)?),
+            Right(resource) => (resource.facet().runtime()?, resource.facet().pool(None)?),
+        };
+        // Compare major versions only so that, e.g., 17beta2 qualifies.
+        if version::PartialVersion::Post10m(17)
+            .compare(runtime.version)
+            .is_lt()
+        {
+            return Err(BackupError::ConfigError(format!(
+                "Incremental backups need PostgreSQL 17 or later; runtime is {}",
+                runtime.version
+            )));
+        }
+        if options.format != Format::Plain {
+            return Err(BackupError::ConfigError(
+                "Incremental backups must be in plain format".into(),
+            ));
+        }
+
+        // Ensure that `summarize_wal` is on. This needs only a reload.
+        match SUMMARIZE_WAL.get(&pool).await? {
+            Some(config::Value::Boolean(true)) => {
+                log::debug!("{SUMMARIZE_WAL:?} already on");
+            }
+            Some(_) => {
+                log::info!("Setting {SUMMARIZE_WAL:?} to 'on'");
+                SUMMARIZE_WAL.set(&pool, true).await?;
+                config::reload(&pool).await?;
+                log::warn!(concat!(
+                    "WAL summarization was off, so earlier backups cannot be used as ",
+                    "a basis for an incremental backup; taking a full backup instead."
+                ));
+                return Ok(None);
+            }
+            None => {
+                return Err(BackupError::ConfigError(
+                    "WAL summarization is not supported; cannot proceed".into(),
+                ))
+            }
+        }
+
+        // Find the latest base backup. It must be a plain backup with a
+        // manifest.
+        let latest = block_in_place(|| list(&self.backup_dir))?.pop();
+        match latest {
+            Some(latest) if latest.join(BACKUP_MANIFEST_NAME).is_file() => {
+                log::info!("Taking incremental backup based on {}", latest.display());
+                Ok(Some(latest.join(BACKUP_MANIFEST_NAME)))
+            }
+            Some(latest) => {
+                log::warn!(
+                    "Base backup {} has no manifest, e.g. it is in tar format; \
+                     taking a full backup instead.",
+                    latest.display()
+                );
+                Ok(None)
+            }
+            None => {
+                log::info!("No earlier base backup found; taking a full backup instead.");
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// List the base backups in `backup_dir`, oldest first.
+pub fn list<P: AsRef<Path>>(backup_dir: P) -> Result<Vec<PathBuf>, io::Error> {
+    let mut backups = backup_dir
+        .as_ref()
+        .read_dir()?
+        .filter_map(Result::ok) // Ignore errors.
+        .filter_map(|entry| match entry.file_name().to_str() {
+            Some(name) if name.starts_with(BACKUP_DATA_PREFIX) => name[BACKUP_DATA_PREFIX.len()..]
+                .parse::<u32>()
+                .ok()
+                .map(|n| (n, entry.path())),
+            Some(_) | None => None,
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Is the base backup in `backup_data_dir` an incremental backup?
+///
+/// Incremental backups have `INCREMENTAL FROM LSN` in their `backup_label`.
+pub fn is_incremental<P: AsRef<Path>>(backup_data_dir: P) -> Result<bool, io::Error> {
+    match std::fs::read_to_string(backup_data_dir.as_ref().join("backup_label")) {
+        Ok(label) => Ok(label
+            .lines()
+            .any(|line| line.starts_with("INCREMENTAL FROM LSN:"))),
+        // Tar-format backups have `backup_label` inside the archive, but these
+        // are never incremental.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Find the chain of base backups needed to reconstruct the base backup in
+/// `backup_data_dir`, oldest – i.e. the full backup – first.
+///
+/// An incremental backup is always based on the base backup that precedes it,
+/// so this walks backwards from `backup_data_dir` until it finds a full backup.
+/// For a full backup, the chain contains only `backup_data_dir`.
+pub fn chain<P: AsRef<Path>>(backup_data_dir: P) -> Result<Vec<PathBuf>, BackupError> {
+    let backup_data_dir = backup_data_dir.as_ref();
+    let backup_dir = backup_data_dir.parent().ok_or_else(|| {
+        BackupError::GeneralError(format!(
+            "{} is not in a backup directory",
+            backup_data_dir.display()
+        ))
+    })?;
+    let backups = list(backup_dir)?;
+    let position = backups
+        .iter()
+        .position(|backup| backup == backup_data_dir)
+        .ok_or_else(|| {
+            BackupError::GeneralError(format!(
+                "Base backup {} not found",
+                backup_data_dir.display()
+            ))
+        })?;
+    let mut chain = Vec::new();
+    for backup in backups[..=position].iter().rev() {
+        chain.push(backup.clone());
+        if !is_incremental(backup)? {
+            chain.reverse();
+            return Ok(chain);
+        }
+    }
+    Err(BackupError::GeneralError(format!(
+        "No full base backup found for incremental backup {}",
+        backup_data_dir.display()
+    )))
+}
+
+/// Reconstruct a full base backup from a chain of backups – see [`chain`] – into
+/// `output` using `pg_combinebackup`.
+///
+/// The tablespaces in the last backup of the chain are written to the
+/// locations given in `tablespaces`, keyed by OID.
+pub fn combine<P: AsRef<Path>>(
+    runtime: &Runtime,
+    chain: &[P],
+    output: &Path,
+    tablespaces: &TablespaceMap,
+) -> Result<(), BackupError> {
+    let last = chain
+        .last()
+        .ok_or_else(|| BackupError::GeneralError("No base backups to combine".into()))?;
+    let last_tablespaces_dir = tablespaces_dir(last);
+    let status = runtime
+        .execute("pg_combinebackup")
+        .arg("--output")
+        .arg(output)
+        .args(tablespaces.0.iter().map(|(oid, location)| {
+            tablespace_mapping_arg(&last_tablespaces_dir.join(oid.to_string()), location)
+        }))
+        .args(chain.iter().map(AsRef::as_ref))
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(status)?
+    }
+}
+
+/// Options for [`Backup::do_base_backup`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BaseBackupOptions {
+    pub format: Format,
+    /// Take an incremental backup based on the latest base backup, if
+    /// possible. This needs PostgreSQL 17 or later, and plain format.
+    pub incremental: bool,
+}
+
+/// The format of a base backup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// A copy of the cluster's data directory. Can be used as-is.
+    #[default]
+    Plain,
+    /// Tar archives, optionally compressed. These must be unpacked before use.
+    Tar(Option<Compression>),
+}
+
+impl Format {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Tar(_) => "tar",
+        }
+    }
+}
+
+/// Compression for a tar-format base backup.
+///
+/// This has the same textual form as `pg_basebackup`'s `--compress` option,
+/// i.e. `[{client|server}-]METHOD[:LEVEL]`, e.g. `server-zstd:9`. Without a
+/// location, compression is done by the client. Compression by the server,
+/// and the `lz4` and `zstd` methods, need PostgreSQL 15 or later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    pub method: CompressionMethod,
+    pub location: CompressionLocation,
+    pub level: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Gzip,
+    Lz4,
+    Zstd,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionLocation {
+    #[default]
+    Client,
+    Server,
+}
+
+impl CompressionMethod {
+    /// The file name extension used for archives compressed with this method.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompressionMethod::Gzip => "gz",
+            CompressionMethod::Lz4 => "lz4",
+            CompressionMethod::Zstd => "zst",
+        }
+    }
+}
+
+impl Compression {
+    /// Arguments to give to `pg_basebackup` from the given version.
+    fn args(&self, version: &version::Version) -> Result<Vec<OsString>, BackupError> {
+        if version::PartialVersion::Post10m(15)
+            .compare(*version)
+            .is_ge()
+        {
+            Ok(vec![format!("--compress={self}").into()])
+        } else if self.method == CompressionMethod::Gzip
+            && self.location == CompressionLocation::Client
+        {
+            // Before PostgreSQL 15 only gzip compression is available, and only
+            // in the client.
+            let mut args = vec!["--gzip".into()];
+            if let Some(level) = self.level {
+                args.push(format!("--compress={level}").into());
+            }
+            Ok(args)
+        } else {
+            Err(BackupError::ConfigError(format!(
+                "Compression {self} needs PostgreSQL 15 or later; runtime is {version}"
+            )))
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match self.location {
+            CompressionLocation::Client => "client",
+            CompressionLocation::Server => "server",
+        };
+        let method = match self.method {
+            CompressionMethod::Gzip => "gzip",
+            CompressionMethod::Lz4 => "lz4",
+            CompressionMethod::Zstd => "zstd",
+        };
+        match self.level {
+            Some(level) => write!(f, "{location}-{method}:{level}"),
+            None => write!(f, "{location}-{method}"),
+        }
+    }
+}
+
+impl serde::Serialize for Compression {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::util::serde_display::serialize(self, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Compression {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::util::serde_display::deserialize(deserializer)
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, level) = match s.split_once(':') {
+            Some((spec, level)) => (
+                spec,
+                Some(
+                    level
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid compression level: {level:?}"))?,
+                ),
+            ),
+            None => (s, None),
+        };
+        let (location, method) = match spec.split_once('-') {
+            Some(("client", method)) => (CompressionLocation::Client, method),
+            Some(("server", method)) => (CompressionLocation::Server, method),
+            Some((location, _)) => Err(format!("invalid compression location: {location:?}"))?,
+            None => (CompressionLocation::Client, spec),
+        };
+        let method = match method {
+            "gzip" => CompressionMethod::Gzip,
+            "lz4" => CompressionMethod::Lz4,
+            "zstd" => CompressionMethod::Zstd,
+            _ => Err(format!("invalid compression method: {method:?}"))?,
+        };
+        Ok(Self { method, location, level })
+    }
+}
+
+/// Build a `--tablespace-mapping` argument for `pg_basebackup`. Any `=` in
+/// either directory name must be escaped with a backslash.
+fn tablespace_mapping_arg(old: &Path, new: &Path) -> OsString {
+    let mut arg = b"--tablespace-mapping=".to_vec();
+    let escape = |arg: &mut Vec<u8>, path: &Path| {
+        for &byte in path.as_os_str().as_bytes() {
+            if byte == b'=' {
+                arg.push(b'\\');
+            }
+            arg.push(byte);
+        }
+    };
+    escape(&mut arg, old);
+    arg.push(b'=');
+    escape(&mut arg, new);
+    OsString::from_vec(arg)
+}
+
+/// The directory in which the tablespaces for the base backup in
+/// `backup_data_dir` are stored, if the cluster had any tablespaces.
+pub fn tablespaces_dir<P: AsRef<Path>>(backup_data_dir: P) -> PathBuf {
+    let backup_data_dir = backup_data_dir.as_ref();
+    let mut name = backup_data_dir.file_name().unwrap_or_default().to_owned();
+    name.push(BACKUP_TABLESPACES_SUFFIX);
+    backup_data_dir.with_file_name(name)
+}
+
+// ----------------------------------------------------------------------------
+
+/// A tablespace, other than the built-in `pg_default` and `pg_global`
+/// tablespaces which live in the cluster's data directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tablespace {
+    pub oid: u32,
+    pub name: String,
+    pub location: PathBuf,
+}
+
+impl Tablespace {
+    pub async fn list(pool: &sqlx::PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        let rows: Vec<(i64, String, String)> = sqlx::query_as(
+            r"
+                SELECT oid::int8, spcname::text, pg_tablespace_location(oid)
+                  FROM pg_catalog.pg_tablespace
+                 WHERE spcname NOT IN ('pg_default', 'pg_global')
+                 ORDER BY oid
+            ",
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter()
+            .map(|(oid, name, location)| {
+                Ok(Self {
+                    oid: u32::try_from(oid).map_err(|err| sqlx::Error::Decode(err.into()))?,
+                    name,
+                    location: location.into(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A mapping from tablespace OID to location, as recorded with a base backup.
+///
+/// This is read and written in the same format as PostgreSQL's own
+/// `tablespace_map` file: one line per tablespace, the OID then a space then
+/// the location. Backslashes, carriage returns, and newlines in the location
+/// are escaped with a backslash.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TablespaceMap(pub BTreeMap<u32, PathBuf>);
+
+impl TablespaceMap {
+    /// Read the tablespace map recorded for the base backup in
+    /// `backup_data_dir`. If no map was recorded – i.e. the cluster had no
+    /// tablespaces – this returns an empty map.
+    pub fn for_backup<P: AsRef<Path>>(backup_data_dir: P) -> Result<Self, BackupError> {
+        let path = tablespaces_dir(backup_data_dir).join(TABLESPACE_MAP_NAME);
+        match std::fs::read(path) {
+            Ok(data) => Self::parse(&data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, BackupError> {
+        let invalid = || BackupError::GeneralError("Invalid tablespace map".into());
+        let mut map = BTreeMap::new();
+        let mut bytes = data.iter().copied().peekable();
+        while bytes.peek().is_some() {
+            let oid: Vec<u8> = bytes.by_ref().take_while(|&byte| byte != b' ').collect();
+            let oid = std::str::from_utf8(&oid)
+                .ok()
+                .and_then(|oid| oid.parse::<u32>().ok())
+                .ok_or_else(invalid)?;
+            let mut location = Vec::new();
+            loop {
+                match bytes.next() {
+                    Some(b'\\') => location.push(bytes.next().ok_or_else(invalid)?),
+                    Some(b'\n') | None => break,
+                    Some(byte) => location.push(byte),
+                }
+            }
+            map.insert(oid, OsString::from_vec(location).into());
+        }
+        Ok(Self(map))
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (oid, location) in &self.0 {
+            data.extend_from_slice(oid.to_string().as_bytes());
+            data.push(b' ');
+            for &byte in location.as_os_str().as_bytes() {
+                if matches!(byte, b'\\' | b'\r' | b'\n') {
+                    data.push(b'\\');
+                }
+                data.push(byte);
+            }
+            data.push(b'\n');
+        }
+        data
+    }
+
+    async fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        fs::write(path, self.serialize()).await
+    }
+}
+
+impl From<&[Tablespace]> for TablespaceMap {
+    fn from(tablespaces: &[Tablespace]) -> Self {
+        Self(
+            tablespaces
+                .iter()
+                .map(|tablespace| (tablespace.oid, tablespace.location.clone()))
+                .collect(),
+        )
+    }
+}
+
 // ----------------------------------------------------------------------------
 
 static ARCHIVE_MODE: config::Parameter = config::Parameter("archive_mode");
 static ARCHIVE_COMMAND: config::Parameter = config::Parameter("archive_command");
 static ARCHIVE_LIBRARY: config::Parameter = config::Parameter("archive_library");
+static SUMMARIZE_WAL: config::Parameter = config::Parameter("summarize_wal");
 static WAL_LEVEL: config::Parameter = config::Parameter("wal_level");
 
 // Successful backups have this directory name prefix.
@@ -197,6 +969,23 @@ pub static BACKUP_DATA_PREFIX: &str = "data.";
 // In-progress backups have this directory name prefix.
 static BACKUP_DATA_PREFIX_TMP: &str = ".tmp.data.";
 
+// Tablespaces for a backup are in a directory named after the backup's data
+// directory with this suffix.
+static BACKUP_TABLESPACES_SUFFIX: &str = ".tablespaces";
+
+// In-progress tablespace backups have this directory name prefix.
+static BACKUP_TABLESPACES_PREFIX_TMP: &str = ".tmp.tablespaces.";
+
+// The tablespace mapping is recorded in this file in the tablespaces directory.
+static TABLESPACE_MAP_NAME: &str = "tablespace_map";
+
+// The manifest that `pg_basebackup` writes into a plain-format backup.
+static BACKUP_MANIFEST_NAME: &str = "backup_manifest";
+
+// Metadata for a backup is in a file named after the backup's data directory
+// with this suffix.
+static BACKUP_METADATA_SUFFIX: &str = ".json";
+
 // Coordinating lock for working in the backup directory.
 static BACKUP_LOCK_NAME: &str = ".lock";
 
@@ -218,6 +1007,10 @@ pub enum BackupError {
     CommandError(ExitStatus),
     #[error("Database error")]
     SqlxError(#[from] cluster::sqlx::Error),
+    #[error(transparent)]
+    VersionError(#[from] version::VersionError),
+    #[error("Metadata error")]
+    JsonError(#[from] serde_json::Error),
 }
 
 impl From<ExitStatus> for BackupError {
@@ -225,3 +1018,91 @@ impl From<ExitStatus> for BackupError {
         Self::CommandError(error)
     }
 }
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{chain, Compression, CompressionLocation::*, CompressionMethod::*, TablespaceMap};
+    use crate::version::Version;
+
+    #[test]
+    fn chain_walks_back_to_full_backup() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup = |n: u32, incremental: bool| {
+            let dir = backup_dir.path().join(format!("data.{n:010}"));
+            std::fs::create_dir(&dir).unwrap();
+            let label = if incremental {
+                "START WAL LOCATION: 0/4000028\nINCREMENTAL FROM LSN: 0/2000028\n"
+            } else {
+                "START WAL LOCATION: 0/2000028\n"
+            };
+            std::fs::write(dir.join("backup_label"), label).unwrap();
+            dir
+        };
+        let b1 = backup(1, false);
+        let b2 = backup(2, false);
+        let b3 = backup(3, true);
+        let b5 = backup(5, true);
+        assert_eq!(chain(&b1).unwrap(), vec![b1.clone()]);
+        assert_eq!(chain(&b2).unwrap(), vec![b2.clone()]);
+        assert_eq!(chain(&b5).unwrap(), vec![b2, b3, b5]);
+    }
+
+    #[test]
+    fn compression_parse_and_display() {
+        let compression: Compression = "server-zstd:9".parse().unwrap();
+        assert_eq!(
+            compression,
+            Compression { method: Zstd, location: Server, level: Some(9) }
+        );
+        assert_eq!(compression.to_string(), "server-zstd:9");
+        let compression: Compression = "gzip".parse().unwrap();
+        assert_eq!(
+            compression,
+            Compression { method: Gzip, location: Client, level: None }
+        );
+        assert_eq!(compression.to_string(), "client-gzip");
+        assert!("bzip2".parse::<Compression>().is_err());
+        assert!("elsewhere-gzip".parse::<Compression>().is_err());
+        assert!("gzip:high".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn compression_args_depend_on_version() {
+        let gzip = Compression { method: Gzip, location: Client, level: Some(5) };
+        assert_eq!(
+            gzip.args(&Version::Post10(14, 3)).unwrap(),
+            vec!["--gzip", "--compress=5"]
+        );
+        assert_eq!(
+            gzip.args(&Version::Post10(15, 0)).unwrap(),
+            vec!["--compress=client-gzip:5"]
+        );
+        let zstd = Compression { method: Zstd, location: Server, level: None };
+        assert!(zstd.args(&Version::Post10(14, 3)).is_err());
+        assert_eq!(
+            zstd.args(&Version::Post10(16, 1)).unwrap(),
+            vec!["--compress=server-zstd"]
+        );
+    }
+
+    #[test]
+    fn tablespace_map_round_trip() {
+        let map = TablespaceMap(
+            [
+                (16384, "/some/where".into()),
+                (16385, "/with spaces/and\\back\nslash".into()),
+                (16386, "/trailing\\".into()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let data = map.serialize();
+        assert_eq!(
+            data,
+            b"16384 /some/where\n16385 /with spaces/and\\\\back\\\nslash\n16386 /trailing\\\\\n"
+        );
+        assert_eq!(TablespaceMap::parse(&data).unwrap(), map);
+    }
+}
@@ -0,0 +1,192 @@
+//! Connect to a [`Cluster`] with [`sqlx`], [`postgres`], or [`tokio_postgres`].
+
+use std::time::Duration;
+
+use super::{Cluster, ClusterError, DATABASE_POSTGRES};
+
+/// The application name reported to PostgreSQL by default.
+pub static APPLICATION_NAME: &str = "pgdo";
+
+/// Build a connection – or pool of connections – to a [`Cluster`].
+///
+/// Create one with [`Cluster::connection`]. By default this connects to
+/// [`DATABASE_POSTGRES`] as the current user, with [`APPLICATION_NAME`] as the
+/// application name.
+///
+/// Pool size, idle timeout, and statement cache capacity are only relevant to
+/// [`pool`][Self::pool]; they are ignored by the other connection methods.
+#[derive(Clone, Debug)]
+pub struct ConnectionBuilder<'a> {
+    cluster: &'a Cluster,
+    database: &'a str,
+    role: Option<&'a str>,
+    application_name: &'a str,
+    connect_timeout: Option<Duration>,
+    statement_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    min_connections: Option<u32>,
+    max_connections: Option<u32>,
+    statement_cache_capacity: Option<usize>,
+}
+
+impl<'a> ConnectionBuilder<'a> {
+    pub(super) fn new(cluster: &'a Cluster) -> Self {
+        Self {
+            cluster,
+            database: DATABASE_POSTGRES,
+            role: None,
+            application_name: APPLICATION_NAME,
+            connect_timeout: None,
+            statement_timeout: None,
+            idle_timeout: None,
+            min_connections: None,
+            max_connections: None,
+            statement_cache_capacity: None,
+        }
+    }
+
+    /// The database to connect to.
+    #[must_use]
+    pub fn database(mut self, database: &'a str) -> Self {
+        self.database = database;
+        self
+    }
+
+    /// The role to connect as. Defaults to the current user.
+    #[must_use]
+    pub fn role(mut self, role: &'a str) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// The application name to report to PostgreSQL.
+    #[must_use]
+    pub fn application_name(mut self, application_name: &'a str) -> Self {
+        self.application_name = application_name;
+        self
+    }
+
+    /// How long to wait for a connection. For a pool, this is how long to wait
+    /// to acquire a connection from the pool.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort any statement that takes longer than this, i.e. set PostgreSQL's
+    /// `statement_timeout` for each session.
+    #[must_use]
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Close pooled connections that have been idle for this long.
+    #[must_use]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// The minimum number of connections that a pool maintains.
+    #[must_use]
+    pub fn min_connections(mut self, min: u32) -> Self {
+        self.min_connections = Some(min);
+        self
+    }
+
+    /// The maximum number of connections that a pool can open.
+    #[must_use]
+    pub fn max_connections(mut self, max: u32) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// The number of prepared statements to cache per pooled connection.
+    #[must_use]
+    pub fn statement_cache_capacity(mut self, capacity: usize) -> Self {
+        self.statement_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// The role to connect as, falling back to the current user.
+    fn role_name(&self) -> Result<String, ClusterError> {
+        match self.role {
+            Some(role) => Ok(role.to_owned()),
+            None => Ok(crate::util::current_user()?),
+        }
+    }
+
+    /// Create a lazy SQLx pool.
+    ///
+    /// Although it's possible to call this anywhere, at runtime it needs a
+    /// Tokio context to work; see [`Cluster::pool`].
+    pub fn pool(&self) -> Result<sqlx::PgPool, ClusterError> {
+        let mut connect = sqlx::postgres::PgConnectOptions::new()
+            .socket(&self.cluster.datadir)
+            .database(self.database)
+            .username(&self.role_name()?)
+            .application_name(self.application_name);
+        if let Some(timeout) = self.statement_timeout {
+            connect = connect.options([("statement_timeout", timeout.as_millis().to_string())]);
+        }
+        if let Some(capacity) = self.statement_cache_capacity {
+            connect = connect.statement_cache_capacity(capacity);
+        }
+        let mut pool = sqlx::postgres::PgPoolOptions::new();
+        if let Some(timeout) = self.connect_timeout {
+            pool = pool.acquire_timeout(timeout);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            pool = pool.idle_timeout(timeout);
+        }
+        if let Some(min) = self.min_connections {
+            pool = pool.min_connections(min);
+        }
+        if let Some(max) = self.max_connections {
+            pool = pool.max_connections(max);
+        }
+        Ok(pool.connect_lazy_with(connect))
+    }
+
+    /// Connect with a blocking [`postgres::Client`].
+    ///
+    /// Do not call this from within an asynchronous context; use
+    /// [`client_async`][Self::client_async] instead.
+    pub fn client(&self) -> Result<postgres::Client, ClusterError> {
+        let config: postgres::Config = self.config()?.into();
+        Ok(config.connect(postgres::NoTls)?)
+    }
+
+    /// Connect with a [`tokio_postgres::Client`].
+    ///
+    /// The connection itself is spawned onto the current Tokio runtime, and
+    /// runs until the client is dropped. Errors on the connection are logged.
+    pub async fn client_async(&self) -> Result<tokio_postgres::Client, ClusterError> {
+        let (client, connection) = self.config()?.connect(tokio_postgres::NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                log::error!("Connection error: {err}");
+            }
+        });
+        Ok(client)
+    }
+
+    /// Configuration common to [`postgres`] and [`tokio_postgres`].
+    fn config(&self) -> Result<tokio_postgres::Config, ClusterError> {
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host_path(&self.cluster.datadir)
+            .dbname(self.database)
+            .user(self.role_name()?)
+            .application_name(self.application_name);
+        if let Some(timeout) = self.connect_timeout {
+            config.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.statement_timeout {
+            config.options(format!("-c statement_timeout={}", timeout.as_millis()));
+        }
+        Ok(config)
+    }
+}
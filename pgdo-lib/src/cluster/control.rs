@@ -0,0 +1,121 @@
+//! Read the state of a cluster from its control file, `global/pg_control`.
+//!
+//! The layout is defined in PostgreSQL's `src/include/catalog/pg_control.h`.
+//! The file is written in the server's native byte order. Only the state is
+//! read here; it follows the system identifier and two version numbers, and
+//! has been in the same place, with the same values, since PostgreSQL 9.0.
+
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// The offset of the state in the control file.
+const STATE_OFFSET: usize = 16;
+
+/// The state of a cluster's database system, as recorded in its control file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatabaseState {
+    /// The server is starting up.
+    Starting,
+    /// The server shut down cleanly.
+    ShutDown,
+    /// The server shut down cleanly while in recovery, e.g. as a standby.
+    ShutDownInRecovery,
+    /// The server is shutting down.
+    ShuttingDown,
+    /// The server is performing crash recovery.
+    InCrashRecovery,
+    /// The server is in archive recovery, e.g. as a standby.
+    InArchiveRecovery,
+    /// The server is running.
+    InProduction,
+    /// A state we don't recognise.
+    Other(u32),
+}
+
+impl DatabaseState {
+    fn from_code(code: u32) -> Self {
+        match code {
+            0 => Self::Starting,
+            1 => Self::ShutDown,
+            2 => Self::ShutDownInRecovery,
+            3 => Self::ShuttingDown,
+            4 => Self::InCrashRecovery,
+            5 => Self::InArchiveRecovery,
+            6 => Self::InProduction,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Parse the state from the contents of a control file.
+    pub fn parse(control: &[u8]) -> Option<Self> {
+        let code = control.get(STATE_OFFSET..STATE_OFFSET + 4)?;
+        Some(Self::from_code(u32::from_ne_bytes(code.try_into().ok()?)))
+    }
+
+    /// Read the state from the control file in the given data directory.
+    /// Returns [`None`] if there is no control file.
+    pub fn read<P: AsRef<Path>>(datadir: P) -> io::Result<Option<Self>> {
+        match fs::read(datadir.as_ref().join("global").join("pg_control")) {
+            Ok(control) => Self::parse(&control)
+                .map(Some)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "pg_control: too short")),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// When the server is not running, did it stop without shutting down
+    /// cleanly? If so, it will perform crash recovery when next started.
+    pub fn needs_crash_recovery(self) -> bool {
+        matches!(
+            self,
+            Self::ShuttingDown | Self::InCrashRecovery | Self::InProduction
+        )
+    }
+}
+
+impl fmt::Display for DatabaseState {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Starting => write!(fmt, "starting up"),
+            Self::ShutDown => write!(fmt, "shut down"),
+            Self::ShutDownInRecovery => write!(fmt, "shut down in recovery"),
+            Self::ShuttingDown => write!(fmt, "shutting down"),
+            Self::InCrashRecovery => write!(fmt, "in crash recovery"),
+            Self::InArchiveRecovery => write!(fmt, "in archive recovery"),
+            Self::InProduction => write!(fmt, "in production"),
+            Self::Other(code) => write!(fmt, "unknown ({code})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DatabaseState::{self, *};
+
+    fn control(code: u32) -> Vec<u8> {
+        let mut control = vec![0xAA; 16];
+        control.extend(code.to_ne_bytes());
+        control.extend([0; 8]);
+        control
+    }
+
+    #[test]
+    fn parse_state() {
+        assert_eq!(DatabaseState::parse(&control(1)), Some(ShutDown));
+        assert_eq!(DatabaseState::parse(&control(4)), Some(InCrashRecovery));
+        assert_eq!(DatabaseState::parse(&control(6)), Some(InProduction));
+        assert_eq!(DatabaseState::parse(&control(99)), Some(Other(99)));
+        assert_eq!(DatabaseState::parse(&control(6)[..19]), None);
+    }
+
+    #[test]
+    fn unclean_states_need_crash_recovery() {
+        assert!(InProduction.needs_crash_recovery());
+        assert!(ShuttingDown.needs_crash_recovery());
+        assert!(InCrashRecovery.needs_crash_recovery());
+        assert!(!ShutDown.needs_crash_recovery());
+        assert!(!ShutDownInRecovery.needs_crash_recovery());
+        assert!(!InArchiveRecovery.needs_crash_recovery());
+    }
+}
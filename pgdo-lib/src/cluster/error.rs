@@ -1,4 +1,4 @@
-use std::{io, process::Output};
+use std::{io, path::PathBuf, process::Output};
 
 use crate::{cluster, runtime, util, version};
 
@@ -6,8 +6,6 @@ use crate::{cluster, runtime, util, version};
 pub enum ClusterError {
     #[error("Input/output error")]
     IoError(#[from] io::Error),
-    #[error("PostgreSQL version not supported: {0}")]
-    UnsupportedVersion(version::Version),
     #[error("PostgreSQL version not known")]
     VersionError(#[from] version::VersionError),
     #[error("PostgreSQL runtime not found for version {0}")]
@@ -28,4 +26,29 @@ pub enum ClusterError {
     CurrentUserError(#[from] util::CurrentUserError),
     #[error("URL error")]
     UrlError(#[from] url::ParseError),
+    #[error(
+        "Extension {name:?}{} is not available in PostgreSQL runtime at {}",
+        version.as_ref().map(|version| format!(" version {version:?}")).unwrap_or_default(),
+        bindir.display(),
+    )]
+    #[diagnostic(help("Install the extension for this runtime, or select another runtime"))]
+    ExtensionNotAvailable { name: String, version: Option<String>, bindir: PathBuf },
+    #[error(
+        "PostgreSQL runtime {pinned} at {}, which created this cluster, is no longer available",
+        bindir.display(),
+    )]
+    #[diagnostic(help(
+        "Reinstall that runtime, or disable strict runtime selection to use another"
+    ))]
+    RuntimePinMissing { bindir: PathBuf, pinned: version::Version },
+    #[error(
+        "PostgreSQL runtime at {}, which created this cluster, has changed from {pinned} to {found}",
+        bindir.display(),
+    )]
+    #[diagnostic(help("Pin the cluster to the new runtime, or disable strict runtime selection"))]
+    RuntimePinChanged {
+        bindir: PathBuf,
+        pinned: version::Version,
+        found: version::Version,
+    },
 }
@@ -0,0 +1,256 @@
+//! Manage extensions in a [`Cluster`].
+
+use postgres::error::SqlState;
+use postgres_protocol::escape::{escape_identifier, escape_literal};
+
+use super::{config, Cluster, ClusterError};
+use crate::coordinate::State::{self, *};
+
+/// The setting used to preload libraries when the server starts.
+pub static SHARED_PRELOAD_LIBRARIES: config::Parameter =
+    config::Parameter("shared_preload_libraries");
+
+/// Well-known extensions that must be named in [`SHARED_PRELOAD_LIBRARIES`]
+/// before they can be used. There is no way to discover this from the catalog,
+/// so this list is necessarily incomplete; use [`Preload::Library`] for other
+/// extensions that need preloading.
+pub static PRELOAD_EXTENSIONS: &[&str] = &[
+    "citus",
+    "pg_cron",
+    "pg_qualstats",
+    "pg_squeeze",
+    "pg_stat_statements",
+    "pg_wait_sampling",
+    "pgaudit",
+    "timescaledb",
+];
+
+/// An extension as described by [`pg_available_extensions`][pae].
+///
+/// [pae]: https://www.postgresql.org/docs/current/view-pg-available-extensions.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extension {
+    pub name: String,
+    pub default_version: Option<String>,
+    /// The version installed in the database, if installed.
+    pub installed_version: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Extension {
+    pub fn is_installed(&self) -> bool {
+        self.installed_version.is_some()
+    }
+
+    /// Does this extension need to be in [`SHARED_PRELOAD_LIBRARIES`]? This
+    /// is only known for [`PRELOAD_EXTENSIONS`].
+    pub fn needs_preload(&self) -> bool {
+        PRELOAD_EXTENSIONS.contains(&self.name.as_str())
+    }
+}
+
+/// Which library, if any, must be in [`SHARED_PRELOAD_LIBRARIES`] before an
+/// extension can be installed; see [`Cluster::ensure_extension`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Preload<'a> {
+    /// Preload the extension's library only if it is one of the well-known
+    /// [`PRELOAD_EXTENSIONS`]. Its library has the same name as the extension.
+    #[default]
+    WellKnown,
+    /// Preload the given library, e.g. the extension's own name.
+    Library(&'a str),
+}
+
+impl<'a> Preload<'a> {
+    /// The library to preload for the extension `name`, if any.
+    fn library(self, name: &'a str) -> Option<&'a str> {
+        match self {
+            Self::WellKnown if PRELOAD_EXTENSIONS.contains(&name) => Some(name),
+            Self::WellKnown => None,
+            Self::Library(library) => Some(library),
+        }
+    }
+}
+
+/// The outcome of [`Cluster::ensure_extension`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExtensionState {
+    /// The extension was already installed at the requested version.
+    Unmodified,
+    /// The extension was installed or updated.
+    Modified,
+    /// The extension must be preloaded, so [`SHARED_PRELOAD_LIBRARIES`] has
+    /// been updated. Restart the cluster then try again.
+    RestartRequired,
+}
+
+impl Cluster {
+    /// The extensions available to `database`, and the versions installed.
+    ///
+    /// When the database is not specified, uses
+    /// [`DATABASE_POSTGRES`][super::DATABASE_POSTGRES].
+    pub fn extensions(&self, database: Option<&str>) -> Result<Vec<Extension>, ClusterError> {
+        let mut client = self.connect(database)?;
+        let rows = client.query(
+            "SELECT name, default_version, installed_version, comment \
+               FROM pg_catalog.pg_available_extensions ORDER BY name",
+            &[],
+        )?;
+        Ok(rows
+            .iter()
+            .map(|row| Extension {
+                name: row.get(0),
+                default_version: row.get(1),
+                installed_version: row.get(2),
+                comment: row.get(3),
+            })
+            .collect())
+    }
+
+    /// Ensure that the named extension is installed in `database`, at the
+    /// given version or, if not specified, the default version. Dependencies
+    /// are installed too.
+    ///
+    /// If the extension needs to be preloaded – according to `preload` – and
+    /// it is not, this adds its library to [`SHARED_PRELOAD_LIBRARIES`] and
+    /// returns [`ExtensionState::RestartRequired`]; the extension is **not**
+    /// installed. See [`resource::ClusterExclusive::ensure_extension`] to have
+    /// the restart done for you. **Note** that only the well-known
+    /// [`PRELOAD_EXTENSIONS`] are preloaded by default; other extensions that
+    /// need preloading install without error but then fail when used, unless
+    /// their library is given with [`Preload::Library`].
+    ///
+    /// Returns [`ClusterError::ExtensionNotAvailable`] if the cluster's runtime
+    /// does not provide the extension at the requested version.
+    ///
+    /// [`resource::ClusterExclusive::ensure_extension`]:
+    ///     super::resource::ClusterExclusive::ensure_extension
+    pub fn ensure_extension(
+        &self,
+        database: Option<&str>,
+        name: &str,
+        version: Option<&str>,
+        preload: Preload,
+    ) -> Result<ExtensionState, ClusterError> {
+        let mut client = self.connect(database)?;
+        let row = client.query_opt(
+            "SELECT installed_version, \
+                    coalesce($2, default_version) IN (\
+                      SELECT version FROM pg_catalog.pg_available_extension_versions v \
+                       WHERE v.name = e.name) \
+               FROM pg_catalog.pg_available_extensions e WHERE name = $1",
+            &[&name, &version],
+        )?;
+        let installed: Option<String> = match row {
+            Some(row) if row.get(1) => row.get(0),
+            _ => {
+                return Err(ClusterError::ExtensionNotAvailable {
+                    name: name.into(),
+                    version: version.map(Into::into),
+                    bindir: self.runtime()?.bindir,
+                })
+            }
+        };
+
+        let statement = match (installed, version) {
+            (Some(_), None) => return Ok(ExtensionState::Unmodified),
+            (Some(installed), Some(version)) if installed == version => {
+                return Ok(ExtensionState::Unmodified)
+            }
+            (Some(_), Some(version)) => format!(
+                "ALTER EXTENSION {} UPDATE TO {}",
+                escape_identifier(name),
+                escape_literal(version)
+            ),
+            (None, _) => {
+                if let Some(library) = preload.library(name) {
+                    let preload: String = client
+                        .query_one("SHOW shared_preload_libraries", &[])?
+                        .get(0);
+                    let mut libraries = parse_libraries(&preload);
+                    if !libraries.contains(&library) {
+                        libraries.push(library);
+                        self.set_preload_libraries(database, &libraries.join(","))?;
+                        return Ok(ExtensionState::RestartRequired);
+                    }
+                }
+                match version {
+                    Some(version) => format!(
+                        "CREATE EXTENSION {} WITH VERSION {} CASCADE",
+                        escape_identifier(name),
+                        escape_literal(version)
+                    ),
+                    None => format!("CREATE EXTENSION {} CASCADE", escape_identifier(name)),
+                }
+            }
+        };
+        client.execute(statement.as_str(), &[])?;
+        Ok(ExtensionState::Modified)
+    }
+
+    /// Remove the named extension from `database`.
+    ///
+    /// Returns [`Unmodified`] if the extension is not installed, otherwise it
+    /// returns [`Modified`]. This fails if other objects depend on it. It is
+    /// not removed from [`SHARED_PRELOAD_LIBRARIES`].
+    pub fn remove_extension(
+        &self,
+        database: Option<&str>,
+        name: &str,
+    ) -> Result<State, ClusterError> {
+        let statement = format!("DROP EXTENSION {}", escape_identifier(name));
+        match self.connect(database)?.execute(statement.as_str(), &[]) {
+            Err(err) if err.code() == Some(&SqlState::UNDEFINED_OBJECT) => Ok(Unmodified),
+            Err(err) => Err(err)?,
+            Ok(_) => Ok(Modified),
+        }
+    }
+
+    /// Update [`SHARED_PRELOAD_LIBRARIES`]. This takes effect after a restart.
+    fn set_preload_libraries(
+        &self,
+        database: Option<&str>,
+        libraries: &str,
+    ) -> Result<(), ClusterError> {
+        // This module's API is synchronous, but `config` is async.
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        rt.block_on(async {
+            let pool = self.pool(database)?;
+            let result = SHARED_PRELOAD_LIBRARIES.set(&pool, libraries).await;
+            pool.close().await;
+            Ok(result?)
+        })
+    }
+}
+
+/// Parse a library list such as `shared_preload_libraries`.
+fn parse_libraries(libraries: &str) -> Vec<&str> {
+    libraries
+        .split(',')
+        .map(|library| library.trim().trim_matches('"'))
+        .filter(|library| !library.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_libraries, Preload};
+
+    #[test]
+    fn preload_library() {
+        assert_eq!(Preload::WellKnown.library("pg_cron"), Some("pg_cron"));
+        assert_eq!(Preload::WellKnown.library("cube"), None);
+        assert_eq!(Preload::Library("foo").library("cube"), Some("foo"));
+    }
+
+    #[test]
+    fn parse_libraries_splits_and_trims() {
+        assert_eq!(parse_libraries(""), Vec::<&str>::new());
+        assert_eq!(
+            parse_libraries(r#"pg_cron, "Foo Bar",auto_explain"#),
+            vec!["pg_cron", "Foo Bar", "auto_explain"]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Metadata that pgdo records in a cluster's data directory.
+//!
+//! PostgreSQL itself records only the major version of the server that
+//! created a cluster, in `PG_VERSION`. That's not enough to choose between,
+//! say, two builds of PostgreSQL 16 with different extensions available, so
+//! pgdo also records the exact runtime used to create the cluster.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::ClusterError;
+use crate::{runtime::Runtime, version::Version};
+
+/// The name of the metadata file in a cluster's data directory.
+pub static METADATA_FILE: &str = "pgdo.json";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The runtime used to create the cluster, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimePin>,
+}
+
+/// The exact runtime used to create a cluster.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimePin {
+    pub bindir: PathBuf,
+    #[serde(with = "crate::util::serde_display")]
+    pub version: Version,
+}
+
+impl From<&Runtime> for RuntimePin {
+    fn from(runtime: &Runtime) -> Self {
+        Self { bindir: runtime.bindir.clone(), version: runtime.version }
+    }
+}
+
+impl Metadata {
+    /// Read the metadata from the given data directory.
+    ///
+    /// Returns [`None`] if there is no metadata file, e.g. the cluster was
+    /// created by an older version of pgdo, or by another tool.
+    pub fn read<P: AsRef<Path>>(datadir: P) -> Result<Option<Self>, ClusterError> {
+        match std::fs::read(datadir.as_ref().join(METADATA_FILE)) {
+            Ok(data) => Ok(Some(
+                serde_json::from_slice(&data).map_err(io::Error::from)?,
+            )),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Write the metadata to the given data directory.
+    ///
+    /// The file is replaced atomically, so readers never see partial metadata.
+    pub fn write<P: AsRef<Path>>(&self, datadir: P) -> Result<(), ClusterError> {
+        let datadir = datadir.as_ref();
+        let mut file = tempfile::NamedTempFile::new_in(datadir)?;
+        serde_json::to_writer_pretty(&mut file, self).map_err(io::Error::from)?;
+        file.write_all(b"\n")?;
+        file.persist(datadir.join(METADATA_FILE))
+            .map_err(|err| err.error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Metadata, RuntimePin, METADATA_FILE};
+    use crate::version::Version;
+
+    type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+    #[test]
+    fn metadata_round_trip() -> TestResult {
+        let datadir = tempfile::tempdir()?;
+        assert_eq!(Metadata::read(&datadir)?, None);
+        let metadata = Metadata {
+            runtime: Some(RuntimePin {
+                bindir: "/usr/lib/postgresql/16/bin".into(),
+                version: Version::Post10(16, 4),
+            }),
+        };
+        metadata.write(&datadir)?;
+        assert_eq!(Metadata::read(&datadir)?, Some(metadata));
+        let json = std::fs::read_to_string(datadir.path().join(METADATA_FILE))?;
+        assert!(json.contains(r#""version": "16.4""#));
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+//! Read `postmaster.pid`, the lock file that PostgreSQL writes into the data
+//! directory of a running cluster.
+//!
+//! The layout is documented in PostgreSQL's `src/include/utils/pidfile.h`. The
+//! server writes the first few lines when it starts, then adds the others as
+//! it goes, so all but the first two are optional here. The status line was
+//! added in PostgreSQL 10.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{fmt, fs, io};
+
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+
+/// How long after it was last modified a `postmaster.pid` file that cannot be
+/// parsed may still be being written by a server that is starting up. After
+/// this it is assumed to have been left empty or truncated, e.g. by a power
+/// loss or by `kill -9`.
+pub const WRITE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Might a `postmaster.pid` file last modified at `modified` still be being
+/// written? See [`WRITE_GRACE_PERIOD`].
+pub fn within_grace_period(modified: SystemTime) -> bool {
+    // A modification time in the future counts as recent.
+    modified
+        .elapsed()
+        .map_or(true, |age| age < WRITE_GRACE_PERIOD)
+}
+
+/// The status of a postmaster, as recorded on the last line of
+/// `postmaster.pid`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PostmasterStatus {
+    /// Starting up, possibly performing crash recovery.
+    Starting,
+    /// Shutting down.
+    Stopping,
+    /// Running and accepting connections. A hot standby is ready too.
+    Ready,
+    /// Running as a standby but not accepting connections, i.e. with
+    /// `hot_standby` off.
+    Standby,
+    /// A status we don't recognise.
+    Other(String),
+}
+
+impl PostmasterStatus {
+    fn parse(line: &str) -> Self {
+        match line.trim() {
+            "starting" => Self::Starting,
+            "stopping" => Self::Stopping,
+            "ready" => Self::Ready,
+            "standby" => Self::Standby,
+            other => Self::Other(other.into()),
+        }
+    }
+}
+
+impl fmt::Display for PostmasterStatus {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Starting => write!(fmt, "starting"),
+            Self::Stopping => write!(fmt, "stopping"),
+            Self::Ready => write!(fmt, "ready"),
+            Self::Standby => write!(fmt, "standby"),
+            Self::Other(other) => write!(fmt, "{other}"),
+        }
+    }
+}
+
+/// The contents of a cluster's `postmaster.pid` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PidFile {
+    /// The process ID of the postmaster. This is negative when the file was
+    /// written by a single-user backend, e.g. `postgres --single`.
+    pub pid: i32,
+    /// The data directory, as given to the server.
+    pub datadir: PathBuf,
+    /// When the server started.
+    pub start_time: Option<jiff::Timestamp>,
+    /// The port on which the server is listening.
+    pub port: Option<u16>,
+    /// The first of `unix_socket_directories`, if any.
+    pub socket_dir: Option<PathBuf>,
+    /// The first of `listen_addresses`, if any.
+    pub listen_address: Option<String>,
+    /// The System V shared memory key.
+    pub shmem_key: Option<u64>,
+    /// The System V shared memory segment ID.
+    pub shmem_id: Option<u64>,
+    /// The status of the postmaster, from PostgreSQL 10.
+    pub status: Option<PostmasterStatus>,
+}
+
+impl PidFile {
+    /// Read and parse the given `postmaster.pid` file. Returns [`None`] if the
+    /// file does not exist.
+    ///
+    /// A file that cannot be parsed – e.g. it is empty because the server has
+    /// only just created it – results in an [`io::ErrorKind::InvalidData`]
+    /// error.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse().map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Does the process named in this file exist?
+    ///
+    /// This sends signal 0 to the process, which checks for its existence
+    /// without disturbing it. A process owned by another user exists too, even
+    /// though we're not permitted to signal it.
+    pub fn process_exists(&self) -> bool {
+        let pid = Pid::from_raw(self.pid.saturating_abs());
+        !matches!(kill(pid, None), Err(Errno::ESRCH))
+    }
+
+    /// Is the process named in this file a server for the given data
+    /// directory?
+    ///
+    /// After a crash or a reboot, the PID may have been reused by an unrelated
+    /// process. On Linux, the server changes into its data directory when it
+    /// starts, so we compare that with the given directory; a process that
+    /// has exited but not yet been reaped has no working directory. Elsewhere,
+    /// or if we are not permitted to look, we assume that an existing process
+    /// is the server.
+    pub fn is_server_for<P: AsRef<Path>>(&self, datadir: P) -> bool {
+        if !self.process_exists() {
+            return false;
+        }
+        if cfg!(target_os = "linux") {
+            let pid = self.pid.saturating_abs();
+            match fs::read_link(format!("/proc/{pid}/cwd")) {
+                Ok(cwd) => {
+                    let datadir = datadir.as_ref();
+                    cwd == datadir.canonicalize().unwrap_or_else(|_| datadir.into())
+                }
+                Err(err) => err.kind() == io::ErrorKind::PermissionDenied,
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Are any processes attached to the shared memory segment named in this
+    /// file? If so, server processes may still be running even though the
+    /// postmaster has gone.
+    ///
+    /// This is only known on Linux, from `/proc/sysvipc/shm`. Elsewhere, this
+    /// returns `false`.
+    pub fn shmem_in_use(&self) -> bool {
+        match self.shmem_id {
+            Some(shmem_id) if cfg!(target_os = "linux") => fs::read_to_string("/proc/sysvipc/shm")
+                .ok()
+                .and_then(|table| attachments(&table, shmem_id))
+                .is_some_and(|attachments| attachments > 0),
+            _ => false,
+        }
+    }
+}
+
+/// Find the number of attachments to the given shared memory segment in a
+/// table formatted like `/proc/sysvipc/shm`.
+fn attachments(table: &str, shmem_id: u64) -> Option<u64> {
+    let mut rows = table.lines().map(str::split_whitespace);
+    let header: Vec<&str> = rows.next()?.collect();
+    let shmid = header.iter().position(|&name| name == "shmid")?;
+    let nattch = header.iter().position(|&name| name == "nattch")?;
+    rows.map(Iterator::collect::<Vec<_>>)
+        .find(|row| row.get(shmid).and_then(|id| id.parse().ok()) == Some(shmem_id))
+        .and_then(|row| row.get(nattch)?.parse().ok())
+}
+
+impl std::str::FromStr for PidFile {
+    type Err = io::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut lines = text.lines();
+        let mut next = || lines.next().map(str::trim).filter(|line| !line.is_empty());
+        let pid = next()
+            .and_then(|line| line.parse().ok())
+            .ok_or_else(|| invalid("postmaster.pid: missing or invalid PID"))?;
+        let datadir = next()
+            .map(PathBuf::from)
+            .ok_or_else(|| invalid("postmaster.pid: missing data directory"))?;
+        let start_time = next()
+            .and_then(|line| line.parse().ok())
+            .and_then(|seconds| jiff::Timestamp::from_second(seconds).ok());
+        let port = next().and_then(|line| line.parse().ok());
+        let socket_dir = next().map(PathBuf::from);
+        let listen_address = next().map(String::from);
+        let (shmem_key, shmem_id) = match next().map(str::split_whitespace) {
+            Some(mut shmem) => (
+                shmem.next().and_then(|key| key.parse().ok()),
+                shmem.next().and_then(|id| id.parse().ok()),
+            ),
+            None => (None, None),
+        };
+        let status = next().map(PostmasterStatus::parse);
+        Ok(Self {
+            pid,
+            datadir,
+            start_time,
+            port,
+            socket_dir,
+            listen_address,
+            shmem_key,
+            shmem_id,
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use super::{attachments, within_grace_period, PidFile, PostmasterStatus, WRITE_GRACE_PERIOD};
+
+    #[test]
+    fn parse_complete_pidfile() {
+        let pidfile: PidFile = concat!(
+            "12345\n",
+            "/var/lib/postgresql/16/main\n",
+            "1718000000\n",
+            "5432\n",
+            "/var/run/postgresql\n",
+            "localhost\n",
+            "  5432001     32769\n",
+            "ready   \n",
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(pidfile.pid, 12345);
+        assert_eq!(pidfile.datadir, Path::new("/var/lib/postgresql/16/main"));
+        assert_eq!(
+            pidfile.start_time.map(jiff::Timestamp::as_second),
+            Some(1_718_000_000)
+        );
+        assert_eq!(pidfile.port, Some(5432));
+        assert_eq!(
+            pidfile.socket_dir.as_deref(),
+            Some(Path::new("/var/run/postgresql"))
+        );
+        assert_eq!(pidfile.listen_address.as_deref(), Some("localhost"));
+        assert_eq!(pidfile.shmem_key, Some(5_432_001));
+        assert_eq!(pidfile.shmem_id, Some(32769));
+        assert_eq!(pidfile.status, Some(PostmasterStatus::Ready));
+    }
+
+    #[test]
+    fn parse_partial_pidfile() {
+        // Without sockets or listen addresses, and before the status is added.
+        let pidfile: PidFile = "-99\n/data\n1718000000\n5433\n\n\n".parse().unwrap();
+        assert_eq!(pidfile.pid, -99);
+        assert_eq!(pidfile.port, Some(5433));
+        assert_eq!(pidfile.socket_dir, None);
+        assert_eq!(pidfile.listen_address, None);
+        assert_eq!(pidfile.shmem_key, None);
+        assert_eq!(pidfile.status, None);
+    }
+
+    #[test]
+    fn parse_invalid_pidfile() {
+        assert!("".parse::<PidFile>().is_err());
+        assert!("foo\n/data\n".parse::<PidFile>().is_err());
+        assert!("123\n".parse::<PidFile>().is_err());
+    }
+
+    #[test]
+    fn process_exists() {
+        let this: PidFile = format!("{}\n/data\n", std::process::id()).parse().unwrap();
+        assert!(this.process_exists());
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        let gone: PidFile = format!("{}\n/data\n", child.id()).parse().unwrap();
+        assert!(!gone.process_exists());
+        assert!(!gone.is_server_for("/data"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn is_server_for_checks_working_directory() {
+        let cwd = std::env::current_dir().unwrap();
+        let this: PidFile = format!("{}\n/data\n", std::process::id()).parse().unwrap();
+        assert!(this.is_server_for(&cwd));
+        assert!(!this.is_server_for(cwd.join("elsewhere")));
+    }
+
+    #[test]
+    fn attachments_from_table() {
+        let table = concat!(
+            "       key      shmid perms       size  cpid  lpid nattch   uid\n",
+            "   5432001      32769   600         56  1234  1240      6  1000\n",
+            "   5433001      32770   600         56  1250  1250      0  1000\n",
+        );
+        assert_eq!(attachments(table, 32769), Some(6));
+        assert_eq!(attachments(table, 32770), Some(0));
+        assert_eq!(attachments(table, 32771), None);
+        assert_eq!(attachments("", 32769), None);
+    }
+
+    #[test]
+    fn within_grace_period_only_when_recent() {
+        let now = SystemTime::now();
+        assert!(within_grace_period(now));
+        assert!(within_grace_period(now + Duration::from_mins(1)));
+        assert!(!within_grace_period(now - WRITE_GRACE_PERIOD));
+        assert!(!within_grace_period(now - Duration::from_hours(1)));
+    }
+}
@@ -8,7 +8,9 @@ use rand::Rng;
 
 use super::{
     coordinate::{resource, CoordinateError, State},
-    exists, Cluster, ClusterError,
+    exists,
+    extension::{ExtensionState, Preload},
+    Cluster, ClusterError, ConnectionBuilder, Runtime, StopOptions,
 };
 
 // ----------------------------------------------------------------------------
@@ -93,11 +95,60 @@ impl ClusterShared<'_> {
         self.cluster.running()
     }
 
+    /// Forwards to [`Cluster::runtime`].
+    pub fn runtime(&self) -> Result<Runtime, ClusterError> {
+        self.cluster.runtime()
+    }
+
+    /// Forwards to [`Cluster::in_recovery`].
+    pub fn in_recovery(&self) -> Result<bool, ClusterError> {
+        self.cluster.in_recovery()
+    }
+
+    /// Forwards to [`Cluster::promote`].
+    pub fn promote(&self) -> Result<State, ClusterError> {
+        self.cluster.promote()
+    }
+
+    /// Forwards to [`Cluster::reload`].
+    pub fn reload(&self) -> Result<State, ClusterError> {
+        self.cluster.reload()
+    }
+
+    /// Forwards to [`Cluster::pending_restart`]. It is up to the caller to
+    /// arrange a restart; the cluster is shared.
+    pub fn pending_restart(&self) -> Result<Vec<String>, ClusterError> {
+        self.cluster.pending_restart()
+    }
+
+    /// Forwards to [`Cluster::ensure_extension`]. If a restart is required, it
+    /// is up to the caller to arrange it; the cluster is shared.
+    pub fn ensure_extension(
+        &self,
+        database: Option<&str>,
+        name: &str,
+        version: Option<&str>,
+        preload: Preload,
+    ) -> Result<ExtensionState, ClusterError> {
+        self.cluster
+            .ensure_extension(database, name, version, preload)
+    }
+
+    /// Forwards to [`Cluster::connection`].
+    pub fn connection(&self) -> ConnectionBuilder<'_> {
+        self.cluster.connection()
+    }
+
     /// Forwards to [`Cluster::pool`].
     pub fn pool(&self, database: Option<&str>) -> Result<sqlx::PgPool, ClusterError> {
         self.cluster.pool(database)
     }
 
+    /// Forwards to [`Cluster::shell`].
+    pub fn shell(&self, database: Option<&str>) -> Result<ExitStatus, ClusterError> {
+        self.cluster.shell(database)
+    }
+
     /// Forwards to [`Cluster::exec`].
     pub fn exec<T: AsRef<OsStr>>(
         &self,
@@ -122,8 +173,12 @@ impl ClusterExclusive<'_> {
         self.cluster.start(options)
     }
 
-    pub fn stop(&self) -> Result<State, ClusterError> {
-        self.cluster.stop()
+    pub fn stop(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.cluster.stop(options)
+    }
+
+    pub fn restart(&self, options: StopOptions) -> Result<State, ClusterError> {
+        self.cluster.restart(options)
     }
 
     pub fn destroy(&self) -> Result<State, ClusterError> {
@@ -138,11 +193,68 @@ impl ClusterExclusive<'_> {
         self.cluster.running()
     }
 
+    /// Forwards to [`Cluster::runtime`].
+    pub fn runtime(&self) -> Result<Runtime, ClusterError> {
+        self.cluster.runtime()
+    }
+
+    /// Forwards to [`Cluster::in_recovery`].
+    pub fn in_recovery(&self) -> Result<bool, ClusterError> {
+        self.cluster.in_recovery()
+    }
+
+    /// Forwards to [`Cluster::promote`].
+    pub fn promote(&self) -> Result<State, ClusterError> {
+        self.cluster.promote()
+    }
+
+    /// Forwards to [`Cluster::reload`].
+    pub fn reload(&self) -> Result<State, ClusterError> {
+        self.cluster.reload()
+    }
+
+    /// Forwards to [`Cluster::pending_restart`].
+    pub fn pending_restart(&self) -> Result<Vec<String>, ClusterError> {
+        self.cluster.pending_restart()
+    }
+
+    /// Forwards to [`Cluster::ensure_extension`]. If the extension needs to be
+    /// preloaded, this restarts the cluster then installs the extension.
+    pub fn ensure_extension(
+        &self,
+        database: Option<&str>,
+        name: &str,
+        version: Option<&str>,
+        preload: Preload,
+    ) -> Result<ExtensionState, ClusterError> {
+        match self
+            .cluster
+            .ensure_extension(database, name, version, preload)?
+        {
+            ExtensionState::RestartRequired => {
+                self.cluster.restart(StopOptions::default())?;
+                self.cluster
+                    .ensure_extension(database, name, version, preload)
+            }
+            state => Ok(state),
+        }
+    }
+
+    /// Forwards to [`Cluster::connection`].
+    pub fn connection(&self) -> ConnectionBuilder<'_> {
+        self.cluster.connection()
+    }
+
     /// Forwards to [`Cluster::pool`].
     pub fn pool(&self, database: Option<&str>) -> Result<sqlx::PgPool, ClusterError> {
         self.cluster.pool(database)
     }
 
+    /// Forwards to [`Cluster::shell`].
+    pub fn shell(&self, database: Option<&str>) -> Result<ExitStatus, ClusterError> {
+        self.cluster.shell(database)
+    }
+
     /// Forwards to [`Cluster::exec`].
     pub fn exec<T: AsRef<OsStr>>(
         &self,
@@ -255,8 +367,8 @@ pub fn startup_if_exists(
     }
 }
 
-/// Shuts down the cluster if it is running and if there are no other concurrent
-/// users.
+/// Shuts down the cluster, with the given options, if it is running and if there
+/// are no other concurrent users.
 ///
 /// The return value has two parts: the state, [`State`], and the resource.
 ///
@@ -266,7 +378,10 @@ pub fn startup_if_exists(
 /// The resource is `Left(ResourceShared)` if the cluster is already in use –
 /// i.e. the resource passed in is returned – else `Right(ResourceExclusive)`
 /// otherwise.
-pub fn shutdown(resource: ResourceShared) -> Result<(State, HeldResource), Error> {
+pub fn shutdown(
+    resource: ResourceShared,
+    options: StopOptions,
+) -> Result<(State, HeldResource), Error> {
     match resource.try_exclusive() {
         Ok(Left(resource)) => {
             // The resource is in use by someone/something else. There's nothing
@@ -275,7 +390,7 @@ pub fn shutdown(resource: ResourceShared) -> Result<(State, HeldResource), Error
         }
         Ok(Right(resource)) => {
             // We have an exclusive lock, so we can mutate the resource.
-            match resource.facet().stop() {
+            match resource.facet().stop(options) {
                 Ok(state) => Ok((state, Right(resource))),
                 Err(err) => {
                     resource.release()?;
@@ -309,3 +424,50 @@ pub fn destroy(resource: ResourceShared) -> Result<(State, HeldResource), Error>
         Err(err) => Err(err),
     }
 }
+
+/// The outcome of [`ensure_applied`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Applied {
+    /// The configuration was reloaded and nothing more was needed.
+    Reloaded,
+    /// The cluster was restarted so that the named settings took effect.
+    Restarted(Vec<String>),
+    /// The named settings need a restart to take effect, but the cluster is in
+    /// use and so could not be restarted.
+    RestartRequired(Vec<String>),
+}
+
+/// Ensures that configuration changes, e.g. those made with `ALTER SYSTEM`, are
+/// in effect in the running cluster.
+///
+/// The configuration is always reloaded. The cluster is then restarted, with
+/// the given options, only if there are settings pending a restart – see
+/// [`Cluster::pending_restart`] – and only if `resource` is held exclusively.
+pub fn ensure_applied(
+    resource: &HeldResource,
+    options: StopOptions,
+) -> Result<Applied, ClusterError> {
+    match resource {
+        Left(resource) => {
+            let facet = resource.facet();
+            facet.reload()?;
+            let pending = facet.pending_restart()?;
+            if pending.is_empty() {
+                Ok(Applied::Reloaded)
+            } else {
+                Ok(Applied::RestartRequired(pending))
+            }
+        }
+        Right(resource) => {
+            let facet = resource.facet();
+            facet.reload()?;
+            let pending = facet.pending_restart()?;
+            if pending.is_empty() {
+                Ok(Applied::Reloaded)
+            } else {
+                facet.restart(options)?;
+                Ok(Applied::Restarted(pending))
+            }
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! Manage roles and privileges in a [`Cluster`].
+//!
+//! A new cluster has only the superuser created by `initdb`. The functions here
+//! make it easy to add other roles, e.g. to test permissions or row-level
+//! security. The cluster must be running.
+
+use std::fmt;
+
+use postgres::error::SqlState;
+use postgres_protocol::escape::{escape_identifier, escape_literal};
+
+use super::{Cluster, ClusterError};
+use crate::coordinate::State::{self, *};
+
+/// A role, as described by [`pg_roles`][pg_roles].
+///
+/// [pg_roles]: https://www.postgresql.org/docs/current/view-pg-roles.html
+#[allow(clippy::struct_excessive_bools)] // Mirrors `pg_roles`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Role {
+    pub name: String,
+    pub superuser: bool,
+    pub inherit: bool,
+    pub create_role: bool,
+    pub create_db: bool,
+    pub login: bool,
+    pub replication: bool,
+    /// `None` means no limit.
+    pub connection_limit: Option<i32>,
+    /// The roles of which this role is a direct member.
+    pub member_of: Vec<String>,
+}
+
+impl Role {
+    /// Is this one of PostgreSQL's predefined roles, e.g. `pg_monitor`?
+    pub fn is_predefined(&self) -> bool {
+        self.name.starts_with("pg_")
+    }
+}
+
+/// Attributes and memberships to use when creating or altering a role.
+///
+/// Fields left as `None` are not mentioned, meaning PostgreSQL's defaults apply
+/// when creating a role, and the existing setting is kept when altering one.
+#[derive(Clone, Debug, Default)]
+pub struct RoleOptions {
+    pub login: Option<bool>,
+    pub superuser: Option<bool>,
+    pub create_db: Option<bool>,
+    pub create_role: Option<bool>,
+    pub inherit: Option<bool>,
+    pub replication: Option<bool>,
+    pub password: Option<Password>,
+    /// Roles of which this role should be a member. When altering a role,
+    /// existing memberships are kept.
+    pub member_of: Vec<String>,
+}
+
+/// A password to set on a role.
+///
+/// Its [`Debug`][`fmt::Debug`] output never includes the password itself.
+#[derive(Clone)]
+pub enum Password {
+    /// Set the password. PostgreSQL will hash it.
+    Set(String),
+    /// Remove the password.
+    Null,
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set(_) => f.write_str("Set(<redacted>)"),
+            Self::Null => f.write_str("Null"),
+        }
+    }
+}
+
+impl RoleOptions {
+    /// Render the attributes as SQL, e.g. ` LOGIN NOSUPERUSER`.
+    fn attributes(&self) -> String {
+        let flags = [
+            (self.login, "LOGIN"),
+            (self.superuser, "SUPERUSER"),
+            (self.create_db, "CREATEDB"),
+            (self.create_role, "CREATEROLE"),
+            (self.inherit, "INHERIT"),
+            (self.replication, "REPLICATION"),
+        ];
+        let mut sql = String::new();
+        for (flag, name) in flags {
+            match flag {
+                Some(true) => sql.extend([" ", name]),
+                Some(false) => sql.extend([" NO", name]),
+                None => (),
+            }
+        }
+        match &self.password {
+            Some(Password::Set(password)) => {
+                sql.extend([" PASSWORD ", &escape_literal(password)]);
+            }
+            Some(Password::Null) => sql.push_str(" PASSWORD NULL"),
+            None => (),
+        }
+        sql
+    }
+}
+
+/// List all roles in the cluster, including PostgreSQL's predefined roles.
+pub fn list(cluster: &Cluster) -> Result<Vec<Role>, ClusterError> {
+    let mut client = cluster.connect(None)?;
+    let query = format!("{QUERY_ROLES} ORDER BY r.rolname");
+    let rows = client.query(query.as_str(), &[])?;
+    Ok(rows.iter().map(role_from_row).collect())
+}
+
+/// Get the named role, if it exists.
+pub fn get(cluster: &Cluster, name: &str) -> Result<Option<Role>, ClusterError> {
+    let mut client = cluster.connect(None)?;
+    let query = format!("{QUERY_ROLES} WHERE r.rolname = $1");
+    let row = client.query_opt(query.as_str(), &[&name])?;
+    Ok(row.as_ref().map(role_from_row))
+}
+
+const QUERY_ROLES: &str = "
+    SELECT r.rolname, r.rolsuper, r.rolinherit, r.rolcreaterole, r.rolcreatedb,
+           r.rolcanlogin, r.rolreplication, r.rolconnlimit,
+           ARRAY(SELECT b.rolname::text
+                   FROM pg_catalog.pg_auth_members m
+                   JOIN pg_catalog.pg_roles b ON (m.roleid = b.oid)
+                  WHERE m.member = r.oid
+                  ORDER BY 1) AS member_of
+      FROM pg_catalog.pg_roles r";
+
+fn role_from_row(row: &postgres::Row) -> Role {
+    let connection_limit: i32 = row.get(7);
+    Role {
+        name: row.get(0),
+        superuser: row.get(1),
+        inherit: row.get(2),
+        create_role: row.get(3),
+        create_db: row.get(4),
+        login: row.get(5),
+        replication: row.get(6),
+        connection_limit: (connection_limit >= 0).then_some(connection_limit),
+        member_of: row.get(8),
+    }
+}
+
+/// Create the named role.
+///
+/// Returns [`Unmodified`] if the role already exists – in which case the
+/// options are **not** applied – otherwise it returns [`Modified`].
+pub fn create(cluster: &Cluster, name: &str, options: &RoleOptions) -> Result<State, ClusterError> {
+    let mut statement = format!(
+        "CREATE ROLE {}{}",
+        escape_identifier(name),
+        options.attributes()
+    );
+    if !options.member_of.is_empty() {
+        statement.push_str(" IN ROLE ");
+        statement.push_str(&identifiers(&options.member_of));
+    }
+    match cluster.connect(None)?.execute(statement.as_str(), &[]) {
+        Err(err) if err.code() == Some(&SqlState::DUPLICATE_OBJECT) => Ok(Unmodified),
+        Err(err) => Err(err)?,
+        Ok(_) => Ok(Modified),
+    }
+}
+
+/// Alter the named role, which must exist.
+///
+/// Memberships in [`RoleOptions::member_of`] are added to existing ones.
+pub fn alter(cluster: &Cluster, name: &str, options: &RoleOptions) -> Result<(), ClusterError> {
+    let mut client = cluster.connect(None)?;
+    let mut transaction = client.transaction()?;
+    let attributes = options.attributes();
+    if !attributes.is_empty() {
+        let statement = format!("ALTER ROLE {}{attributes}", escape_identifier(name));
+        transaction.execute(statement.as_str(), &[])?;
+    }
+    if !options.member_of.is_empty() {
+        let statement = format!(
+            "GRANT {} TO {}",
+            identifiers(&options.member_of),
+            escape_identifier(name)
+        );
+        transaction.execute(statement.as_str(), &[])?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Drop the named role.
+///
+/// Returns [`Unmodified`] if the role does not exist, otherwise it returns
+/// [`Modified`]. This fails if the role owns objects or has privileges.
+pub fn drop(cluster: &Cluster, name: &str) -> Result<State, ClusterError> {
+    let statement = format!("DROP ROLE {}", escape_identifier(name));
+    match cluster.connect(None)?.execute(statement.as_str(), &[]) {
+        Err(err) if err.code() == Some(&SqlState::UNDEFINED_OBJECT) => Ok(Unmodified),
+        Err(err) => Err(err)?,
+        Ok(_) => Ok(Modified),
+    }
+}
+
+/// Make `member` a member of `role`.
+pub fn grant_membership(cluster: &Cluster, role: &str, member: &str) -> Result<(), ClusterError> {
+    let statement = format!(
+        "GRANT {} TO {}",
+        escape_identifier(role),
+        escape_identifier(member)
+    );
+    cluster.connect(None)?.execute(statement.as_str(), &[])?;
+    Ok(())
+}
+
+/// Remove `member` from `role`.
+pub fn revoke_membership(cluster: &Cluster, role: &str, member: &str) -> Result<(), ClusterError> {
+    let statement = format!(
+        "REVOKE {} FROM {}",
+        escape_identifier(role),
+        escape_identifier(member)
+    );
+    cluster.connect(None)?.execute(statement.as_str(), &[])?;
+    Ok(())
+}
+
+/// A privilege that can be granted on an [`Object`].
+///
+/// Not all privileges apply to all kinds of object; PostgreSQL will complain if
+/// one is used inappropriately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Privilege {
+    All,
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+    References,
+    Trigger,
+    Create,
+    Connect,
+    Temporary,
+    Usage,
+}
+
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Privilege::*;
+        f.write_str(match self {
+            All => "ALL PRIVILEGES",
+            Select => "SELECT",
+            Insert => "INSERT",
+            Update => "UPDATE",
+            Delete => "DELETE",
+            Truncate => "TRUNCATE",
+            References => "REFERENCES",
+            Trigger => "TRIGGER",
+            Create => "CREATE",
+            Connect => "CONNECT",
+            Temporary => "TEMPORARY",
+            Usage => "USAGE",
+        })
+    }
+}
+
+/// An object on which privileges can be granted.
+#[derive(Clone, Copy, Debug)]
+pub enum Object<'a> {
+    /// The named database.
+    Database(&'a str),
+    /// The named schema.
+    Schema(&'a str),
+    /// The named table, found via the `search_path`.
+    Table(&'a str),
+    /// All tables in the named schema (that exist now).
+    AllTablesInSchema(&'a str),
+}
+
+impl fmt::Display for Object<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Object::*;
+        match self {
+            Database(name) => write!(f, "DATABASE {}", escape_identifier(name)),
+            Schema(name) => write!(f, "SCHEMA {}", escape_identifier(name)),
+            Table(name) => write!(f, "TABLE {}", escape_identifier(name)),
+            AllTablesInSchema(name) => {
+                write!(f, "ALL TABLES IN SCHEMA {}", escape_identifier(name))
+            }
+        }
+    }
+}
+
+/// Grant `privileges` on `object` to `role`.
+///
+/// Schemas and tables are found in `database`; when not specified, this
+/// connects to [`DATABASE_POSTGRES`][super::DATABASE_POSTGRES]. Does nothing
+/// when `privileges` is empty.
+pub fn grant(
+    cluster: &Cluster,
+    database: Option<&str>,
+    privileges: &[Privilege],
+    object: Object<'_>,
+    role: &str,
+) -> Result<(), ClusterError> {
+    if privileges.is_empty() {
+        return Ok(());
+    }
+    let statement = format!(
+        "GRANT {} ON {object} TO {}",
+        list_of(privileges),
+        escape_identifier(role)
+    );
+    cluster
+        .connect(database)?
+        .execute(statement.as_str(), &[])?;
+    Ok(())
+}
+
+/// Revoke `privileges` on `object` from `role`.
+///
+/// See [`grant`] for details.
+pub fn revoke(
+    cluster: &Cluster,
+    database: Option<&str>,
+    privileges: &[Privilege],
+    object: Object<'_>,
+    role: &str,
+) -> Result<(), ClusterError> {
+    if privileges.is_empty() {
+        return Ok(());
+    }
+    let statement = format!(
+        "REVOKE {} ON {object} FROM {}",
+        list_of(privileges),
+        escape_identifier(role)
+    );
+    cluster
+        .connect(database)?
+        .execute(statement.as_str(), &[])?;
+    Ok(())
+}
+
+/// Escape and join the given names, e.g. `"foo", "bar"`.
+fn identifiers<T: AsRef<str>>(names: &[T]) -> String {
+    names
+        .iter()
+        .map(|name| escape_identifier(name.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Join the given items, e.g. `SELECT, INSERT`.
+fn list_of<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Object, Password, Privilege, RoleOptions};
+
+    #[test]
+    fn role_options_attributes_are_rendered() {
+        let options = RoleOptions {
+            login: Some(true),
+            superuser: Some(false),
+            password: Some(Password::Set("it's".into())),
+            ..Default::default()
+        };
+        assert_eq!(options.attributes(), " LOGIN NOSUPERUSER PASSWORD 'it''s'");
+        assert_eq!(RoleOptions::default().attributes(), "");
+    }
+
+    #[test]
+    fn passwords_are_redacted_in_debug_output() {
+        let options = RoleOptions {
+            password: Some(Password::Set("sekrit".into())),
+            ..Default::default()
+        };
+        let debug = format!("{options:?}");
+        assert!(debug.contains("Set(<redacted>)"));
+        assert!(!debug.contains("sekrit"));
+        assert_eq!(format!("{:?}", Password::Null), "Null");
+    }
+
+    #[test]
+    fn objects_are_escaped() {
+        assert_eq!(
+            Object::AllTablesInSchema("Foo\"Bar").to_string(),
+            "ALL TABLES IN SCHEMA \"Foo\"\"Bar\""
+        );
+        assert_eq!(Privilege::All.to_string(), "ALL PRIVILEGES");
+    }
+}
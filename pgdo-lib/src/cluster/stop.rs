@@ -0,0 +1,248 @@
+//! Options for stopping a cluster.
+//!
+//! See PostgreSQL's [Shutting Down the Server][shutdown] page for how the
+//! shutdown modes differ.
+//!
+//! [shutdown]: https://www.postgresql.org/docs/current/server-shutdown.html
+
+use std::fmt;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+use super::{Cluster, ClusterError};
+use crate::runtime::Runtime;
+
+/// How to shut down a cluster, from slowest and most polite to fastest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownMode {
+    /// Wait for all sessions to end, then shut down cleanly.
+    Smart,
+    /// Disconnect sessions, rolling back their transactions, then shut down
+    /// cleanly.
+    #[default]
+    Fast,
+    /// Abort without a clean shutdown. The server will perform crash recovery
+    /// when next started.
+    Immediate,
+}
+
+impl ShutdownMode {
+    /// The next faster mode, if there is one.
+    pub fn escalate(self) -> Option<Self> {
+        match self {
+            Self::Smart => Some(Self::Fast),
+            Self::Fast => Some(Self::Immediate),
+            Self::Immediate => None,
+        }
+    }
+
+    /// The mode as understood by `pg_ctl stop -m …` and `pg_ctl restart -m …`.
+    pub(crate) fn as_arg(self) -> &'static str {
+        match self {
+            Self::Smart => "smart",
+            Self::Fast => "fast",
+            Self::Immediate => "immediate",
+        }
+    }
+}
+
+impl fmt::Display for ShutdownMode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.pad(self.as_arg())
+    }
+}
+
+/// Options for [`Cluster::stop`][`super::Cluster::stop`].
+///
+/// When the server does not stop within the timeout – `pg_ctl`'s default if
+/// none is given – the next faster [`ShutdownMode`] is tried, with the same
+/// timeout, until `Immediate` has been tried too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StopOptions {
+    /// The mode in which to try stopping the server first.
+    pub mode: ShutdownMode,
+    /// How long to wait for the server to stop in each mode. When `None`,
+    /// `pg_ctl`'s default applies: 60 seconds, or `PGCTLTIMEOUT` if set.
+    pub timeout: Option<Duration>,
+}
+
+impl StopOptions {
+    /// The timeout as understood by `pg_ctl stop -t …`, i.e. whole seconds,
+    /// rounded up, and at least 1.
+    pub(crate) fn timeout_arg(&self) -> Option<String> {
+        self.timeout.map(|timeout| {
+            let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+            seconds.max(1).to_string()
+        })
+    }
+}
+
+impl From<ShutdownMode> for StopOptions {
+    fn from(mode: ShutdownMode) -> Self {
+        Self { mode, timeout: None }
+    }
+}
+
+/// How to shut down a server with `pg_ctl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ShutdownAction {
+    Stop,
+    Restart,
+}
+
+impl ShutdownAction {
+    fn as_arg(self) -> &'static str {
+        match self {
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+        }
+    }
+}
+
+/// A shutdown of a cluster's server – to stop or to restart it – that
+/// escalates to faster modes when `pg_ctl` times out.
+///
+/// Callers run [`command`][`Self::command`] and, when it fails, call
+/// [`escalate`][`Self::escalate`] to decide whether to run it again.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Shutdown {
+    action: ShutdownAction,
+    options: StopOptions,
+    mode: ShutdownMode,
+    /// The PID of the server being shut down.
+    pid: Option<i32>,
+}
+
+impl Shutdown {
+    pub(crate) fn new(action: ShutdownAction, options: StopOptions, pid: Option<i32>) -> Self {
+        Self { action, options, mode: options.mode, pid }
+    }
+
+    /// Construct the `pg_ctl stop` or `pg_ctl restart` command for the current
+    /// mode.
+    pub(crate) fn command(&self, cluster: &Cluster, runtime: &Runtime) -> Command {
+        // pg_ctl options:
+        //  -s -- no informational messages.
+        //  -w -- wait for shutdown (and startup) to complete.
+        //  -m <mode> -- shutdown mode.
+        //  -t <seconds> -- how long to wait.
+        let mut command = cluster.ctl(runtime);
+        command
+            .arg(self.action.as_arg())
+            .arg("-s")
+            .arg("-w")
+            .arg("-m")
+            .arg(self.mode.as_arg())
+            // So that we can recognise a timeout; see `timed_out`.
+            .env("LC_ALL", "C");
+        if let Some(timeout) = self.options.timeout_arg() {
+            command.arg("-t").arg(timeout);
+        }
+        if self.action == ShutdownAction::Restart {
+            // Without a log file the new server would inherit our stdout and
+            // stderr. The server's own options are reused from the
+            // `postmaster.opts` file that the previous server wrote.
+            command.arg("-l").arg(cluster.logfile());
+        }
+        command
+    }
+
+    /// After `pg_ctl` fails with `output`, should it be run again in a faster
+    /// mode? If so, this switches to that mode and logs a warning.
+    ///
+    /// This is only when `pg_ctl` reports that its timeout expired – the
+    /// caller's, or `pg_ctl`'s default – and the server being shut down is
+    /// still running; when a restart times out, for example, a new server may
+    /// be starting up.
+    pub(crate) fn escalate(
+        &mut self,
+        cluster: &Cluster,
+        output: &Output,
+    ) -> Result<bool, ClusterError> {
+        match self.faster(output) {
+            Some(faster) if self.pid.is_some() && cluster.server_pid()? == self.pid => {
+                log::warn!(
+                    "Cluster {}: server did not stop in {} mode; trying {faster} mode",
+                    cluster.datadir.display(),
+                    self.mode,
+                );
+                self.mode = faster;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The next faster mode, if `pg_ctl` failed with `output` because its
+    /// timeout expired.
+    fn faster(&self, output: &Output) -> Option<ShutdownMode> {
+        if timed_out(output) {
+            self.mode.escalate()
+        } else {
+            None
+        }
+    }
+}
+
+/// Did `pg_ctl` give up waiting for the server to shut down? Its messages are
+/// not translated because [`Shutdown::command`] sets `LC_ALL=C`.
+fn timed_out(output: &Output) -> bool {
+    !output.status.success()
+        && String::from_utf8_lossy(&output.stderr).contains("server does not shut down")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    use super::{Shutdown, ShutdownAction, ShutdownMode::*, StopOptions};
+
+    #[test]
+    fn escalate_to_faster_modes() {
+        assert_eq!(Smart.escalate(), Some(Fast));
+        assert_eq!(Fast.escalate(), Some(Immediate));
+        assert_eq!(Immediate.escalate(), None);
+        assert!(Smart < Fast && Fast < Immediate);
+    }
+
+    #[test]
+    fn timeout_rounds_up_to_whole_seconds() {
+        let arg = |timeout| StopOptions { mode: Fast, timeout }.timeout_arg();
+        assert_eq!(arg(None), None);
+        assert_eq!(arg(Some(Duration::ZERO)).as_deref(), Some("1"));
+        assert_eq!(arg(Some(Duration::from_millis(1500))).as_deref(), Some("2"));
+        assert_eq!(arg(Some(Duration::from_secs(30))).as_deref(), Some("30"));
+    }
+
+    fn failed(stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    #[test]
+    fn escalate_only_when_timeout_expired() {
+        let timeout = Some(Duration::from_secs(1));
+        let timed_out = failed("pg_ctl: server does not shut down\n");
+        let shutdown = |mode, timeout| {
+            Shutdown::new(
+                ShutdownAction::Stop,
+                StopOptions { mode, timeout },
+                Some(1234),
+            )
+        };
+        assert_eq!(shutdown(Smart, timeout).faster(&timed_out), Some(Fast));
+        assert_eq!(shutdown(Fast, timeout).faster(&timed_out), Some(Immediate));
+        assert_eq!(shutdown(Immediate, timeout).faster(&timed_out), None);
+        // Without a timeout from the caller, `pg_ctl`'s default applies.
+        assert_eq!(shutdown(Fast, None).faster(&timed_out), Some(Immediate));
+        // Other failures are not timeouts.
+        let other = failed("pg_ctl: could not send stop signal (PID: 1234)\n");
+        assert_eq!(shutdown(Fast, timeout).faster(&other), None);
+    }
+}
@@ -12,10 +12,13 @@
 //! let cluster = cluster::Cluster::new(&data_dir, strategy)?;
 //! let lock_file = cluster_dir.path().join("lock");
 //! let lock = lock::UnlockedFile::try_from(lock_file.as_path())?;
-//! assert!(coordinate::run_and_stop(&cluster, &[], lock, || cluster::exists(&cluster))?);
+//! let stop = cluster::StopOptions::default();
+//! assert!(coordinate::run_and_stop(&cluster, &[], stop, lock, || cluster::exists(&cluster))?);
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
+#[cfg(feature = "async")]
+mod asynchronous;
 pub mod cleanup;
 mod error;
 pub mod finally;
@@ -31,6 +34,10 @@ use either::Either::{Left, Right};
 use rand::Rng;
 
 use crate::lock;
+#[cfg(feature = "async")]
+pub use asynchronous::{
+    run_and_destroy_async, run_and_stop_async, run_and_stop_if_exists_async, AsyncSubject,
+};
 pub use error::CoordinateError;
 
 use self::finally::with_finally;
@@ -49,8 +56,9 @@ pub enum State {
 pub trait Subject {
     type Error: std::error::Error + Send + Sync;
     type Options<'a>: Default;
+    type StopOptions: Default + Clone;
     fn start(&self, options: Self::Options<'_>) -> Result<State, Self::Error>;
-    fn stop(&self) -> Result<State, Self::Error>;
+    fn stop(&self, options: Self::StopOptions) -> Result<State, Self::Error>;
     fn destroy(&self) -> Result<State, Self::Error>;
     fn exists(&self) -> Result<bool, Self::Error>;
     fn running(&self) -> Result<bool, Self::Error>;
@@ -60,22 +68,25 @@ pub trait Subject {
 ///
 /// Using the given lock for synchronisation, this creates the subject if it
 /// does not exist, starts it if it's not running, performs the `action`, then
-/// (maybe) stops the subject again, and finally returns the result of `action`.
-/// If there are other users of the subject – i.e. if an exclusive lock cannot
-/// be acquired during the shutdown phase – then the subject is left running.
+/// (maybe) stops the subject again, with `stop_options`, and finally returns the
+/// result of `action`. If there are other users of the subject – i.e. if an
+/// exclusive lock cannot be acquired during the shutdown phase – then the
+/// subject is left running.
 pub fn run_and_stop<S, F, T>(
     subject: &S,
     options: S::Options<'_>,
+    stop_options: S::StopOptions,
     lock: lock::UnlockedFile,
     action: F,
 ) -> Result<T, CoordinateError<S::Error>>
 where
     S: std::panic::RefUnwindSafe + Subject,
+    S::StopOptions: std::panic::UnwindSafe,
     F: std::panic::UnwindSafe + FnOnce() -> T,
 {
     let lock = startup(lock, subject, options)?;
     with_finally(
-        || shutdown::<S, _, _>(lock, || subject.stop()),
+        || shutdown::<S, _, _>(lock, || subject.stop(stop_options)),
         || -> Result<T, CoordinateError<S::Error>> { Ok(action()) },
     )
 }
@@ -84,22 +95,24 @@ where
 ///
 /// Using the given lock for synchronisation, this starts the subject it if it's
 /// not running, performs the `action`, then (maybe) stops the subject again,
-/// and finally returns the result of `action`. If there are other users of the
-/// subject – i.e. if an exclusive lock cannot be acquired during the shutdown
-/// phase – then the subject is left running.
+/// with `stop_options`, and finally returns the result of `action`. If there
+/// are other users of the subject – i.e. if an exclusive lock cannot be
+/// acquired during the shutdown phase – then the subject is left running.
 pub fn run_and_stop_if_exists<S, F, T>(
     subject: &S,
     options: S::Options<'_>,
+    stop_options: S::StopOptions,
     lock: lock::UnlockedFile,
     action: F,
 ) -> Result<T, CoordinateError<S::Error>>
 where
     S: std::panic::RefUnwindSafe + Subject,
+    S::StopOptions: std::panic::UnwindSafe,
     F: std::panic::UnwindSafe + FnOnce() -> T,
 {
     let lock = startup_if_exists(lock, subject, options)?;
     with_finally(
-        || shutdown::<S, _, _>(lock, || subject.stop()),
+        || shutdown::<S, _, _>(lock, || subject.stop(stop_options)),
         || -> Result<T, CoordinateError<S::Error>> { Ok(action()) },
     )
 }
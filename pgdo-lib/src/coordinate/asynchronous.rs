@@ -0,0 +1,190 @@
+//! Asynchronous versions of the coordinate functions, for use with Tokio.
+//!
+//! Blocking on a lock is done via [`tokio::task::spawn_blocking`], so these do
+//! not hold up the runtime's worker threads.
+
+use std::future::Future;
+use std::time::Duration;
+
+use either::Either::{Left, Right};
+use rand::Rng;
+
+use super::{finally::with_finally_async, lock, CoordinateError, State};
+
+/// The trait that the asynchronous coordinate functions work with.
+///
+/// This is the asynchronous counterpart of [`Subject`][super::Subject].
+pub trait AsyncSubject {
+    type Error: std::error::Error + Send + Sync;
+    type Options<'a>: Default + Send;
+    type StopOptions: Default + Clone + Send + Sync;
+    fn start(
+        &self,
+        options: Self::Options<'_>,
+    ) -> impl Future<Output = Result<State, Self::Error>> + Send;
+    fn stop(
+        &self,
+        options: Self::StopOptions,
+    ) -> impl Future<Output = Result<State, Self::Error>> + Send;
+    fn destroy(&self) -> impl Future<Output = Result<State, Self::Error>> + Send;
+    fn exists(&self) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+    fn running(&self) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+}
+
+/// Asynchronous version of [`run_and_stop`][super::run_and_stop].
+pub async fn run_and_stop_async<S, F, T>(
+    subject: &S,
+    options: S::Options<'_>,
+    stop_options: S::StopOptions,
+    lock: lock::UnlockedFile,
+    action: F,
+) -> Result<T, CoordinateError<S::Error>>
+where
+    S: AsyncSubject,
+    F: Future<Output = T>,
+{
+    let lock = startup_async(lock, subject, options, Startup::Create).await?;
+    with_finally_async(
+        shutdown_async::<S, _, _, _>(lock, || subject.stop(stop_options)),
+        async { Ok(action.await) },
+    )
+    .await
+}
+
+/// Asynchronous version of
+/// [`run_and_stop_if_exists`][super::run_and_stop_if_exists].
+pub async fn run_and_stop_if_exists_async<S, F, T>(
+    subject: &S,
+    options: S::Options<'_>,
+    stop_options: S::StopOptions,
+    lock: lock::UnlockedFile,
+    action: F,
+) -> Result<T, CoordinateError<S::Error>>
+where
+    S: AsyncSubject,
+    F: Future<Output = T>,
+{
+    let lock = startup_async(lock, subject, options, Startup::IfExists).await?;
+    with_finally_async(
+        shutdown_async::<S, _, _, _>(lock, || subject.stop(stop_options)),
+        async { Ok(action.await) },
+    )
+    .await
+}
+
+/// Asynchronous version of [`run_and_destroy`][super::run_and_destroy].
+pub async fn run_and_destroy_async<S, F, T>(
+    subject: &S,
+    options: S::Options<'_>,
+    lock: lock::UnlockedFile,
+    action: F,
+) -> Result<T, CoordinateError<S::Error>>
+where
+    S: AsyncSubject,
+    F: Future<Output = T>,
+{
+    let lock = startup_async(lock, subject, options, Startup::Create).await?;
+    with_finally_async(
+        shutdown_async::<S, _, _, _>(lock, || subject.destroy()),
+        async { Ok(action.await) },
+    )
+    .await
+}
+
+// ----------------------------------------------------------------------------
+
+/// Whether [`startup_async`] may create the subject.
+#[derive(Clone, Copy)]
+pub(super) enum Startup {
+    Create,
+    IfExists,
+}
+
+pub(super) async fn startup_async<S: AsyncSubject>(
+    mut lock: lock::UnlockedFile,
+    subject: &S,
+    options: S::Options<'_>,
+    mode: Startup,
+) -> Result<lock::LockedFileShared, CoordinateError<S::Error>> {
+    loop {
+        lock = match lock.try_lock_exclusive() {
+            Ok(Left(lock)) => {
+                // The subject is locked elsewhere, shared or exclusively. We
+                // optimistically take a shared lock, which may block, so do so
+                // on a thread where blocking is okay.
+                let lock = tokio::task::spawn_blocking(move || lock.lock_shared())
+                    .await
+                    .map_err(std::io::Error::other)??;
+                // If obtaining the lock blocked, i.e. the lock elsewhere was
+                // exclusive, then the subject may have been started by the
+                // process that held that exclusive lock. We should check.
+                if subject
+                    .running()
+                    .await
+                    .map_err(CoordinateError::ControlError)?
+                {
+                    return Ok(lock);
+                }
+                // Release all locks then sleep for a random time between 200ms
+                // and 1000ms in an attempt to make sure that when there are
+                // many competing processes one of them rapidly acquires an
+                // exclusive lock and is able to create and start the subject.
+                let lock = lock.unlock()?;
+                let delay = rand::rng().next_u32();
+                let delay = 200 + (delay % 800);
+                let delay = Duration::from_millis(u64::from(delay));
+                tokio::time::sleep(delay).await;
+                lock
+            }
+            Ok(Right(lock)) => {
+                // We have an exclusive lock, so try to start the subject.
+                if let Startup::IfExists = mode {
+                    if !subject
+                        .exists()
+                        .await
+                        .map_err(CoordinateError::ControlError)?
+                    {
+                        return Err(CoordinateError::DoesNotExist);
+                    }
+                }
+                subject
+                    .start(options)
+                    .await
+                    .map_err(CoordinateError::ControlError)?;
+                // Once started, downgrade to a shared log.
+                return Ok(lock.lock_shared()?);
+            }
+            Err(err) => return Err(err.into()),
+        };
+    }
+}
+
+pub(super) async fn shutdown_async<S, F, FUT, T>(
+    lock: lock::LockedFileShared,
+    action: F,
+) -> Result<Option<T>, CoordinateError<S::Error>>
+where
+    S: AsyncSubject,
+    F: FnOnce() -> FUT,
+    FUT: Future<Output = Result<T, S::Error>>,
+{
+    match lock.try_lock_exclusive() {
+        Ok(Left(lock)) => {
+            // The subject is in use elsewhere. There's nothing more we can do
+            // here.
+            lock.unlock()?;
+            Ok(None)
+        }
+        Ok(Right(lock)) => {
+            // We have an exclusive lock, so we can mutate the subject.
+            match action().await {
+                Ok(result) => {
+                    lock.unlock()?;
+                    Ok(Some(result))
+                }
+                Err(err) => Err(CoordinateError::ControlError(err)),
+            }
+        }
+        Err(err) => Err(err.into()),
+    }
+}
@@ -51,6 +51,74 @@ where
     }
 }
 
+/// Asynchronous version of [`with_finally`].
+///
+/// Panics while polling `task` or `finally` are caught and handled in the same
+/// way as [`with_finally`] handles them. Note that the futures are **not**
+/// required to be [`UnwindSafe`][std::panic::UnwindSafe]; take care.
+#[cfg(feature = "async")]
+pub async fn with_finally_async<TASK, T, E, FINALLY, FT, FE>(
+    finally: FINALLY,
+    task: TASK,
+) -> Result<T, E>
+where
+    TASK: std::future::Future<Output = Result<T, E>>,
+    FINALLY: std::future::Future<Output = Result<FT, FE>>,
+    E: std::fmt::Display,
+    FE: std::fmt::Display + Into<E>,
+{
+    match catch_unwind_async(task).await {
+        Ok(Ok(t)) => match catch_unwind_async(finally).await {
+            Ok(Ok(_)) => Ok(t),
+            Ok(Err(ce)) => {
+                log::error!("Task succeeded but cleaning-up failed");
+                Err(ce.into())
+            }
+            Err(panic) => {
+                log::error!("Task succeeded but cleaning-up panicked");
+                resume_unwind(panic)
+            }
+        },
+        Ok(Err(e)) => match catch_unwind_async(finally).await {
+            Ok(Ok(_)) => Err(e),
+            Ok(Err(ce)) => {
+                log::error!("Task failed & cleaning-up also failed: {ce}");
+                Err(e)
+            }
+            Err(_) => {
+                log::error!("Task failed & cleaning-up panicked (suppressed)");
+                Err(e)
+            }
+        },
+        Err(panic) => match catch_unwind_async(finally).await {
+            Ok(Ok(_)) => resume_unwind(panic),
+            Ok(Err(ce)) => {
+                log::error!("Task panicked & cleaning-up failed: {ce}");
+                resume_unwind(panic)
+            }
+            Err(_) => {
+                log::error!("Task panicked & cleaning-up also panicked (suppressed)");
+                resume_unwind(panic)
+            }
+        },
+    }
+}
+
+/// Poll `future` to completion, catching any panic.
+#[cfg(feature = "async")]
+async fn catch_unwind_async<F: std::future::Future>(future: F) -> std::thread::Result<F::Output> {
+    use std::{panic::AssertUnwindSafe, task::Poll};
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(
+        |cx| match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        },
+    )
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::with_finally;
@@ -111,4 +179,45 @@ mod tests {
             || panic!("Panic/task"),
         );
     }
+
+    #[cfg(feature = "async")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_with_cleanup_async() {
+        use super::with_finally_async;
+        let result: Result<&'static str, &'static str> = block_on(with_finally_async(
+            async { Ok::<&'static str, &'static str>("Ok/cleanup") },
+            async { Ok("Ok/task") },
+        ));
+        assert!(matches!(result, Ok("Ok/task")));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_with_cleanup_async_error_in_task_and_cleanup() {
+        use super::with_finally_async;
+        let result: Result<(), &'static str> = block_on(with_finally_async(
+            async { Err::<(), &'static str>("Err/cleanup") },
+            async { Err("Err/task") },
+        ));
+        assert!(matches!(result, Err("Err/task")));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    #[should_panic(expected = "Panic/task")]
+    fn test_with_cleanup_async_panic_in_task() {
+        use super::with_finally_async;
+        let _result: Result<(), &'static str> = block_on(with_finally_async(
+            async { Ok::<(), &'static str>(()) },
+            async { panic!("Panic/task") },
+        ));
+    }
 }
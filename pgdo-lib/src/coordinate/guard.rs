@@ -1,7 +1,12 @@
+#[cfg(feature = "async")]
+use super::{
+    asynchronous::{shutdown_async, startup_async, Startup},
+    AsyncSubject,
+};
 use super::{lock, shutdown, startup, CoordinateError, Subject};
 
-enum GuardDropMode {
-    Stop,
+enum GuardDropMode<STOP> {
+    Stop(STOP),
     Destroy,
 }
 
@@ -14,7 +19,7 @@ pub struct Guard<SUBJECT>
 where
     SUBJECT: Subject,
 {
-    mode: GuardDropMode,
+    mode: GuardDropMode<SUBJECT::StopOptions>,
     lock: Option<lock::LockedFileShared>,
     subject: SUBJECT,
 }
@@ -30,7 +35,11 @@ where
         options: T::Options<'_>,
     ) -> Result<Self, CoordinateError<T::Error>> {
         let lock = startup(lock.into(), &subject, options)?;
-        Ok(Self { mode: GuardDropMode::Stop, lock: lock.into(), subject })
+        Ok(Self {
+            mode: GuardDropMode::Stop(T::StopOptions::default()),
+            lock: lock.into(),
+            subject,
+        })
     }
 }
 
@@ -40,8 +49,15 @@ where
 {
     /// Configures the guard to *stop* the subject when it goes out of scope.
     #[must_use]
-    pub fn and_stop(mut self) -> Self {
-        self.mode = GuardDropMode::Stop;
+    pub fn and_stop(self) -> Self {
+        self.and_stop_with(T::StopOptions::default())
+    }
+
+    /// Configures the guard to *stop* the subject, with the given options, when
+    /// it goes out of scope.
+    #[must_use]
+    pub fn and_stop_with(mut self, options: T::StopOptions) -> Self {
+        self.mode = GuardDropMode::Stop(options);
         self
     }
 
@@ -71,12 +87,14 @@ where
     fn drop(&mut self) {
         if let Some(lock) = self.lock.take() {
             let result = match &self.mode {
-                GuardDropMode::Stop => shutdown::<T, _, _>(lock, || self.subject.stop()),
+                GuardDropMode::Stop(options) => {
+                    shutdown::<T, _, _>(lock, || self.subject.stop(options.clone()))
+                }
                 GuardDropMode::Destroy => shutdown::<T, _, _>(lock, || self.subject.destroy()),
             };
             match (&self.mode, result) {
-                (GuardDropMode::Stop, Ok(_)) => (),
-                (GuardDropMode::Stop, Err(err)) => {
+                (GuardDropMode::Stop(_), Ok(_)) => (),
+                (GuardDropMode::Stop(_), Err(err)) => {
                     log::error!("Error stopping subject: {err}");
                 }
                 (GuardDropMode::Destroy, Ok(_)) => (),
@@ -87,3 +105,138 @@ where
         }
     }
 }
+
+// ----------------------------------------------------------------------------
+
+/// Asynchronous version of [`Guard`], for an [`AsyncSubject`].
+///
+/// Callers should finish with [`shutdown().await`][Self::shutdown]. Dropping
+/// the guard without doing so falls back to stopping or destroying the subject
+/// on a separate thread with its own Tokio runtime, and blocks until that is
+/// complete. Inside an asynchronous runtime this ties up a worker thread – and
+/// on a current-thread runtime, everything else – for as long as shutdown takes,
+/// so a warning is logged when it happens. Errors when stopping or destroying
+/// the subject in this way are logged but otherwise ignored.
+#[cfg(feature = "async")]
+pub struct AsyncGuard<SUBJECT>
+where
+    SUBJECT: AsyncSubject + Sync,
+{
+    mode: GuardDropMode<SUBJECT::StopOptions>,
+    lock: Option<lock::LockedFileShared>,
+    subject: SUBJECT,
+}
+
+#[cfg(feature = "async")]
+impl<T> AsyncGuard<T>
+where
+    T: AsyncSubject + Sync,
+{
+    /// Starts the given subject and returns the guard.
+    pub async fn startup<L: Into<lock::UnlockedFile>>(
+        lock: L,
+        subject: T,
+        options: T::Options<'_>,
+    ) -> Result<Self, CoordinateError<T::Error>> {
+        let lock = startup_async(lock.into(), &subject, options, Startup::Create).await?;
+        Ok(Self {
+            mode: GuardDropMode::Stop(T::StopOptions::default()),
+            lock: lock.into(),
+            subject,
+        })
+    }
+
+    /// Configures the guard to *stop* the subject when it goes out of scope.
+    #[must_use]
+    pub fn and_stop(self) -> Self {
+        self.and_stop_with(T::StopOptions::default())
+    }
+
+    /// Configures the guard to *stop* the subject, with the given options, when
+    /// it goes out of scope.
+    #[must_use]
+    pub fn and_stop_with(mut self, options: T::StopOptions) -> Self {
+        self.mode = GuardDropMode::Stop(options);
+        self
+    }
+
+    /// Configures the guard to *destroy* the subject when it goes out of scope.
+    #[must_use]
+    pub fn and_destroy(mut self) -> Self {
+        self.mode = GuardDropMode::Destroy;
+        self
+    }
+
+    /// Stop or destroy the subject now, according to the configured mode.
+    pub async fn shutdown(mut self) -> Result<(), CoordinateError<T::Error>> {
+        match self.lock.take() {
+            Some(lock) => self.release(lock).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn release(&self, lock: lock::LockedFileShared) -> Result<(), CoordinateError<T::Error>> {
+        match &self.mode {
+            GuardDropMode::Stop(options) => {
+                shutdown_async::<T, _, _, _>(lock, || self.subject.stop(options.clone())).await?;
+            }
+            GuardDropMode::Destroy => {
+                shutdown_async::<T, _, _, _>(lock, || self.subject.destroy()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> std::ops::Deref for AsyncGuard<T>
+where
+    T: AsyncSubject + Sync,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.subject
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Drop for AsyncGuard<T>
+where
+    T: AsyncSubject + Sync,
+{
+    fn drop(&mut self) {
+        if let Some(lock) = self.lock.take() {
+            log::warn!(
+                "AsyncGuard dropped without calling shutdown().await; \
+                 blocking this thread to shut down subject"
+            );
+            // We may be inside a runtime here, where we cannot block on a
+            // future, so run the shutdown on a new thread and wait for it.
+            let this = &*self;
+            let result = std::thread::scope(|scope| {
+                scope
+                    .spawn(|| {
+                        tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .map_err(CoordinateError::from)?
+                            .block_on(this.release(lock))
+                    })
+                    .join()
+            });
+            match (&self.mode, result) {
+                (_, Ok(Ok(()))) => (),
+                (GuardDropMode::Stop(_), Ok(Err(err))) => {
+                    log::error!("Error stopping subject: {err}");
+                }
+                (GuardDropMode::Destroy, Ok(Err(err))) => {
+                    log::error!("Error destroying subject: {err}");
+                }
+                (_, Err(_)) => {
+                    log::error!("Panic while shutting down subject");
+                }
+            }
+        }
+    }
+}
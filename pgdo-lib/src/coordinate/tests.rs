@@ -14,8 +14,9 @@ fn run_and_stop_still_stops_when_action_panics() -> TestResult {
     let subject = SubjectExample::default();
     let status = subject.status.clone();
     let (_setup, lock) = Setup::run()?;
-    let panic =
-        std::panic::catch_unwind(|| run_and_stop(&subject, (), lock, || panic!("test panic")));
+    let panic = std::panic::catch_unwind(|| {
+        run_and_stop(&subject, (), "fast", lock, || panic!("test panic"))
+    });
     assert!(panic.is_err());
     let payload = *panic.unwrap_err().downcast::<&str>().unwrap();
     assert_eq!(payload, "test panic");
@@ -23,13 +24,23 @@ fn run_and_stop_still_stops_when_action_panics() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn run_and_stop_passes_stop_options() -> TestResult {
+    let subject = SubjectExample::default();
+    let (_setup, lock) = Setup::run()?;
+    assert!(run_and_stop(&subject, (), "smart", lock, || true)?);
+    assert_eq!(*subject.stopped_with.read().unwrap(), Some("smart"));
+    Ok(())
+}
+
 #[test]
 fn run_and_stop_still_panics_if_stop_fails() -> TestResult {
     // i.e. the error from `stop` is suppressed when the action has panicked.
     let subject = SubjectExample::already_exists().but_cannot_stop();
     let (_setup, lock) = Setup::run()?;
-    let panic =
-        std::panic::catch_unwind(|| run_and_stop(&subject, (), lock, || panic!("test panic")));
+    let panic = std::panic::catch_unwind(|| {
+        run_and_stop(&subject, (), "fast", lock, || panic!("test panic"))
+    });
     assert!(panic.is_err());
     let payload = *panic.unwrap_err().downcast::<&str>().unwrap();
     assert_eq!(payload, "test panic");
@@ -42,7 +53,7 @@ fn run_and_stop_if_exists_still_stops_when_action_panics() -> TestResult {
     let status = subject.status.clone();
     let (_setup, lock) = Setup::run()?;
     let panic = std::panic::catch_unwind(|| {
-        run_and_stop_if_exists(&subject, (), lock, || panic!("test panic"))
+        run_and_stop_if_exists(&subject, (), "fast", lock, || panic!("test panic"))
     });
     assert!(panic.is_err());
     let payload = *panic.unwrap_err().downcast::<&str>().unwrap();
@@ -57,7 +68,7 @@ fn run_and_stop_if_exists_still_panics_if_stop_fails() -> TestResult {
     let subject = SubjectExample::already_exists().but_cannot_stop();
     let (_setup, lock) = Setup::run()?;
     let panic = std::panic::catch_unwind(|| {
-        run_and_stop_if_exists(&subject, (), lock, || panic!("test panic"))
+        run_and_stop_if_exists(&subject, (), "fast", lock, || panic!("test panic"))
     });
     assert!(panic.is_err());
     let payload = *panic.unwrap_err().downcast::<&str>().unwrap();
@@ -107,6 +118,19 @@ fn guard_stops_subject() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn guard_stops_subject_with_stop_options() -> TestResult {
+    let subject = SubjectExample::default();
+    let status = subject.status.clone();
+    let stopped_with = subject.stopped_with.clone();
+    let (_setup, lock) = Setup::run()?;
+    let guard = super::guard::Guard::startup(lock, subject, ())?.and_stop_with("immediate");
+    drop(guard);
+    assert!(!status.read().unwrap().running);
+    assert_eq!(*stopped_with.read().unwrap(), Some("immediate"));
+    Ok(())
+}
+
 #[test]
 fn guard_stops_subject_when_something_panics() -> TestResult {
     let subject = SubjectExample::default();
@@ -128,6 +152,100 @@ fn guard_stops_subject_when_something_panics() -> TestResult {
 
 // ----------------------------------------------------------------------------
 
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn run_and_stop_async_still_stops_when_action_panics() -> TestResult {
+    let subject = SubjectExample::default();
+    let status = subject.status.clone();
+    let (_setup, lock) = Setup::run()?;
+    let panic = std::panic::catch_unwind(|| {
+        block_on(super::run_and_stop_async(
+            &subject,
+            (),
+            "fast",
+            lock,
+            async { panic!("test panic") },
+        ))
+    });
+    assert!(panic.is_err());
+    let payload = *panic.unwrap_err().downcast::<&str>().unwrap();
+    assert_eq!(payload, "test panic");
+    assert!(!status.read().unwrap().running);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn run_and_stop_if_exists_async_returns_error_if_subject_does_not_exist() -> TestResult {
+    let subject = SubjectExample::default();
+    let (_setup, lock) = Setup::run()?;
+    let result = block_on(super::run_and_stop_if_exists_async(
+        &subject,
+        (),
+        "fast",
+        lock,
+        async {},
+    ));
+    assert!(matches!(result, Err(super::CoordinateError::DoesNotExist)));
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn run_and_destroy_async_destroys_subject() -> TestResult {
+    let subject = SubjectExample::default();
+    let status = subject.status.clone();
+    let (_setup, lock) = Setup::run()?;
+    let result = block_on(super::run_and_destroy_async(&subject, (), lock, async {
+        status.read().unwrap().running
+    }))?;
+    assert!(result);
+    assert!(!status.read().unwrap().exists);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_guard_stops_subject_on_shutdown() -> TestResult {
+    let subject = SubjectExample::default();
+    let status = subject.status.clone();
+    let (_setup, lock) = Setup::run()?;
+    block_on(async {
+        let guard = super::guard::AsyncGuard::startup(lock, subject, ()).await?;
+        assert!(status.read().unwrap().running);
+        guard.shutdown().await
+    })?;
+    assert!(!status.read().unwrap().running);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_guard_stops_subject_when_dropped_in_runtime() -> TestResult {
+    let subject = SubjectExample::default();
+    let status = subject.status.clone();
+    let (_setup, lock) = Setup::run()?;
+    block_on(async {
+        let guard = super::guard::AsyncGuard::startup(lock, subject, ()).await?;
+        assert!(status.read().unwrap().running);
+        drop(guard);
+        Ok::<_, super::CoordinateError<Error>>(())
+    })?;
+    assert!(!status.read().unwrap().running);
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
 #[allow(unused)]
 struct Setup {
     tempdir: tempfile::TempDir,
@@ -169,6 +287,7 @@ struct SubjectStatus {
 #[derive(Debug, Default)]
 struct SubjectExample {
     status: Arc<RwLock<SubjectStatus>>,
+    stopped_with: Arc<RwLock<Option<&'static str>>>,
     cannot_stop: bool,
     cannot_destroy: bool,
 }
@@ -177,6 +296,7 @@ impl SubjectExample {
     fn already_exists() -> Self {
         Self {
             status: Arc::new(RwLock::new(SubjectStatus { exists: true, running: false })),
+            stopped_with: Arc::default(),
             cannot_stop: false,
             cannot_destroy: false,
         }
@@ -196,6 +316,7 @@ impl SubjectExample {
 impl Subject for SubjectExample {
     type Error = Error;
     type Options<'a> = ();
+    type StopOptions = &'static str;
 
     fn start(&self, _options: Self::Options<'_>) -> Result<State, Self::Error> {
         let mut status = self.status.write()?;
@@ -208,10 +329,11 @@ impl Subject for SubjectExample {
         }
     }
 
-    fn stop(&self) -> Result<State, Self::Error> {
+    fn stop(&self, options: Self::StopOptions) -> Result<State, Self::Error> {
         if self.cannot_stop {
             Err(Error { error: "cannot stop".to_string() })
         } else {
+            *self.stopped_with.write()? = Some(options);
             let mut status = self.status.write()?;
             match *status {
                 SubjectStatus { exists: _, running: false } => Ok(State::Unmodified),
@@ -246,3 +368,30 @@ impl Subject for SubjectExample {
         Ok(self.status.read()?.running)
     }
 }
+
+#[cfg(feature = "async")]
+impl super::AsyncSubject for SubjectExample {
+    type Error = Error;
+    type Options<'a> = ();
+    type StopOptions = &'static str;
+
+    async fn start(&self, options: Self::Options<'_>) -> Result<State, Self::Error> {
+        Subject::start(self, options)
+    }
+
+    async fn stop(&self, options: Self::StopOptions) -> Result<State, Self::Error> {
+        Subject::stop(self, options)
+    }
+
+    async fn destroy(&self) -> Result<State, Self::Error> {
+        Subject::destroy(self)
+    }
+
+    async fn exists(&self) -> Result<bool, Self::Error> {
+        Subject::exists(self)
+    }
+
+    async fn running(&self) -> Result<bool, Self::Error> {
+        Subject::running(self)
+    }
+}
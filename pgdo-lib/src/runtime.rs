@@ -8,7 +8,9 @@
 
 mod cache;
 pub mod constraint;
+mod details;
 mod error;
+pub mod health;
 pub mod strategy;
 
 use std::env;
@@ -18,9 +20,16 @@ use std::process::Command;
 
 use crate::util;
 use crate::version;
+pub use details::RuntimeDetails;
 pub use error::RuntimeError;
 
+/// A PostgreSQL runtime, i.e. an installation of PostgreSQL.
+///
+/// With the `serde` feature, this serializes as a map of `bindir` and
+/// `version`, the latter as a string, e.g. `{"bindir": "/usr/bin", "version":
+/// "16.4"}`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Runtime {
     /// Path to the directory containing the `pg_ctl` executable and other
     /// PostgreSQL binaries.
@@ -107,4 +116,14 @@ mod tests {
         assert_eq!(bindir, pg.bindir);
         Ok(())
     }
+
+    #[test]
+    fn runtime_details() -> TestResult {
+        let pg = Runtime::new(find_bindir())?;
+        if let Some(details) = pg.details()? {
+            assert!(details.sharedir.is_dir());
+            assert!(details.has_extension("plpgsql"));
+        }
+        Ok(())
+    }
 }
@@ -1,32 +1,75 @@
 //! Version cache for binaries.
+//!
+//! Versions are cached in memory and on disk, in `$XDG_CACHE_HOME/pgdo` (or
+//! `~/.cache/pgdo`), so that a new `pgdo` process does not need to run every
+//! `pg_ctl` it finds to learn its version. This crate's unit tests do not use
+//! the on-disk cache, so that the binaries they create do not pollute it.
 
-use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::hash::Hasher;
-use std::io::Read;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::{LazyLock, RwLock};
 
+use serde::{Deserialize, Serialize};
+
 use super::RuntimeError;
+use crate::lock;
 use crate::version::{Version, VersionError};
 
-#[derive(Debug)]
-struct Entry {
+/// Cheap-to-obtain facts about a binary. When these have not changed, neither
+/// has the binary – probably.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Stat {
     size: u64,
+    mtime: i64,
+    mtime_nsec: i64,
+    inode: u64,
+}
+
+impl From<&fs::Metadata> for Stat {
+    fn from(metadata: &fs::Metadata) -> Self {
+        Self {
+            size: metadata.size(),
+            mtime: metadata.mtime(),
+            mtime_nsec: metadata.mtime_nsec(),
+            inode: metadata.ino(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    stat: Stat,
     hash: u64,
+    #[serde(with = "crate::util::serde_display")]
     version: Version,
 }
 
-static CACHE: LazyLock<RwLock<HashMap<PathBuf, Entry>>> = LazyLock::new(|| HashMap::new().into());
+/// The on-disk representation of the cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    entries: HashMap<PathBuf, Entry>,
+}
+
+static CACHE: LazyLock<RwLock<HashMap<PathBuf, Entry>>> = LazyLock::new(|| {
+    cache_file()
+        .map(|file| load(&file))
+        .unwrap_or_default()
+        .into()
+});
 
 /// Get a cached version of PostgreSQL from a given PostgreSQL binary.
 ///
-/// If the binary referenced has changed, as measured by size and a hash
-/// calculated from its contents, this will run the binary again to determine
-/// the version. Even with hashing, a cache hit turns out to be ~10x faster than
-/// running `pg_ctl -version` (and adds 200-300µs to a cache miss).
+/// If the binary's size, modification time, and inode are unchanged, the
+/// cached version is used as-is. Otherwise a hash is calculated from its
+/// contents; if that matches the cached hash, the cached version is still
+/// good. Only when that fails is the binary run to determine its version,
+/// which is more than 10x slower than even a hashed cache hit.
 ///
 /// The [PostgreSQL "Versioning Policy"][versioning] shows that version numbers
 /// are **not** SemVer compatible. The [`version`][`mod@crate::version`] module
@@ -36,39 +79,37 @@ static CACHE: LazyLock<RwLock<HashMap<PathBuf, Entry>>> = LazyLock::new(|| HashM
 /// [versioning]: https://www.postgresql.org/support/versioning/
 pub fn version<P: AsRef<Path>>(binary: P) -> Result<Version, RuntimeError> {
     let binary: PathBuf = binary.as_ref().canonicalize()?;
-    let (size, hash) = {
-        let mut file = File::open(&binary)?;
-        let size = file.metadata()?.len();
-        let hash = {
-            let mut hasher = DefaultHasher::new();
-            let mut buffer = [0u8; 16384]; // 16 kiB buffer.
-            loop {
-                let bytes_read = file.read(&mut buffer)?;
-                if bytes_read == 0 {
-                    break; // Reached end of file
-                }
-                hasher.write(&buffer[..bytes_read]);
-            }
-            hasher.finish()
-        };
-        (size, hash)
-    };
+    let mut file = File::open(&binary)?;
+    let stat = Stat::from(&file.metadata()?);
 
-    // Try to check if we already know the version.
-    if let Ok(cache) = CACHE.read() {
-        if let Some(entry) = cache.get(&binary) {
-            if entry.size == size && entry.hash == hash {
-                return Ok(entry.version);
-            }
+    // Try to check if we already know the version, without reading the binary.
+    let cached = CACHE
+        .read()
+        .ok()
+        .and_then(|cache| cache.get(&binary).cloned());
+    if let Some(entry) = &cached {
+        if entry.stat == stat {
+            return Ok(entry.version);
         }
     }
 
-    // Okay, we definitely need to check the version.
-    let version = version_from_binary(&binary)?;
+    // The binary has been touched, at least. Has its content changed?
+    let hash = hash(&mut file)?;
+    let version = match cached {
+        Some(entry) if entry.stat.size == stat.size && entry.hash == hash => entry.version,
+        // Okay, we definitely need to check the version.
+        _ => version_from_binary(&binary)?,
+    };
 
-    // Try to cache the version.
+    // Try to cache the version, in memory and on disk.
+    let entry = Entry { stat, hash, version };
     if let Ok(mut cache) = CACHE.write() {
-        cache.insert(binary, Entry { size, hash, version });
+        cache.insert(binary.clone(), entry.clone());
+    }
+    if let Some(file) = cache_file() {
+        if let Err(err) = save(&file, binary, entry) {
+            log::debug!("Could not save runtime cache to {}: {err}", file.display());
+        }
     }
 
     Ok(version)
@@ -93,3 +134,142 @@ fn version_from_binary<P: AsRef<Path>>(binary: P) -> Result<Version, RuntimeErro
         Err(VersionError::NotFound { text: None })?
     }
 }
+
+/// Hash the contents of the given file.
+fn hash<R: Read>(file: &mut R) -> io::Result<u64> {
+    let mut hasher = Fnv1a::default();
+    let mut buffer = [0u8; 16384]; // 16 kiB buffer.
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break; // Reached end of file
+        }
+        hasher.write(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finish())
+}
+
+/// 64-bit [FNV-1a]. Unlike [`DefaultHasher`][std::hash::DefaultHasher], this is
+/// stable across Rust releases, so it's safe to persist.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// The path to the on-disk cache, if there's somewhere to put it.
+#[cfg(not(test))]
+fn cache_file() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .map(|dir| dir.join("pgdo").join("runtimes.json"))
+}
+
+/// There's no on-disk cache in unit tests.
+#[cfg(test)]
+fn cache_file() -> Option<PathBuf> {
+    None
+}
+
+/// Load the on-disk cache. Problems – e.g. a missing or corrupt file – result
+/// in an empty cache; it's only a cache.
+fn load(file: &Path) -> HashMap<PathBuf, Entry> {
+    fs::read(file)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+        .map(|cache| cache.entries)
+        .unwrap_or_default()
+}
+
+/// Add an entry to the on-disk cache, and drop entries for binaries that no
+/// longer exist.
+///
+/// This holds an exclusive lock on a neighbouring `.lock` file while it
+/// re-reads the cache – to pick up entries saved by other processes – then
+/// replaces it atomically.
+fn save(file: &Path, binary: PathBuf, entry: Entry) -> io::Result<()> {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let lock = lock::UnlockedFile::try_from(file.with_extension("lock").as_path())?
+        .lock_exclusive()
+        .map_err(io::Error::from)?;
+    let mut cache = CacheFile { entries: load(file) };
+    cache.entries.insert(binary, entry);
+    cache.entries.retain(|binary, _| binary.exists());
+    let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer(&mut temp, &cache)?;
+    temp.persist(file)?;
+    lock.unlock().map_err(io::Error::from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::{hash, load, save, Entry, Fnv1a, Stat};
+    use crate::version::Version;
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        let fnv1a = |bytes: &[u8]| {
+            let mut hasher = Fnv1a::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+        // Hashing in chunks is the same as hashing all at once.
+        assert_eq!(hash(&mut &b"foobar"[..]).unwrap(), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn cache_save_and_load() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("pgdo").join("runtimes.json");
+        assert!(load(&file).is_empty());
+
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::write(&a, "")?;
+        std::fs::write(&b, "")?;
+        let stat = Stat { size: 1, mtime: 2, mtime_nsec: 3, inode: 4 };
+        let entry = Entry { stat, hash: 5, version: Version::Post10(16, 4) };
+        save(&file, a.clone(), entry.clone())?;
+        save(&file, b.clone(), entry.clone())?;
+        let entries = load(&file);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries.get(&a), Some(&entry));
+
+        // Entries for binaries that no longer exist are dropped on save.
+        std::fs::remove_file(&b)?;
+        save(&file, a.clone(), entry.clone())?;
+        assert_eq!(load(&file).keys().collect::<Vec<_>>(), [&a]);
+
+        // A corrupt cache is treated as empty, and is replaced on save.
+        std::fs::write(&file, "not json")?;
+        assert!(load(&file).is_empty());
+        save(&file, a, entry)?;
+        assert_eq!(load(&file).len(), 1);
+        Ok(())
+    }
+}
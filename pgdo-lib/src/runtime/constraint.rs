@@ -1,50 +1,32 @@
-use std::{error, fmt, str::FromStr};
+mod parser;
+
+use std::{fmt, str::FromStr};
 
 use globset::{Error as GlobError, Glob, GlobBuilder, GlobMatcher};
+use miette::SourceSpan;
 
 use crate::version::{self, PartialVersion, VersionError};
 
 use super::Runtime;
 
-#[derive(Debug)]
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
 pub enum ConstraintError {
-    GlobError(GlobError),
-    VersionError(version::VersionError),
-}
-
-impl fmt::Display for ConstraintError {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        use ConstraintError::*;
-        match self {
-            GlobError(ref error) => write!(fmt, "could not parse constraint: {error}"),
-            VersionError(ref error) => write!(
-                fmt,
-                "could not parse version constraint {text:?}: {error}",
-                text = error.text().unwrap_or("<unknown>")
-            ),
-        }
-    }
-}
-
-impl error::Error for ConstraintError {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match *self {
-            ConstraintError::GlobError(ref error) => Some(error),
-            ConstraintError::VersionError(ref error) => Some(error),
-        }
-    }
-}
-
-impl From<GlobError> for ConstraintError {
-    fn from(error: GlobError) -> ConstraintError {
-        ConstraintError::GlobError(error)
-    }
-}
-
-impl From<version::VersionError> for ConstraintError {
-    fn from(error: version::VersionError) -> ConstraintError {
-        ConstraintError::VersionError(error)
-    }
+    #[error("could not parse constraint: {0}")]
+    GlobError(#[from] GlobError),
+    #[error(
+        "could not parse version constraint {text:?}: {0}",
+        text = .0.text().unwrap_or("<unknown>")
+    )]
+    VersionError(#[from] version::VersionError),
+    #[error("could not parse constraint: {message}")]
+    SyntaxError {
+        #[source_code]
+        input: String,
+        #[label("{label}")]
+        span: SourceSpan,
+        message: String,
+        label: String,
+    },
 }
 
 /// A constraint used when selecting a PostgreSQL runtime.
@@ -54,6 +36,9 @@ pub enum Constraint {
     BinDir(GlobMatcher),
     /// Match the given version.
     Version(PartialVersion),
+    /// Compare the runtime's version with the given version, considering only
+    /// the parts present in the latter, e.g. 16.4 is `>=16` and `<=16`.
+    Compare(Comparison, PartialVersion),
     /// Either constraint can be satisfied.
     Either(Box<Constraint>, Box<Constraint>),
     /// Both constraints must be satisfied.
@@ -122,6 +107,9 @@ impl Constraint {
         match self {
             Self::BinDir(matcher) => matcher.is_match(&runtime.bindir),
             Self::Version(version) => version.compatible(runtime.version),
+            Self::Compare(comparison, version) => {
+                comparison.matches(version.compare(runtime.version))
+            }
             Self::Either(ca, cb) => ca.matches(runtime) || cb.matches(runtime),
             Self::Both(ca, cb) => ca.matches(runtime) && cb.matches(runtime),
             Self::Not(constraint) => !constraint.matches(runtime),
@@ -181,23 +169,148 @@ impl From<PartialVersion> for Constraint {
 impl FromStr for Constraint {
     type Err = ConstraintError;
 
-    /// Parse a constraint from a string.
+    /// Parse a constraint expression from a string.
     ///
-    /// If it contains a path separator, it will be parsed as a glob pattern,
-    /// otherwise it will be parsed as a version constraint.
+    /// Atoms that contain a path separator are parsed as glob patterns,
+    /// otherwise they're parsed as version constraints. These can be combined
+    /// with `and`, `or`, `not`, and parentheses, e.g. `>=15 and not /opt/**`.
+    /// Input containing a path separator that is not a valid expression is
+    /// parsed as a single glob pattern, e.g. a path containing spaces. See
+    /// [`Display`][`fmt::Display`] for the inverse.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains(std::path::MAIN_SEPARATOR) {
-            Ok(Self::path(s)?)
-        } else {
-            Ok(Self::version(s)?)
+        parser::parse(s)
+    }
+}
+
+impl fmt::Display for Constraint {
+    /// Format this constraint as an expression that parses back into an
+    /// equivalent constraint. Parentheses are added only where needed.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_precedence(fmt, 0)
+    }
+}
+
+impl Constraint {
+    /// Precedence of this constraint's operator; higher binds more tightly.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Either(..) => 1,
+            Self::Both(..) => 2,
+            Self::Not(..) => 3,
+            _ => 4,
+        }
+    }
+
+    fn fmt_with_precedence(&self, fmt: &mut fmt::Formatter, outer: u8) -> fmt::Result {
+        let precedence = self.precedence();
+        if precedence < outer {
+            write!(fmt, "(")?;
+        }
+        match self {
+            Self::BinDir(matcher) => {
+                let pattern = matcher.glob().glob();
+                let bare = pattern.contains('/')
+                    && !pattern.contains(|c: char| c.is_whitespace() || "()\"".contains(c))
+                    && !["path:", "version:"]
+                        .iter()
+                        .any(|prefix| parser::strip_prefix_ignore_case(pattern, prefix).is_some());
+                if bare {
+                    write!(fmt, "{pattern}")?;
+                } else {
+                    let escaped = pattern.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(fmt, "path:\"{escaped}\"")?;
+                }
+            }
+            Self::Version(version) => write!(fmt, "{version}")?,
+            Self::Compare(comparison, version) => write!(fmt, "{comparison}{version}")?,
+            Self::Either(ca, cb) => {
+                ca.fmt_with_precedence(fmt, precedence)?;
+                write!(fmt, " or ")?;
+                cb.fmt_with_precedence(fmt, precedence + 1)?;
+            }
+            Self::Both(ca, cb) => {
+                ca.fmt_with_precedence(fmt, precedence)?;
+                write!(fmt, " and ")?;
+                cb.fmt_with_precedence(fmt, precedence + 1)?;
+            }
+            Self::Not(constraint) => {
+                write!(fmt, "not ")?;
+                constraint.fmt_with_precedence(fmt, precedence)?;
+            }
+            Self::Anything => write!(fmt, "anything")?,
+            Self::Nothing => write!(fmt, "nothing")?,
+        }
+        if precedence < outer {
+            write!(fmt, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// How to compare a runtime's version in a [`Constraint::Compare`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `=`
+    Eq,
+}
+
+impl Comparison {
+    /// Does the given ordering – of a runtime's version relative to the version
+    /// in a constraint – satisfy this comparison?
+    pub fn matches(self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        match self {
+            Self::Lt => ordering == Less,
+            Self::Le => ordering != Greater,
+            Self::Gt => ordering == Greater,
+            Self::Ge => ordering != Less,
+            Self::Eq => ordering == Equal,
         }
     }
+
+    /// Strip a comparison operator from the start of the given string.
+    fn strip_prefix(s: &str) -> Option<(Self, &str)> {
+        [
+            ("<=", Self::Le),
+            (">=", Self::Ge),
+            ("<", Self::Lt),
+            (">", Self::Gt),
+            ("=", Self::Eq),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (op, rest)))
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Eq => "=",
+        })
+    }
 }
 
+// Serialized as a string, e.g. `">=14 and not /opt/**"`.
+#[cfg(feature = "serde")]
+crate::util::impl_serde_display!(Constraint);
+
 #[cfg(test)]
 mod tests {
-    use super::Constraint;
     use super::PartialVersion;
+    use super::{Comparison, Constraint, ConstraintError};
+    use crate::runtime::Runtime;
 
     /// An example constraint.
     const CONSTRAINT: Constraint = Constraint::Version(PartialVersion::Post10m(13));
@@ -358,4 +471,186 @@ mod tests {
                 && matches!(*cb, Constraint::Version(_))
         ));
     }
+
+    fn runtime(bindir: &str, version: &str) -> Runtime {
+        Runtime { bindir: bindir.into(), version: version.parse().unwrap() }
+    }
+
+    fn parse(s: &str) -> Constraint {
+        s.parse()
+            .unwrap_or_else(|err| panic!("could not parse {s:?}: {err}"))
+    }
+
+    #[test]
+    fn test_parse_atoms() {
+        assert!(matches!(
+            parse("15"),
+            Constraint::Version(PartialVersion::Post10m(15))
+        ));
+        assert!(matches!(parse("/usr/**"), Constraint::BinDir(_)));
+        assert!(matches!(parse("path:**"), Constraint::BinDir(_)));
+        assert!(matches!(
+            parse(r#"path:"/my pg/**""#),
+            Constraint::BinDir(_)
+        ));
+        assert!(matches!(parse("version:9.6"), Constraint::Version(_)));
+        assert!(matches!(
+            parse(">=14"),
+            Constraint::Compare(Comparison::Ge, PartialVersion::Post10m(14))
+        ));
+        assert!(matches!(
+            parse("< 17.2"),
+            Constraint::Compare(Comparison::Lt, PartialVersion::Post10mm(17, 2))
+        ));
+        assert!(matches!(parse("ANYTHING"), Constraint::Anything));
+        assert!(matches!(parse("nothing"), Constraint::Nothing));
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        // `not` binds tighter than `and`, which binds tighter than `or`.
+        assert!(matches!(
+            parse("not 13 and 14 or 15"),
+            Constraint::Either(ca, _) if matches!(
+                *ca, Constraint::Both(ref cc, _) if matches!(**cc, Constraint::Not(_))
+            )
+        ));
+        assert!(matches!(
+            parse("13 and (14 or 15)"),
+            Constraint::Both(_, cb) if matches!(*cb, Constraint::Either(..))
+        ));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for (input, expected) in [
+            ("15", "15"),
+            (">=14 AND <17", ">=14 and <17"),
+            ("14..16", ">=14 and <16"),
+            ("14..=16", ">=14 and <=16"),
+            ("..=16", "<=16"),
+            ("9.6..", ">=9.6"),
+            ("(13 or 14) and not /opt/**", "(13 or 14) and not /opt/**"),
+            ("13 or (14 or 15)", "13 or (14 or 15)"),
+            ("(13 or 14) or 15", "13 or 14 or 15"),
+            ("not (13 and 14)", "not (13 and 14)"),
+            ("not not 13", "not not 13"),
+            (r#"path:"/my \"pg\"/**""#, r#"path:"/my \"pg\"/**""#),
+            ("path:**", r#"path:"**""#),
+            ("anything or nothing", "anything or nothing"),
+        ] {
+            let constraint = parse(input);
+            assert_eq!(constraint.to_string(), expected, "input: {input:?}");
+            assert_eq!(parse(expected).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_bare_paths_that_are_not_expressions() {
+        let app = runtime("/Library/Application Support/pg (16)/bin", "16.4");
+        let usr = runtime("/usr/lib/postgresql/16/bin", "16.4");
+        for input in [
+            "/Library/Application Support/pg (16)/bin",
+            "/Library/Application Support/**",
+            "/Library/*/pg (*)/bin",
+        ] {
+            let constraint = parse(input);
+            assert!(constraint.matches(&app), "input: {input:?}");
+            assert!(!constraint.matches(&usr), "input: {input:?}");
+        }
+        // Expressions are still preferred when they parse.
+        assert!(matches!(parse("/opt/** or 16"), Constraint::Either(..)));
+    }
+
+    #[test]
+    fn test_parse_errors_have_spans() {
+        for (input, offset, len) in [
+            ("", 0, 0),
+            (">=15 and", 5, 3),
+            ("15 16", 3, 2),
+            ("(15 or 16", 0, 9),
+            (">=15 and foo", 9, 3),
+            ("10.1.1", 0, 6),
+            ("path:/[", 5, 2),
+            (r#"path:"/opt"#, 5, 5),
+            ("13 or )", 6, 1),
+            ("..", 0, 2),
+        ] {
+            match input.parse::<Constraint>() {
+                Err(ConstraintError::SyntaxError { span, .. }) => {
+                    assert_eq!(
+                        (span.offset(), span.len()),
+                        (offset, len),
+                        "input: {input:?}"
+                    );
+                }
+                other => panic!("expected syntax error for {input:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_comparisons() {
+        let pg9 = runtime("/usr/lib/postgresql/9.6/bin", "9.6.24");
+        let pg14 = runtime("/usr/lib/postgresql/14/bin", "14.12");
+        let pg16 = runtime("/opt/postgresql/16/bin", "16.4");
+        let matching = |s: &str| {
+            let constraint = parse(s);
+            [&pg9, &pg14, &pg16]
+                .into_iter()
+                .filter(|runtime| constraint.matches(runtime))
+                .map(|runtime| runtime.version.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching(">=14"), ["14.12", "16.4"]);
+        assert_eq!(matching(">14"), ["16.4"]);
+        assert_eq!(matching("<=14"), ["9.6.24", "14.12"]);
+        assert_eq!(matching("<14.12"), ["9.6.24"]);
+        assert_eq!(matching("=16"), ["16.4"]);
+        assert_eq!(matching("=16.3"), Vec::<String>::new());
+        assert_eq!(matching("9.6..16"), ["9.6.24", "14.12"]);
+        assert_eq!(matching(">=14 and not /opt/**"), ["14.12"]);
+        assert_eq!(matching("9.6 or path:/opt/**"), ["9.6.24", "16.4"]);
+    }
+
+    #[test]
+    fn test_matches_pre_releases() {
+        let runtimes = [
+            runtime("/opt/pg/17/bin", "17.6"),
+            runtime("/opt/pg/18beta1/bin", "18beta1"),
+            runtime("/opt/pg/18beta2/bin", "18beta2"),
+            runtime("/opt/pg/18rc1/bin", "18rc1"),
+            runtime("/opt/pg/18/bin", "18.0"),
+        ];
+        let matching = |s: &str| {
+            let constraint = parse(s);
+            runtimes
+                .iter()
+                .filter(|runtime| constraint.matches(runtime))
+                .map(|runtime| runtime.version.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching("18beta"), ["18beta1", "18beta2"]);
+        assert_eq!(matching("18beta2"), ["18beta2"]);
+        assert_eq!(matching("18"), ["18beta1", "18beta2", "18rc1", "18.0"]);
+        assert_eq!(matching(">=18beta2"), ["18beta2", "18rc1", "18.0"]);
+        assert_eq!(matching("17..18rc"), ["17.6", "18beta1", "18beta2"]);
+        assert_eq!(matching(">=18.0"), ["18.0"]);
+        assert_eq!(parse("18beta or 17rc1").to_string(), "18beta or 17rc1");
+        assert!("18gamma".parse::<Constraint>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let constraint = parse("(13 or 14..16) and not path:\"/my pg/**\"");
+        let json = serde_json::to_string(&constraint).unwrap();
+        assert_eq!(json, r#""(13 or >=14 and <16) and not path:\"/my pg/**\"""#);
+        let constraint: Constraint = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            constraint.to_string(),
+            r#"(13 or >=14 and <16) and not path:"/my pg/**""#
+        );
+        assert!(serde_json::from_str::<Constraint>(r#"">=14 and""#).is_err());
+    }
 }
@@ -0,0 +1,347 @@
+//! Parser for the constraint expression language.
+//!
+//! ```text
+//! expr    := and ( "or" and )*
+//! and     := unary ( "and" unary )*
+//! unary   := "not" unary | primary
+//! primary := "(" expr ")" | "anything" | "nothing" | atom
+//! atom    := "path:" PATTERN | "version:" SPEC | PATTERN | SPEC
+//! ```
+//!
+//! A bare `PATTERN` must contain a `/`; anything else is a version `SPEC`,
+//! e.g. `15`, `>=14`, `<17.2`, `=16.4`, `14..16`, or `14..=16`. Patterns
+//! containing spaces or parentheses can be quoted, e.g. `path:"/my pg/**"`.
+//! Keywords are case-insensitive.
+//!
+//! Input that contains a `/`, but no quotes, and does not parse as an
+//! expression is treated as a single pattern, e.g. `/Library/Application Support/pg (16)/bin`. Such
+//! input was always parsed as a pattern before expressions were introduced.
+
+use std::ops::Range;
+
+use super::{Comparison, Constraint, ConstraintError};
+use crate::version::PartialVersion;
+
+/// Parse a constraint expression or, failing that, a bare path pattern.
+pub(super) fn parse(input: &str) -> Result<Constraint, ConstraintError> {
+    match parse_expr(input) {
+        // Quotes suggest an attempt at a `path:"…"` expression.
+        Err(err) if input.contains('/') && !input.contains('"') => {
+            Constraint::path(input).map_err(|_| err)
+        }
+        result => result,
+    }
+}
+
+/// Parse a constraint expression.
+fn parse_expr(input: &str) -> Result<Constraint, ConstraintError> {
+    let tokens = Lexer { input, pos: 0 }.tokens()?;
+    let mut parser = Parser { input, tokens, pos: 0 };
+    let constraint = parser.expr()?;
+    match parser.next() {
+        Token { kind: Kind::End, .. } => Ok(constraint),
+        Token { span, .. } => Err(parser.error(
+            span,
+            "unexpected input after constraint",
+            "expected `and`, `or`, or the end of the constraint",
+        )),
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind<'a> {
+    Open,
+    Close,
+    Word(&'a str),
+    Quoted(String),
+    End,
+}
+
+#[derive(Clone, Debug)]
+struct Token<'a> {
+    kind: Kind<'a>,
+    span: Range<usize>,
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn tokens(mut self) -> Result<Vec<Token<'a>>, ConstraintError> {
+        let mut tokens = Vec::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            let start = self.pos;
+            let kind = match trimmed.chars().next() {
+                None => {
+                    tokens.push(Token { kind: Kind::End, span: start..start });
+                    return Ok(tokens);
+                }
+                Some('(') => {
+                    self.pos += 1;
+                    Kind::Open
+                }
+                Some(')') => {
+                    self.pos += 1;
+                    Kind::Close
+                }
+                Some('"') => Kind::Quoted(self.quoted()?),
+                Some(_) => {
+                    let len = trimmed
+                        .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                        .unwrap_or(trimmed.len());
+                    self.pos += len;
+                    Kind::Word(&trimmed[..len])
+                }
+            };
+            tokens.push(Token { kind, span: start..self.pos });
+        }
+    }
+
+    /// Lex a double-quoted string. Backslash escapes the next character.
+    fn quoted(&mut self) -> Result<String, ConstraintError> {
+        let start = self.pos;
+        let mut string = String::new();
+        let mut chars = self.input[start + 1..].char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos = start + 1 + index + 1;
+                    return Ok(string);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => string.push(c),
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+        Err(error(
+            self.input,
+            start..self.input.len(),
+            "unterminated string",
+            "this string has no closing quote",
+        ))
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token<'a> {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token<'a> {
+        let token = self.tokens[self.pos].clone();
+        if !matches!(token.kind, Kind::End) {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /// Consume the next token if it's the given keyword.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek().kind {
+            Kind::Word(word) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn error(&self, span: Range<usize>, message: &str, label: &str) -> ConstraintError {
+        error(self.input, span, message, label)
+    }
+
+    fn expr(&mut self) -> Result<Constraint, ConstraintError> {
+        let mut constraint = self.and()?;
+        while self.keyword("or") {
+            let rhs = self.and()?;
+            constraint = Constraint::Either(Box::new(constraint), Box::new(rhs));
+        }
+        Ok(constraint)
+    }
+
+    fn and(&mut self) -> Result<Constraint, ConstraintError> {
+        let mut constraint = self.unary()?;
+        while self.keyword("and") {
+            let rhs = self.unary()?;
+            constraint = Constraint::Both(Box::new(constraint), Box::new(rhs));
+        }
+        Ok(constraint)
+    }
+
+    fn unary(&mut self) -> Result<Constraint, ConstraintError> {
+        if self.keyword("not") {
+            Ok(Constraint::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Constraint, ConstraintError> {
+        let token = self.next();
+        match token.kind {
+            Kind::Open => {
+                let constraint = self.expr()?;
+                match self.next() {
+                    Token { kind: Kind::Close, .. } => Ok(constraint),
+                    Token { span, .. } => Err(self.error(
+                        token.span.start..span.end,
+                        "unclosed parenthesis",
+                        "expected `)` to close this",
+                    )),
+                }
+            }
+            Kind::Quoted(pattern) => self.path(&pattern, token.span),
+            Kind::Word(word) if word.eq_ignore_ascii_case("anything") => Ok(Constraint::Anything),
+            Kind::Word(word) if word.eq_ignore_ascii_case("nothing") => Ok(Constraint::Nothing),
+            Kind::Word(word)
+                if ["and", "or", "not"]
+                    .iter()
+                    .any(|k| word.eq_ignore_ascii_case(k)) =>
+            {
+                Err(self.error(token.span, "expected a constraint", "found a keyword"))
+            }
+            Kind::Word(word) => self.atom(word, token.span),
+            Kind::Close => Err(self.error(token.span, "expected a constraint", "found `)`")),
+            Kind::End => match self.pos.checked_sub(1).map(|pos| &self.tokens[pos]) {
+                Some(previous) => Err(self.error(
+                    previous.span.clone(),
+                    "expected a constraint",
+                    "expected a constraint after this",
+                )),
+                None => Err(self.error(token.span, "expected a constraint", "empty constraint")),
+            },
+        }
+    }
+
+    fn atom(&mut self, word: &'a str, span: Range<usize>) -> Result<Constraint, ConstraintError> {
+        if let Some(pattern) = strip_prefix_ignore_case(word, "path:") {
+            if pattern.is_empty() {
+                let token = self.next();
+                match token.kind {
+                    Kind::Quoted(pattern) => self.path(&pattern, token.span),
+                    Kind::Word(pattern) => self.path(pattern, token.span),
+                    _ => Err(self.error(span, "expected a path pattern", "after this")),
+                }
+            } else {
+                self.path(pattern, span.start + 5..span.end)
+            }
+        } else if let Some(spec) = strip_prefix_ignore_case(word, "version:") {
+            if spec.is_empty() {
+                match self.next() {
+                    Token { kind: Kind::Word(spec), span } => self.spec(spec, span),
+                    _ => Err(self.error(span, "expected a version", "after this")),
+                }
+            } else {
+                self.spec(spec, span.start + 8..span.end)
+            }
+        } else if word.contains('/') {
+            self.path(word, span)
+        } else {
+            self.spec(word, span)
+        }
+    }
+
+    fn path(&self, pattern: &str, span: Range<usize>) -> Result<Constraint, ConstraintError> {
+        Constraint::path(pattern).map_err(|err| {
+            let message = format!("invalid path pattern: {}", err.kind());
+            self.error(span, &message, "this pattern")
+        })
+    }
+
+    /// Parse a version specification, e.g. `15`, `>=14.2`, `14..16`.
+    fn spec(&mut self, spec: &'a str, span: Range<usize>) -> Result<Constraint, ConstraintError> {
+        if let Some((lo, hi)) = spec.split_once("..") {
+            let (hi, hi_op, hi_offset) = match hi.strip_prefix('=') {
+                Some(hi) => (hi, Comparison::Le, lo.len() + 3),
+                None => (hi, Comparison::Lt, lo.len() + 2),
+            };
+            let lo_span = span.start..span.start + lo.len();
+            let hi_span = span.start + hi_offset..span.end;
+            let lo = (!lo.is_empty())
+                .then(|| self.version(lo, lo_span))
+                .transpose()?
+                .map(|v| Constraint::Compare(Comparison::Ge, v));
+            let hi = if hi.is_empty() && hi_op == Comparison::Le {
+                return Err(self.error(hi_span, "expected a version", "after `..=`"));
+            } else {
+                (!hi.is_empty())
+                    .then(|| self.version(hi, hi_span))
+                    .transpose()?
+                    .map(|v| Constraint::Compare(hi_op, v))
+            };
+            return match (lo, hi) {
+                (Some(lo), Some(hi)) => Ok(Constraint::Both(Box::new(lo), Box::new(hi))),
+                (Some(bound), None) | (None, Some(bound)) => Ok(bound),
+                (None, None) => Err(self.error(span, "empty version range", "no bounds")),
+            };
+        }
+        let (op, version, offset) = match Comparison::strip_prefix(spec) {
+            Some((op, "")) => match self.next() {
+                Token { kind: Kind::Word(version), span } => (op, version, span.start),
+                _ => return Err(self.error(span, "expected a version", "after this")),
+            },
+            Some((op, version)) => (op, version, span.end - version.len()),
+            None => {
+                return Ok(Constraint::Version(self.version(spec, span)?));
+            }
+        };
+        let version = self.version(version, offset..offset + version.len())?;
+        Ok(Constraint::Compare(op, version))
+    }
+
+    fn version(
+        &self,
+        version: &str,
+        span: Range<usize>,
+    ) -> Result<PartialVersion, ConstraintError> {
+        // A pre-release suffix, e.g. `beta2` in `18beta2`, is checked when
+        // parsing the version below.
+        let numbers = version
+            .find(|c: char| c.is_ascii_alphabetic())
+            .map_or(version, |split| &version[..split]);
+        let well_formed = numbers.split('.').count() <= 3
+            && numbers
+                .split('.')
+                .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+        if !well_formed {
+            return Err(self.error(
+                span,
+                "invalid version",
+                "expected e.g. 15, 16.4, 9.6, or 18beta",
+            ));
+        }
+        version.parse().map_err(|err| {
+            let message = format!("invalid version: {err}");
+            self.error(span, &message, "this version")
+        })
+    }
+}
+
+pub(super) fn strip_prefix_ignore_case<'a>(word: &'a str, prefix: &str) -> Option<&'a str> {
+    match word.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&word[prefix.len()..]),
+        _ => None,
+    }
+}
+
+fn error(input: &str, span: Range<usize>, message: &str, label: &str) -> ConstraintError {
+    ConstraintError::SyntaxError {
+        input: input.into(),
+        span: (span.start, span.len()).into(),
+        message: message.into(),
+        label: label.into(),
+    }
+}
@@ -0,0 +1,170 @@
+//! Details of a runtime, as reported by its `pg_config`.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Runtime, RuntimeError};
+
+/// How a runtime was built, and what it comes with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RuntimeDetails {
+    /// Architecture-independent support files, e.g. extension scripts.
+    pub sharedir: PathBuf,
+    /// Dynamically loadable modules.
+    pub pkglibdir: PathBuf,
+    /// C header files for client interfaces.
+    pub includedir: PathBuf,
+    /// C header files for the server, needed to build extensions.
+    pub includedir_server: PathBuf,
+    /// Options given to `configure` when PostgreSQL was built.
+    pub configure: Vec<String>,
+    /// The C compiler used when PostgreSQL was built.
+    pub cc: String,
+    /// Extensions that can be installed with `CREATE EXTENSION`, i.e. those
+    /// with a control file in `$sharedir/extension`.
+    pub extensions: Vec<String>,
+    /// Loadable modules in `pkglibdir`, e.g. contrib modules like
+    /// `auto_explain` that are not extensions.
+    pub modules: Vec<String>,
+}
+
+impl RuntimeDetails {
+    /// Was this runtime configured with the given option, e.g. `--with-icu`?
+    ///
+    /// Options that take a value, e.g. `--with-uuid=e2fs`, match by name too.
+    pub fn configured_with(&self, option: &str) -> bool {
+        self.configure.iter().any(|configured| {
+            configured == option
+                || configured
+                    .strip_prefix(option)
+                    .is_some_and(|rest| rest.starts_with('='))
+        })
+    }
+
+    /// Does this runtime come with the given extension?
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Parse the output of `pg_config` when run with no arguments, then look
+    /// for extensions and modules in the directories it names.
+    fn from_pg_config(output: &str) -> Self {
+        let settings: HashMap<&str, &str> = output
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .collect();
+        let setting = |name| settings.get(name).copied().unwrap_or_default();
+        let sharedir = PathBuf::from(setting("SHAREDIR"));
+        let pkglibdir = PathBuf::from(setting("PKGLIBDIR"));
+        Self {
+            extensions: names_in(&sharedir.join("extension"), "control"),
+            modules: names_in(&pkglibdir, std::env::consts::DLL_EXTENSION),
+            sharedir,
+            pkglibdir,
+            includedir: setting("INCLUDEDIR").into(),
+            includedir_server: setting("INCLUDEDIR-SERVER").into(),
+            configure: split_configure(setting("CONFIGURE")),
+            cc: setting("CC").into(),
+        }
+    }
+}
+
+impl Runtime {
+    /// Details of this runtime, from its `pg_config`.
+    ///
+    /// Returns [`None`] if this runtime has no `pg_config`; some distributions
+    /// package it separately, with the development headers.
+    pub fn details(&self) -> Result<Option<RuntimeDetails>, RuntimeError> {
+        let pg_config = self.bindir.join("pg_config");
+        if !pg_config.exists() {
+            return Ok(None);
+        }
+        let output = self.execute("pg_config").output()?;
+        if output.status.success() {
+            let output = String::from_utf8_lossy(&output.stdout);
+            Ok(Some(RuntimeDetails::from_pg_config(&output)))
+        } else {
+            Err(RuntimeError::DetailsError(
+                String::from_utf8_lossy(&output.stderr).trim().into(),
+            ))
+        }
+    }
+}
+
+/// Names of files in `dir` with the given extension, sorted. Problems reading
+/// the directory – e.g. it doesn't exist – result in an empty list.
+fn names_in(dir: &Path, extension: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new(extension)))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Split the `CONFIGURE` line from `pg_config`. Each option is wrapped in
+/// single quotes, e.g. `'--with-icu' '--with-extra-version= (Debian)'`.
+fn split_configure(configure: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut option: Option<String> = None;
+    for c in configure.chars() {
+        match (c, &mut option) {
+            ('\'', None) => option = Some(String::new()),
+            ('\'', Some(_)) => options.extend(option.take()),
+            (c, Some(option)) => option.push(c),
+            (_, None) => (), // Whitespace between options.
+        }
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_configure, RuntimeDetails};
+
+    #[test]
+    fn split_configure_options() {
+        assert_eq!(
+            split_configure(" '--prefix=/usr' '--with-extra-version= (Debian 15.18)' '--with-icu'"),
+            vec![
+                "--prefix=/usr",
+                "--with-extra-version= (Debian 15.18)",
+                "--with-icu"
+            ],
+        );
+        assert!(split_configure("").is_empty());
+    }
+
+    #[test]
+    fn details_from_pg_config() {
+        let details = RuntimeDetails::from_pg_config(concat!(
+            "BINDIR = /usr/lib/postgresql/16/bin\n",
+            "INCLUDEDIR = /usr/include/postgresql\n",
+            "INCLUDEDIR-SERVER = /usr/include/postgresql/16/server\n",
+            "PKGLIBDIR = /nonexistent/lib\n",
+            "SHAREDIR = /nonexistent/share\n",
+            "CONFIGURE =  '--with-icu' '--with-uuid=e2fs'\n",
+            "CC = gcc\n",
+            "LDFLAGS_EX = \n",
+        ));
+        assert_eq!(
+            details.includedir_server.to_str(),
+            Some("/usr/include/postgresql/16/server")
+        );
+        assert_eq!(details.cc, "gcc");
+        assert!(details.configured_with("--with-icu"));
+        assert!(details.configured_with("--with-uuid"));
+        assert!(!details.configured_with("--with-llvm"));
+        assert!(!details.configured_with("--with-uu"));
+        assert!(details.extensions.is_empty());
+        assert!(details.modules.is_empty());
+    }
+}
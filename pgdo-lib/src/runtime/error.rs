@@ -6,4 +6,6 @@ pub enum RuntimeError {
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     VersionError(#[from] version::VersionError),
+    #[error("Could not get runtime details from pg_config: {0}")]
+    DetailsError(String),
 }
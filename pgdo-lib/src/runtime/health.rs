@@ -0,0 +1,196 @@
+//! Check that a runtime has the binaries it needs.
+//!
+//! A runtime is discovered by finding `pg_ctl`, but some packagings – e.g.
+//! Debian's – split PostgreSQL across several packages, so a runtime may be
+//! missing `initdb`, `postgres`, or client tools like `psql`.
+
+use std::fmt;
+use std::os::unix::fs::PermissionsExt;
+
+use super::{cache, Runtime};
+use crate::version::Version;
+
+/// Binaries without which a runtime cannot create, start, or stop a cluster.
+pub const REQUIRED: &[&str] = &["pg_ctl", "initdb", "postgres"];
+
+/// Binaries needed only for some operations, e.g. `psql` for a shell, or
+/// `pg_basebackup` for backups.
+pub const OPTIONAL: &[&str] = &[
+    "psql",
+    "pg_basebackup",
+    "pg_dump",
+    "pg_restore",
+    "pg_config",
+];
+
+/// The result of checking a single binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinaryStatus {
+    /// Present, executable, and reports the runtime's version.
+    Ok,
+    /// Not present in the runtime's `bindir`.
+    Missing,
+    /// Present but not executable.
+    NotExecutable,
+    /// Reports a different version to the runtime.
+    VersionMismatch(Version),
+    /// Did not report a version that we could understand.
+    VersionUnknown(String),
+}
+
+impl BinaryStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok)
+    }
+}
+
+impl fmt::Display for BinaryStatus {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ok => write!(fmt, "ok"),
+            Self::Missing => write!(fmt, "missing"),
+            Self::NotExecutable => write!(fmt, "not executable"),
+            Self::VersionMismatch(version) => write!(fmt, "reports version {version}"),
+            Self::VersionUnknown(error) => write!(fmt, "version unknown: {error}"),
+        }
+    }
+}
+
+/// The result of checking a binary in a runtime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryCheck {
+    pub name: String,
+    /// Is this binary in [`REQUIRED`]?
+    pub required: bool,
+    pub status: BinaryStatus,
+}
+
+/// The results of checking a runtime's binaries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Health(pub Vec<BinaryCheck>);
+
+impl Health {
+    /// Are all of the [`REQUIRED`] binaries okay?
+    pub fn is_complete(&self) -> bool {
+        self.0
+            .iter()
+            .filter(|check| check.required)
+            .all(|check| check.status.is_ok())
+    }
+
+    /// Are all of the checked binaries okay?
+    pub fn is_ok(&self) -> bool {
+        self.0.iter().all(|check| check.status.is_ok())
+    }
+
+    /// Checks that have found a problem.
+    pub fn problems(&self) -> impl Iterator<Item = &BinaryCheck> {
+        self.0.iter().filter(|check| !check.status.is_ok())
+    }
+}
+
+impl Runtime {
+    /// Check the [`REQUIRED`] and [`OPTIONAL`] binaries in this runtime.
+    pub fn check(&self) -> Health {
+        self.check_binaries(REQUIRED.iter().chain(OPTIONAL))
+    }
+
+    /// Check the given binaries in this runtime: each must be present,
+    /// executable, and report the same version as the runtime.
+    ///
+    /// Versions are cached in the same way as the runtime's own version, so
+    /// checking again is cheap.
+    pub fn check_binaries<I, T>(&self, names: I) -> Health
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        Health(
+            names
+                .into_iter()
+                .map(|name| {
+                    let name = name.as_ref();
+                    BinaryCheck {
+                        name: name.to_owned(),
+                        required: REQUIRED.contains(&name),
+                        status: self.check_binary(name),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn check_binary(&self, name: &str) -> BinaryStatus {
+        let path = self.bindir.join(name);
+        match path.metadata() {
+            Err(_) => BinaryStatus::Missing,
+            Ok(metadata) if !metadata.is_file() => BinaryStatus::Missing,
+            Ok(metadata) if metadata.permissions().mode() & 0o111 == 0 => {
+                BinaryStatus::NotExecutable
+            }
+            Ok(_) => match cache::version(&path) {
+                Ok(version) if version == self.version => BinaryStatus::Ok,
+                Ok(version) => BinaryStatus::VersionMismatch(version),
+                Err(err) => BinaryStatus::VersionUnknown(err.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::{BinaryStatus, Runtime};
+    use crate::version::Version;
+
+    type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+    /// Write a fake binary that reports the given version.
+    fn fake(dir: &std::path::Path, name: &str, version: &str, mode: u32) -> std::io::Result<()> {
+        let path = dir.join(name);
+        fs::write(
+            &path,
+            format!("#!/bin/sh\necho '{name} (PostgreSQL) {version}'\n"),
+        )?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))
+    }
+
+    #[test]
+    fn check_binaries() -> TestResult {
+        let bindir = tempfile::tempdir()?;
+        fake(bindir.path(), "pg_ctl", "16.4", 0o755)?;
+        fake(bindir.path(), "initdb", "16.4", 0o755)?;
+        fake(bindir.path(), "postgres", "16.3", 0o755)?;
+        fake(bindir.path(), "psql", "16.4", 0o644)?;
+        let runtime = Runtime {
+            bindir: bindir.path().into(),
+            version: Version::Post10(16, 4),
+        };
+        let health = runtime.check();
+        let status = |name: &str| {
+            health
+                .0
+                .iter()
+                .find(|check| check.name == name)
+                .map(|check| check.status.clone())
+        };
+        assert_eq!(status("pg_ctl"), Some(BinaryStatus::Ok));
+        assert_eq!(status("initdb"), Some(BinaryStatus::Ok));
+        assert_eq!(
+            status("postgres"),
+            Some(BinaryStatus::VersionMismatch(Version::Post10(16, 3)))
+        );
+        assert_eq!(status("psql"), Some(BinaryStatus::NotExecutable));
+        assert_eq!(status("pg_dump"), Some(BinaryStatus::Missing));
+        assert!(!health.is_complete());
+
+        fake(bindir.path(), "postgres", "16.4", 0o755)?;
+        let health = runtime.check();
+        assert!(health.is_complete());
+        assert!(!health.is_ok());
+        assert!(runtime.check_binaries(["pg_ctl", "initdb"]).is_ok());
+        Ok(())
+    }
+}
@@ -1,9 +1,19 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStringExt;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex, PoisonError};
 
 use super::{constraint::Constraint, Runtime};
 
+mod description;
+mod policy;
+
+pub use description::StrategyDescription;
+pub use policy::{PolicyError, SelectionPolicy, StrategyWithPolicy, VersionPolicy};
+
 pub type Runtimes<'a> = Box<dyn Iterator<Item = Runtime> + 'a>;
 
 /// A strategy for finding PostgreSQL runtimes.
@@ -23,6 +33,15 @@ pub trait StrategyLike: std::fmt::Debug + std::panic::RefUnwindSafe + 'static {
     /// Find all runtimes that this strategy knows about.
     fn runtimes(&self) -> Runtimes<'_>;
 
+    /// A short name for where this strategy finds runtimes, e.g. `PATH`. This
+    /// is for humans, to explain where a runtime came from.
+    ///
+    /// The default implementation returns `other`.
+    #[allow(clippy::unnecessary_literal_bound)] // Implementations may borrow.
+    fn source(&self) -> &str {
+        "other"
+    }
+
     /// Determine the most appropriate runtime known to this strategy for the
     /// given constraint.
     ///
@@ -43,6 +62,15 @@ pub trait StrategyLike: std::fmt::Debug + std::panic::RefUnwindSafe + 'static {
     fn fallback(&self) -> Option<Runtime> {
         self.runtimes().max_by(|ra, rb| ra.version.cmp(&rb.version))
     }
+
+    /// Describe this strategy so that it can be recreated later, e.g. after
+    /// being written to a configuration file.
+    ///
+    /// The default implementation returns [`None`], i.e. this strategy cannot
+    /// be described.
+    fn describe(&self) -> Option<StrategyDescription> {
+        None
+    }
 }
 
 /// Find runtimes on a given path.
@@ -52,6 +80,13 @@ pub trait StrategyLike: std::fmt::Debug + std::panic::RefUnwindSafe + 'static {
 #[derive(Clone, Debug)]
 pub struct RuntimesOnPath(PathBuf);
 
+impl RuntimesOnPath {
+    /// Find runtimes on the given path, e.g. `/opt/pg/17/bin:/opt/pg/16/bin`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self(path.into())
+    }
+}
+
 impl StrategyLike for RuntimesOnPath {
     fn runtimes(&self) -> Runtimes<'_> {
         Box::new(
@@ -61,6 +96,14 @@ impl StrategyLike for RuntimesOnPath {
                 .filter_map(|bindir| Runtime::new(bindir).ok()),
         )
     }
+
+    fn source(&self) -> &'static str {
+        "path"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Path(self.0.clone()))
+    }
 }
 
 /// Find runtimes on `PATH` (from the environment).
@@ -82,6 +125,118 @@ impl StrategyLike for RuntimesOnPathEnv {
                 .into_iter(),
         )
     }
+
+    fn source(&self) -> &'static str {
+        "PATH"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::PathEnv)
+    }
+}
+
+/// Find runtimes on `PGDO_RUNTIME_PATH` (from the environment).
+///
+/// This is parsed like `PATH`. Each entry can be a directory containing
+/// `pg_ctl`, or an installation prefix with `pg_ctl` in its `bin`
+/// subdirectory.
+#[derive(Clone, Debug)]
+pub struct RuntimesOnRuntimePathEnv;
+
+impl RuntimesOnRuntimePathEnv {
+    /// The environment variable consulted by this strategy.
+    pub const VAR: &'static str = "PGDO_RUNTIME_PATH";
+
+    /// Find runtime directories on the given path.
+    pub fn find(path: &OsStr) -> Vec<PathBuf> {
+        env::split_paths(path)
+            .filter_map(|dir| {
+                if dir.join("pg_ctl").exists() {
+                    Some(dir)
+                } else if dir.join("bin").join("pg_ctl").exists() {
+                    Some(dir.join("bin"))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl StrategyLike for RuntimesOnRuntimePathEnv {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            env::var_os(Self::VAR)
+                .map(|path| Self::find(&path))
+                .unwrap_or_default()
+                .into_iter()
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        Self::VAR
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::RuntimePathEnv)
+    }
+}
+
+/// Find the runtime that `pg_config --bindir` reports.
+///
+/// Uses the `pg_config` named in the `PG_CONFIG` environment variable, as
+/// PGXS does, otherwise the one on `PATH`.
+#[derive(Clone, Debug)]
+pub struct RuntimesOnPgConfig;
+
+impl RuntimesOnPgConfig {
+    /// Ask `pg_config` for its `bindir`.
+    ///
+    /// The answer is cached for the life of the process because runtimes are
+    /// often selected many times, and running `pg_config` each time is slow.
+    pub fn find() -> Option<PathBuf> {
+        static CACHE: LazyLock<Mutex<HashMap<OsString, Option<PathBuf>>>> =
+            LazyLock::new(Default::default);
+        let pg_config = env::var_os("PG_CONFIG").unwrap_or_else(|| "pg_config".into());
+        let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        cache
+            .entry(pg_config)
+            .or_insert_with_key(|pg_config| {
+                let output = Command::new(pg_config).arg("--bindir").output().ok()?;
+                if output.status.success() {
+                    let mut bindir = output.stdout;
+                    while bindir.last().is_some_and(u8::is_ascii_whitespace) {
+                        bindir.pop();
+                    }
+                    Some(OsString::from_vec(bindir).into())
+                } else {
+                    None
+                }
+            })
+            .clone()
+    }
+}
+
+impl StrategyLike for RuntimesOnPgConfig {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            Self::find()
+                .into_iter()
+                .filter(|bindir| bindir.join("pg_ctl").exists())
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        "pg_config"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::PgConfig)
+    }
 }
 
 /// Find runtimes using platform-specific knowledge.
@@ -101,16 +256,7 @@ impl RuntimesOnPlatform {
     /// For example: on Debian and Ubuntu, check `/usr/lib/postgresql`.
     #[cfg(any(doc, target_os = "linux"))]
     pub fn find() -> Vec<PathBuf> {
-        glob::glob("/usr/lib/postgresql/*/bin/pg_ctl")
-            .ok()
-            .map(|entries| {
-                entries
-                    .filter_map(Result::ok)
-                    .filter(|path| path.is_file())
-                    .filter_map(|path| path.parent().map(Path::to_owned))
-                    .collect()
-            })
-            .unwrap_or_default()
+        find_by_glob(["/usr/lib/postgresql/*/bin/pg_ctl"])
     }
 
     /// Find runtimes using platform-specific knowledge (macOS).
@@ -132,19 +278,11 @@ impl RuntimesOnPlatform {
                     None
                 }
             })
-            .and_then(|brew_prefix| {
-                glob::glob(&format!(
+            .map(|brew_prefix| {
+                find_by_glob([format!(
                     "{}/Cellar/postgresql@*/*/bin/pg_ctl",
                     brew_prefix.to_string_lossy().trim_end()
-                ))
-                .ok()
-            })
-            .map(|entries| {
-                entries
-                    .filter_map(Result::ok)
-                    .filter(|path| path.is_file())
-                    .filter_map(|path| path.parent().map(Path::to_owned))
-                    .collect()
+                )])
             })
             .unwrap_or_default()
     }
@@ -159,6 +297,195 @@ impl StrategyLike for RuntimesOnPlatform {
                 .filter_map(|bindir| Runtime::new(bindir).ok()),
         )
     }
+
+    fn source(&self) -> &'static str {
+        "platform"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Platform)
+    }
+}
+
+/// Find runtimes installed from the PostgreSQL Yum repository, as used on RHEL,
+/// Fedora, and derivatives, i.e. in `/usr/pgsql-*`.
+#[derive(Clone, Debug)]
+pub struct RuntimesOnRedHat;
+
+impl RuntimesOnRedHat {
+    pub fn find() -> Vec<PathBuf> {
+        find_by_glob(["/usr/pgsql-*/bin/pg_ctl"])
+    }
+}
+
+impl StrategyLike for RuntimesOnRedHat {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            Self::find()
+                .into_iter()
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        "redhat"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::RedHat)
+    }
+}
+
+/// Find runtimes installed in `/opt`, e.g. `/opt/postgresql-16` or
+/// `/opt/postgresql/16`.
+#[derive(Clone, Debug)]
+pub struct RuntimesOnOpt;
+
+impl RuntimesOnOpt {
+    pub fn find() -> Vec<PathBuf> {
+        find_by_glob([
+            "/opt/postgresql*/bin/pg_ctl",
+            "/opt/postgresql*/*/bin/pg_ctl",
+        ])
+    }
+}
+
+impl StrategyLike for RuntimesOnOpt {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            Self::find()
+                .into_iter()
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        "opt"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Opt)
+    }
+}
+
+/// Find runtimes installed into Nix profiles.
+///
+/// Checks the profiles in `NIX_PROFILES`, the user's profile, and the system
+/// profiles. Profiles contain symlinks into the Nix store, so the runtimes
+/// found refer to the store paths; these do not change when the profile does.
+#[derive(Clone, Debug)]
+pub struct RuntimesOnNix;
+
+impl RuntimesOnNix {
+    pub fn find() -> Vec<PathBuf> {
+        let mut profiles: Vec<PathBuf> = env::var("NIX_PROFILES")
+            .map(|profiles| profiles.split_whitespace().map(PathBuf::from).collect())
+            .unwrap_or_default();
+        if let Some(home) = env::var_os("HOME") {
+            profiles.push(Path::new(&home).join(".nix-profile"));
+        }
+        if let Ok(user) = env::var("USER") {
+            profiles.push(Path::new("/etc/profiles/per-user").join(user));
+        }
+        profiles.push("/nix/var/nix/profiles/default".into());
+        profiles.push("/run/current-system/sw".into());
+
+        let mut bindirs: Vec<PathBuf> = profiles
+            .iter()
+            .filter_map(|profile| profile.join("bin").join("pg_ctl").canonicalize().ok())
+            .filter_map(|pg_ctl| pg_ctl.parent().map(Path::to_owned))
+            .collect();
+        bindirs.dedup();
+        bindirs
+    }
+}
+
+impl StrategyLike for RuntimesOnNix {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            Self::find()
+                .into_iter()
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        "nix"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Nix)
+    }
+}
+
+/// Find runtimes installed by version managers in the user's home directory:
+/// [pgenv](https://github.com/theory/pgenv), [asdf](https://asdf-vm.com/),
+/// and [mise](https://mise.jdx.dev/).
+#[derive(Clone, Debug)]
+pub struct RuntimesOnVersionManagers;
+
+impl RuntimesOnVersionManagers {
+    pub fn find() -> Vec<PathBuf> {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let root = |var: &str, default: &str| {
+            env::var_os(var)
+                .map(PathBuf::from)
+                .or_else(|| home.as_ref().map(|home| home.join(default)))
+        };
+        let patterns = [
+            root("PGENV_ROOT", ".pgenv").map(|root| root.join("pgsql-*/bin/pg_ctl")),
+            root("ASDF_DATA_DIR", ".asdf").map(|root| root.join("installs/postgres/*/bin/pg_ctl")),
+            root("MISE_DATA_DIR", ".local/share/mise")
+                .map(|root| root.join("installs/postgres/*/bin/pg_ctl")),
+        ];
+        find_by_glob(
+            patterns
+                .into_iter()
+                .flatten()
+                .map(|pattern| pattern.to_string_lossy().into_owned()),
+        )
+    }
+}
+
+impl StrategyLike for RuntimesOnVersionManagers {
+    fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(
+            Self::find()
+                .into_iter()
+                // Throw away runtimes that we can't determine the version for.
+                .filter_map(|bindir| Runtime::new(bindir).ok()),
+        )
+    }
+
+    fn source(&self) -> &'static str {
+        "version-manager"
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::VersionManagers)
+    }
+}
+
+/// Find `pg_ctl` executables matching the given glob patterns, and return the
+/// directories containing them.
+fn find_by_glob<I, P>(patterns: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<str>,
+{
+    patterns
+        .into_iter()
+        .filter_map(|pattern| glob::glob(pattern.as_ref()).ok())
+        .flat_map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file())
+                .filter_map(|path| path.parent().map(Path::to_owned))
+        })
+        .collect()
 }
 
 /// Compose strategies for finding PostgreSQL runtimes.
@@ -212,12 +539,74 @@ impl Strategy {
     }
 }
 
+impl Strategy {
+    /// Choose runtimes from this strategy according to the given policy.
+    ///
+    /// See [`StrategyWithPolicy`].
+    #[must_use]
+    pub fn with_policy(self, policy: SelectionPolicy) -> Self {
+        StrategyWithPolicy { strategy: self, policy }.into()
+    }
+
+    /// Skip runtimes that are missing any of the given binaries, or in which
+    /// they're broken; see [`Runtime::check_binaries`].
+    ///
+    /// Use this to avoid choosing a runtime that is incomplete for a given
+    /// operation, e.g. [`health::REQUIRED`][`super::health::REQUIRED`] for
+    /// running a cluster, plus `pg_basebackup` to take backups.
+    #[must_use]
+    pub fn requiring<I, T>(self, binaries: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let binaries = binaries.into_iter().map(Into::into).collect();
+        StrategyRequiring { strategy: self, binaries }.into()
+    }
+
+    /// Like [`StrategyLike::runtimes`], but also yields the
+    /// [source][`StrategyLike::source`] of each runtime, i.e. the name of the
+    /// strategy that found it. Runtimes are deduplicated in the same way.
+    pub fn runtimes_with_source(&self) -> Box<dyn Iterator<Item = (&str, Runtime)> + '_> {
+        match self {
+            Self::Chain(chain) => {
+                let mut seen = std::collections::HashSet::new();
+                Box::new(
+                    chain
+                        .iter()
+                        .flat_map(Strategy::runtimes_with_source)
+                        .filter(move |(_, runtime)| seen.insert(runtime.version)),
+                )
+            }
+            Self::Delegated(strategy) => {
+                let mut seen = std::collections::HashSet::new();
+                let source = strategy.source();
+                Box::new(
+                    strategy
+                        .runtimes()
+                        .filter(move |runtime| seen.insert(runtime.version))
+                        .map(move |runtime| (source, runtime)),
+                )
+            }
+            Self::Single(runtime) => Box::new(std::iter::once((self.source(), runtime.clone()))),
+        }
+    }
+}
+
 impl Default for Strategy {
-    /// Select runtimes from on `PATH` followed by platform-specific runtimes.
+    /// Select runtimes from `PGDO_RUNTIME_PATH`, then `PATH`, then the runtime
+    /// that `pg_config` reports, then platform-specific and other well-known
+    /// locations.
     fn default() -> Self {
         Self::Chain(VecDeque::new())
-            .push_front(RuntimesOnPathEnv)
+            .push_back(RuntimesOnRuntimePathEnv)
+            .push_back(RuntimesOnPathEnv)
+            .push_back(RuntimesOnPgConfig)
             .push_back(RuntimesOnPlatform)
+            .push_back(RuntimesOnRedHat)
+            .push_back(RuntimesOnOpt)
+            .push_back(RuntimesOnNix)
+            .push_back(RuntimesOnVersionManagers)
     }
 }
 
@@ -232,25 +621,17 @@ impl StrategyLike for Strategy {
     /// multiple strategies, or is yielded multiple times by a single strategy,
     /// it will only be returned the first time it is seen.
     fn runtimes(&self) -> Runtimes<'_> {
+        Box::new(self.runtimes_with_source().map(|(_, runtime)| runtime))
+    }
+
+    /// - For a [`Strategy::Chain`], returns `chain`.
+    /// - For a [`Strategy::Delegated`], calls through to the wrapped strategy.
+    /// - For a [`Strategy::Single`], returns `single`.
+    fn source(&self) -> &str {
         match self {
-            Self::Chain(chain) => {
-                let mut seen = std::collections::HashSet::new();
-                Box::new(
-                    chain
-                        .iter()
-                        .flat_map(|strategy| strategy.runtimes())
-                        .filter(move |runtime| seen.insert(runtime.version)),
-                )
-            }
-            Self::Delegated(strategy) => {
-                let mut seen = std::collections::HashSet::new();
-                Box::new(
-                    strategy
-                        .runtimes()
-                        .filter(move |runtime| seen.insert(runtime.version)),
-                )
-            }
-            Self::Single(runtime) => Box::new(std::iter::once(runtime.clone())),
+            Self::Chain(_) => "chain",
+            Self::Delegated(strategy) => strategy.source(),
+            Self::Single(_) => "single",
         }
     }
 
@@ -278,6 +659,90 @@ impl StrategyLike for Strategy {
             Self::Single(runtime) => Some(runtime.clone()),
         }
     }
+
+    /// - For a [`Strategy::Chain`], describes each strategy in the chain; if
+    ///   any cannot be described, returns [`None`].
+    /// - For a [`Strategy::Delegated`], calls through to the wrapped strategy.
+    /// - For a [`Strategy::Single`], describes the runtime it's holding.
+    fn describe(&self) -> Option<StrategyDescription> {
+        match self {
+            Self::Chain(chain) => chain
+                .iter()
+                .map(Strategy::describe)
+                .collect::<Option<_>>()
+                .map(StrategyDescription::Chain),
+            Self::Delegated(strategy) => strategy.describe(),
+            Self::Single(runtime) => Some(StrategyDescription::Single(runtime.clone())),
+        }
+    }
+}
+
+/// A strategy that skips runtimes without the given binaries.
+///
+/// When wrapping a [`Strategy::Chain`], incomplete runtimes are skipped before
+/// deduplicating by version, so that an incomplete runtime does not hide a
+/// complete one of the same version found later in the chain.
+///
+/// See [`Strategy::requiring`].
+#[derive(Debug)]
+pub struct StrategyRequiring {
+    pub strategy: Strategy,
+    pub binaries: Vec<String>,
+}
+
+impl StrategyLike for StrategyRequiring {
+    fn runtimes(&self) -> Runtimes<'_> {
+        let complete = |runtime: &Runtime| runtime.check_binaries(&self.binaries).is_ok();
+        match &self.strategy {
+            Strategy::Chain(chain) => {
+                let mut seen = std::collections::HashSet::new();
+                Box::new(
+                    chain
+                        .iter()
+                        .flat_map(StrategyLike::runtimes)
+                        .filter(complete)
+                        .filter(move |runtime| seen.insert(runtime.version)),
+                )
+            }
+            strategy => Box::new(strategy.runtimes().filter(complete)),
+        }
+    }
+
+    fn source(&self) -> &str {
+        self.strategy.source()
+    }
+
+    fn select(&self, constraint: &Constraint) -> Option<Runtime> {
+        match self.strategy.select(constraint) {
+            Some(runtime) if runtime.check_binaries(&self.binaries).is_ok() => Some(runtime),
+            // Fall back to considering all runtimes together.
+            _ => self
+                .runtimes()
+                .filter(|runtime| constraint.matches(runtime))
+                .max_by(|ra, rb| ra.version.cmp(&rb.version)),
+        }
+    }
+
+    fn fallback(&self) -> Option<Runtime> {
+        match self.strategy.fallback() {
+            Some(runtime) if runtime.check_binaries(&self.binaries).is_ok() => Some(runtime),
+            _ => self.runtimes().max_by(|ra, rb| ra.version.cmp(&rb.version)),
+        }
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Requiring {
+            strategy: self.strategy.describe()?.into(),
+            binaries: self.binaries.clone(),
+        })
+    }
+}
+
+impl From<StrategyRequiring> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: StrategyRequiring) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
 }
 
 impl From<RuntimesOnPath> for Strategy {
@@ -301,6 +766,48 @@ impl From<RuntimesOnPlatform> for Strategy {
     }
 }
 
+impl From<RuntimesOnRuntimePathEnv> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnRuntimePathEnv) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+impl From<RuntimesOnPgConfig> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnPgConfig) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+impl From<RuntimesOnRedHat> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnRedHat) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+impl From<RuntimesOnOpt> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnOpt) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+impl From<RuntimesOnNix> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnNix) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+impl From<RuntimesOnVersionManagers> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: RuntimesOnVersionManagers) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
 impl From<Runtime> for Strategy {
     /// Converts the given runtime into a [`Strategy::Single`].
     fn from(runtime: Runtime) -> Self {
@@ -312,13 +819,16 @@ impl From<Runtime> for Strategy {
 mod tests {
     use std::env;
 
-    use super::{RuntimesOnPath, RuntimesOnPathEnv, RuntimesOnPlatform, Strategy, StrategyLike};
+    use super::{
+        RuntimesOnPath, RuntimesOnPathEnv, RuntimesOnPlatform, RuntimesOnRuntimePathEnv, Strategy,
+        StrategyLike,
+    };
 
     /// This will fail if there are no PostgreSQL runtimes installed.
     #[test]
     fn runtime_find_custom_path() {
         let path = env::var_os("PATH").expect("PATH not set");
-        let strategy = RuntimesOnPath(path.into());
+        let strategy = RuntimesOnPath::new(path);
         let runtimes = strategy.runtimes();
         assert_ne!(0, runtimes.count());
     }
@@ -338,6 +848,84 @@ mod tests {
         assert_ne!(0, runtimes.count());
     }
 
+    #[test]
+    fn runtime_find_on_runtime_path_accepts_bindir_or_prefix() -> std::io::Result<()> {
+        let temp = tempfile::tempdir()?;
+        let (bindir, prefix, neither) = (
+            temp.path().join("a"),
+            temp.path().join("b"),
+            temp.path().join("c"),
+        );
+        for dir in [&bindir, &prefix.join("bin"), &neither] {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(bindir.join("pg_ctl"), "")?;
+        std::fs::write(prefix.join("bin").join("pg_ctl"), "")?;
+        let path = env::join_paths([&bindir, &prefix, &neither]).expect("invalid path");
+        assert_eq!(
+            RuntimesOnRuntimePathEnv::find(&path),
+            vec![bindir, prefix.join("bin")]
+        );
+        Ok(())
+    }
+
+    /// This will fail if there are no PostgreSQL runtimes installed.
+    #[test]
+    fn runtime_strategy_runtimes_with_source() {
+        let strategy = Strategy::default();
+        let runtimes: Vec<_> = strategy.runtimes().collect();
+        let sourced: Vec<_> = strategy.runtimes_with_source().collect();
+        assert_eq!(
+            runtimes,
+            sourced
+                .iter()
+                .map(|(_, runtime)| runtime.clone())
+                .collect::<Vec<_>>()
+        );
+        assert!(sourced.iter().all(|(source, _)| !source.is_empty()));
+        // A single runtime pushed to the front of the chain is its own source.
+        let runtime = runtimes.first().expect("no runtimes").clone();
+        let strategy = strategy.push_front(runtime);
+        assert_eq!(strategy.runtimes_with_source().next().unwrap().0, "single");
+    }
+
+    #[test]
+    fn runtime_strategy_requiring_skips_incomplete_runtimes() {
+        use crate::runtime::{constraint::Constraint, health::REQUIRED, Runtime};
+        let bindir = tempfile::tempdir().expect("could not create temporary directory");
+        let incomplete = Runtime {
+            bindir: bindir.path().into(),
+            version: crate::version::Version::Post10(16, 4),
+        };
+        let strategy = Strategy::from(incomplete.clone());
+        assert_eq!(strategy.fallback(), Some(incomplete.clone()));
+        let strategy = strategy.requiring(REQUIRED.iter().copied());
+        assert_eq!(strategy.fallback(), None);
+        assert_eq!(strategy.select(&Constraint::Anything), None);
+        assert_eq!(strategy.runtimes().count(), 0);
+    }
+
+    /// This will fail if there are no PostgreSQL runtimes installed.
+    #[test]
+    fn runtime_strategy_requiring_finds_complete_runtime_of_same_version() {
+        use crate::runtime::{constraint::Constraint, health::REQUIRED, Runtime};
+        let complete = Strategy::default().fallback().expect("no runtimes");
+        let bindir = tempfile::tempdir().expect("could not create temporary directory");
+        let incomplete = Runtime { bindir: bindir.path().into(), version: complete.version };
+        let strategy = Strategy::from(incomplete)
+            .push_back(complete.clone())
+            .requiring(REQUIRED.iter().copied());
+        assert_eq!(
+            strategy.runtimes().collect::<Vec<_>>(),
+            vec![complete.clone()]
+        );
+        assert_eq!(
+            strategy.select(&Constraint::Anything),
+            Some(complete.clone())
+        );
+        assert_eq!(strategy.fallback(), Some(complete));
+    }
+
     /// This will fail if there are no PostgreSQL runtimes installed. It's also
     /// somewhat fragile because it relies upon knowing the implementation of
     /// the strategies of which the default [`StrategySet`] is composed.
@@ -0,0 +1,156 @@
+//! Describe strategies so that they can be written out, e.g. to a
+//! configuration file, and read back again.
+
+use std::path::PathBuf;
+
+use super::{
+    RuntimesOnNix, RuntimesOnOpt, RuntimesOnPath, RuntimesOnPathEnv, RuntimesOnPgConfig,
+    RuntimesOnPlatform, RuntimesOnRedHat, RuntimesOnRuntimePathEnv, RuntimesOnVersionManagers,
+    SelectionPolicy, Strategy, StrategyRequiring, StrategyWithPolicy,
+};
+use crate::runtime::Runtime;
+
+/// A description of a strategy from which an equivalent [`Strategy`] can be
+/// recreated. See [`StrategyLike::describe`][`super::StrategyLike::describe`].
+///
+/// With the `serde` feature, this – and [`Strategy`] – can be serialized, e.g.
+/// `{"chain": [{"path": "/opt/pg/bin"}, "platform"]}`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum StrategyDescription {
+    /// See [`Strategy::Chain`].
+    Chain(Vec<StrategyDescription>),
+    /// See [`RuntimesOnPath`].
+    Path(PathBuf),
+    /// See [`RuntimesOnPathEnv`].
+    PathEnv,
+    /// See [`RuntimesOnRuntimePathEnv`].
+    RuntimePathEnv,
+    /// See [`RuntimesOnPgConfig`].
+    PgConfig,
+    /// See [`RuntimesOnPlatform`].
+    Platform,
+    /// See [`RuntimesOnRedHat`].
+    RedHat,
+    /// See [`RuntimesOnOpt`].
+    Opt,
+    /// See [`RuntimesOnNix`].
+    Nix,
+    /// See [`RuntimesOnVersionManagers`].
+    VersionManagers,
+    /// See [`Strategy::Single`].
+    Single(Runtime),
+    /// See [`StrategyRequiring`].
+    Requiring { strategy: Box<StrategyDescription>, binaries: Vec<String> },
+    /// See [`StrategyWithPolicy`].
+    Policy { strategy: Box<StrategyDescription>, policy: SelectionPolicy },
+}
+
+impl From<StrategyDescription> for Strategy {
+    fn from(description: StrategyDescription) -> Self {
+        use StrategyDescription::*;
+        match description {
+            Chain(chain) => Self::Chain(chain.into_iter().map(Into::into).collect()),
+            Path(path) => RuntimesOnPath::new(path).into(),
+            PathEnv => RuntimesOnPathEnv.into(),
+            RuntimePathEnv => RuntimesOnRuntimePathEnv.into(),
+            PgConfig => RuntimesOnPgConfig.into(),
+            Platform => RuntimesOnPlatform.into(),
+            RedHat => RuntimesOnRedHat.into(),
+            Opt => RuntimesOnOpt.into(),
+            Nix => RuntimesOnNix.into(),
+            VersionManagers => RuntimesOnVersionManagers.into(),
+            Single(runtime) => Self::Single(runtime),
+            Requiring { strategy, binaries } => {
+                StrategyRequiring { strategy: (*strategy).into(), binaries }.into()
+            }
+            Policy { strategy, policy } => {
+                StrategyWithPolicy { strategy: (*strategy).into(), policy }.into()
+            }
+        }
+    }
+}
+
+/// Serializes the strategy's [description][`StrategyDescription`]. This fails
+/// if the strategy, or any strategy it contains, cannot be described.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Strategy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use super::StrategyLike;
+        use serde::ser::Error;
+        self.describe()
+            .ok_or_else(|| S::Error::custom(format!("strategy cannot be described: {self:?}")))?
+            .serialize(serializer)
+    }
+}
+
+/// Deserializes a [`StrategyDescription`] and converts it into a strategy.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Strategy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        StrategyDescription::deserialize(deserializer).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde")]
+mod tests {
+    use super::super::{Runtimes, RuntimesOnPath, RuntimesOnPlatform, Strategy, StrategyLike};
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn strategy_round_trip() -> Result<(), serde_json::Error> {
+        let runtime = Runtime {
+            bindir: "/opt/pg/16/bin".into(),
+            version: "16.4".parse().unwrap(),
+        };
+        let strategy = Strategy::Single(runtime.clone())
+            .push_back(RuntimesOnPath::new("/opt/pg/17/bin:/opt/pg/15/bin"))
+            .push_back(RuntimesOnPlatform)
+            .requiring(["psql"]);
+        let json = serde_json::to_value(&strategy)?;
+        assert_eq!(
+            json,
+            serde_json::json!({"requiring": {
+                "strategy": {"chain": [
+                    {"single": {"bindir": "/opt/pg/16/bin", "version": "16.4"}},
+                    {"path": "/opt/pg/17/bin:/opt/pg/15/bin"},
+                    "platform",
+                ]},
+                "binaries": ["psql"],
+            }}),
+        );
+        let strategy: Strategy = serde_json::from_value(json.clone())?;
+        assert_eq!(serde_json::to_value(&strategy)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn strategy_with_policy_round_trip() -> Result<(), serde_json::Error> {
+        let json = serde_json::json!({"policy": {
+            "strategy": "path-env",
+            "policy": {"prefer": ["/usr/lib/**"], "exclude": "<14", "versions": "exact"},
+        }});
+        let strategy: Strategy = serde_json::from_value(json.clone())?;
+        assert_eq!(serde_json::to_value(&strategy)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn strategy_that_cannot_be_described() {
+        #[derive(Debug)]
+        struct Custom;
+        impl StrategyLike for Custom {
+            fn runtimes(&self) -> Runtimes<'_> {
+                Box::new(std::iter::empty())
+            }
+        }
+        let strategy = Strategy::Delegated(Box::new(Custom)).push_back(RuntimesOnPlatform);
+        assert!(strategy.describe().is_none());
+        assert!(serde_json::to_value(&strategy).is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! Policies for choosing between runtimes.
+//!
+//! By default, [`StrategyLike::select`] chooses the runtime with the highest
+//! version that matches a constraint, and [`StrategyLike::fallback`] the
+//! runtime with the highest version overall. A [`SelectionPolicy`] can refine
+//! this, e.g. to prefer distribution packages over custom builds, to exclude
+//! certain runtimes altogether, or to insist on exact version matches.
+
+use std::cmp::{Ordering, Reverse};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, io, str::FromStr};
+
+use serde::Deserialize;
+
+use super::{Runtimes, Strategy, StrategyDescription, StrategyLike};
+use crate::runtime::{
+    constraint::{Comparison, Constraint, ConstraintError},
+    Runtime,
+};
+use crate::version::PartialVersion;
+
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum PolicyError {
+    #[error("Could not read runtime selection policy from {0}")]
+    IoError(PathBuf, #[source] io::Error),
+    #[error("Could not parse runtime selection policy from {0}")]
+    ParseError(PathBuf, #[source] serde_json::Error),
+    #[error("Invalid constraint in {0}")]
+    ConstraintError(
+        String,
+        #[source]
+        #[diagnostic_source]
+        ConstraintError,
+    ),
+    #[error("Invalid version policy {1:?} in {0}; expected one of: newest, newest-minor, exact")]
+    VersionPolicyError(String, String),
+}
+
+/// How versions are treated when choosing a runtime.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum VersionPolicy {
+    /// Choose the most preferred runtime, then the newest of those.
+    #[default]
+    Newest,
+    /// Choose the major version from the most preferred runtime, then the
+    /// newest minor release of that major version, even if it's from a less
+    /// preferred runtime.
+    NewestMinor,
+    /// Like [`Newest`][`Self::Newest`], but version constraints must match
+    /// exactly, e.g. `16.2` matches 16.2 only, rather than 16.2 or later.
+    Exact,
+}
+
+impl FromStr for VersionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "newest" => Ok(Self::Newest),
+            "newest-minor" => Ok(Self::NewestMinor),
+            "exact" => Ok(Self::Exact),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for VersionPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Self::Newest => "newest",
+            Self::NewestMinor => "newest-minor",
+            Self::Exact => "exact",
+        })
+    }
+}
+
+/// A policy for choosing between runtimes.
+///
+/// The default policy is equivalent to the default behaviour of
+/// [`StrategyLike`], i.e. choose the runtime with the highest version.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct SelectionPolicy {
+    /// Constraints in order of preference. A runtime matching an earlier
+    /// constraint is preferred over one matching a later constraint, which in
+    /// turn is preferred over one matching none.
+    pub prefer: Vec<Constraint>,
+    /// Runtimes matching this constraint are never chosen.
+    pub exclude: Constraint,
+    /// How versions are treated.
+    pub versions: VersionPolicy,
+}
+
+impl Default for SelectionPolicy {
+    fn default() -> Self {
+        Self {
+            prefer: Vec::new(),
+            exclude: Constraint::Nothing,
+            versions: VersionPolicy::default(),
+        }
+    }
+}
+
+/// The on-disk representation of a [`SelectionPolicy`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    prefer: Vec<String>,
+    exclude: Option<String>,
+    versions: Option<String>,
+}
+
+impl SelectionPolicy {
+    /// Environment variable naming a policy file. When not set, the policy
+    /// file is `pgdo/runtime-policy.json` in `$XDG_CONFIG_HOME` (or
+    /// `~/.config`), if it exists.
+    pub const VAR_FILE: &'static str = "PGDO_RUNTIME_POLICY";
+    /// Environment variable containing preferred constraints, separated by
+    /// `;`. Overrides `prefer` from the policy file.
+    pub const VAR_PREFER: &'static str = "PGDO_RUNTIME_PREFER";
+    /// Environment variable containing a constraint; matching runtimes are
+    /// excluded. Overrides `exclude` from the policy file.
+    pub const VAR_EXCLUDE: &'static str = "PGDO_RUNTIME_EXCLUDE";
+    /// Environment variable containing the version policy, i.e. `newest`,
+    /// `newest-minor`, or `exact`. Overrides `versions` from the policy file.
+    pub const VAR_VERSIONS: &'static str = "PGDO_RUNTIME_VERSIONS";
+
+    /// Load a policy from the given JSON file, e.g.
+    ///
+    /// ```json
+    /// {
+    ///   "prefer": ["/usr/lib/postgresql/**", "/usr/pgsql-*/**"],
+    ///   "exclude": "/opt/**",
+    ///   "versions": "newest-minor"
+    /// }
+    /// ```
+    ///
+    /// All fields are optional.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PolicyError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|err| PolicyError::IoError(path.to_owned(), err))?;
+        let file: PolicyFile = serde_json::from_slice(&data)
+            .map_err(|err| PolicyError::ParseError(path.to_owned(), err))?;
+        let name = path.display().to_string();
+        Ok(Self {
+            prefer: file
+                .prefer
+                .iter()
+                .map(|constraint| parse(&name, constraint))
+                .collect::<Result<_, _>>()?,
+            exclude: match file.exclude {
+                Some(exclude) => parse(&name, &exclude)?,
+                None => Constraint::Nothing,
+            },
+            versions: match file.versions {
+                Some(versions) => versions
+                    .parse()
+                    .map_err(|versions| PolicyError::VersionPolicyError(name, versions))?,
+                None => VersionPolicy::default(),
+            },
+        })
+    }
+
+    /// Load a policy from the environment, if one is configured.
+    ///
+    /// The policy file is read first, if there is one, then the environment
+    /// variables [`VAR_PREFER`][`Self::VAR_PREFER`],
+    /// [`VAR_EXCLUDE`][`Self::VAR_EXCLUDE`], and
+    /// [`VAR_VERSIONS`][`Self::VAR_VERSIONS`] override its settings. Returns
+    /// [`None`] when there's no policy file and none of these are set.
+    pub fn from_env() -> Result<Option<Self>, PolicyError> {
+        let file = match env::var_os(Self::VAR_FILE) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+                .map(|dir| dir.join("pgdo").join("runtime-policy.json"))
+                .filter(|path| path.is_file()),
+        };
+        let mut configured = file.is_some();
+        let mut policy = match file {
+            Some(file) => Self::from_file(file)?,
+            None => Self::default(),
+        };
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+        if let Some(prefer) = var(Self::VAR_PREFER) {
+            policy.prefer = prefer
+                .split(';')
+                .map(|constraint| parse(Self::VAR_PREFER, constraint))
+                .collect::<Result<_, _>>()?;
+            configured = true;
+        }
+        if let Some(exclude) = var(Self::VAR_EXCLUDE) {
+            policy.exclude = parse(Self::VAR_EXCLUDE, &exclude)?;
+            configured = true;
+        }
+        if let Some(versions) = var(Self::VAR_VERSIONS) {
+            policy.versions = versions.parse().map_err(|versions| {
+                PolicyError::VersionPolicyError(Self::VAR_VERSIONS.into(), versions)
+            })?;
+            configured = true;
+        }
+        Ok(configured.then_some(policy))
+    }
+
+    /// Choose the best runtime matching the given constraint.
+    pub fn select<I>(&self, runtimes: I, constraint: &Constraint) -> Option<Runtime>
+    where
+        I: IntoIterator<Item = Runtime>,
+    {
+        let constraint = match self.versions {
+            VersionPolicy::Exact => exactly(constraint),
+            VersionPolicy::Newest | VersionPolicy::NewestMinor => constraint.clone(),
+        };
+        self.choose(
+            runtimes
+                .into_iter()
+                .filter(|runtime| constraint.matches(runtime)),
+        )
+    }
+
+    /// Choose the best runtime when there are no constraints.
+    pub fn fallback<I>(&self, runtimes: I) -> Option<Runtime>
+    where
+        I: IntoIterator<Item = Runtime>,
+    {
+        self.choose(runtimes.into_iter())
+    }
+
+    /// The rank of the given runtime; lower is more preferred.
+    fn rank(&self, runtime: &Runtime) -> usize {
+        self.prefer
+            .iter()
+            .position(|constraint| constraint.matches(runtime))
+            .unwrap_or(self.prefer.len())
+    }
+
+    /// Choose from the given runtimes. When runtimes are otherwise equal, the
+    /// first is chosen.
+    fn choose(&self, runtimes: impl Iterator<Item = Runtime>) -> Option<Runtime> {
+        let candidates: Vec<_> = runtimes
+            .filter(|runtime| !self.exclude.matches(runtime))
+            .collect();
+        let best = candidates
+            .iter()
+            .min_by_key(|runtime| (self.rank(runtime), Reverse(runtime.version)))?;
+        match self.versions {
+            VersionPolicy::Newest | VersionPolicy::Exact => Some(best.clone()),
+            VersionPolicy::NewestMinor => {
+                let major = PartialVersion::from(best.version).widened();
+                candidates
+                    .iter()
+                    .filter(|runtime| major.compare(runtime.version) == Ordering::Equal)
+                    .min_by_key(|runtime| (Reverse(runtime.version), self.rank(runtime)))
+                    .cloned()
+            }
+        }
+    }
+}
+
+/// Parse a constraint from the given source, e.g. an environment variable.
+fn parse(name: &str, constraint: &str) -> Result<Constraint, PolicyError> {
+    constraint
+        .parse()
+        .map_err(|err| PolicyError::ConstraintError(name.into(), err))
+}
+
+/// Rewrite version constraints to match exactly.
+fn exactly(constraint: &Constraint) -> Constraint {
+    match constraint {
+        Constraint::Version(version) => Constraint::Compare(Comparison::Eq, *version),
+        Constraint::Either(ca, cb) => Constraint::Either(exactly(ca).into(), exactly(cb).into()),
+        Constraint::Both(ca, cb) => Constraint::Both(exactly(ca).into(), exactly(cb).into()),
+        Constraint::Not(constraint) => Constraint::Not(exactly(constraint).into()),
+        constraint => constraint.clone(),
+    }
+}
+
+/// A strategy that chooses runtimes according to a [`SelectionPolicy`].
+///
+/// Runtimes from all of the wrapped strategy's sources are considered
+/// together, rather than asking each strategy in a chain in turn.
+#[derive(Debug)]
+pub struct StrategyWithPolicy {
+    pub strategy: Strategy,
+    pub policy: SelectionPolicy,
+}
+
+impl StrategyLike for StrategyWithPolicy {
+    fn runtimes(&self) -> Runtimes<'_> {
+        self.strategy.runtimes()
+    }
+
+    fn source(&self) -> &str {
+        self.strategy.source()
+    }
+
+    fn select(&self, constraint: &Constraint) -> Option<Runtime> {
+        self.policy.select(self.strategy.runtimes(), constraint)
+    }
+
+    fn fallback(&self) -> Option<Runtime> {
+        self.policy.fallback(self.strategy.runtimes())
+    }
+
+    fn describe(&self) -> Option<StrategyDescription> {
+        Some(StrategyDescription::Policy {
+            strategy: self.strategy.describe()?.into(),
+            policy: self.policy.clone(),
+        })
+    }
+}
+
+impl From<StrategyWithPolicy> for Strategy {
+    /// Converts the given strategy into a [`Strategy::Delegated`].
+    fn from(strategy: StrategyWithPolicy) -> Self {
+        Self::Delegated(Box::new(strategy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Constraint, Runtime, SelectionPolicy, VersionPolicy};
+
+    fn runtimes() -> Vec<Runtime> {
+        [
+            ("/opt/pg/17/bin", "17.0"),
+            ("/opt/pg/16/bin", "16.4"),
+            ("/usr/lib/postgresql/16/bin", "16.2"),
+            ("/usr/lib/postgresql/15/bin", "15.8"),
+        ]
+        .into_iter()
+        .map(|(bindir, version)| Runtime {
+            bindir: bindir.into(),
+            version: version.parse().unwrap(),
+        })
+        .collect()
+    }
+
+    fn policy(prefer: &[&str], exclude: &str, versions: VersionPolicy) -> SelectionPolicy {
+        SelectionPolicy {
+            prefer: prefer.iter().map(|c| c.parse().unwrap()).collect(),
+            exclude: exclude.parse().unwrap(),
+            versions,
+        }
+    }
+
+    fn select(policy: &SelectionPolicy, constraint: &str) -> Option<String> {
+        let constraint: Constraint = constraint.parse().unwrap();
+        policy
+            .select(runtimes(), &constraint)
+            .map(|runtime| runtime.version.to_string())
+    }
+
+    fn fallback(policy: &SelectionPolicy) -> Option<String> {
+        policy
+            .fallback(runtimes())
+            .map(|runtime| runtime.version.to_string())
+    }
+
+    #[test]
+    fn default_policy_chooses_newest() {
+        let policy = SelectionPolicy::default();
+        assert_eq!(fallback(&policy).as_deref(), Some("17.0"));
+        assert_eq!(select(&policy, "16").as_deref(), Some("16.4"));
+        assert_eq!(select(&policy, "14"), None);
+    }
+
+    #[test]
+    fn preferences_are_ordered() {
+        let usr_first = policy(&["/usr/lib/**"], "nothing", VersionPolicy::Newest);
+        assert_eq!(fallback(&usr_first).as_deref(), Some("16.2"));
+        assert_eq!(select(&usr_first, "16").as_deref(), Some("16.2"));
+        assert_eq!(select(&usr_first, "17").as_deref(), Some("17.0"));
+        let pg15_first = policy(&["15", "/opt/**"], "nothing", VersionPolicy::Newest);
+        assert_eq!(fallback(&pg15_first).as_deref(), Some("15.8"));
+        assert_eq!(select(&pg15_first, "16").as_deref(), Some("16.4"));
+    }
+
+    #[test]
+    fn exclusions_are_never_chosen() {
+        let policy = policy(&[], "/opt/** or 15", VersionPolicy::Newest);
+        assert_eq!(fallback(&policy).as_deref(), Some("16.2"));
+        assert_eq!(select(&policy, "15"), None);
+    }
+
+    #[test]
+    fn newest_minor_within_preferred_major() {
+        let policy = policy(&["/usr/lib/**"], "nothing", VersionPolicy::NewestMinor);
+        assert_eq!(fallback(&policy).as_deref(), Some("16.4"));
+        assert_eq!(select(&policy, "16").as_deref(), Some("16.4"));
+        assert_eq!(select(&policy, "15").as_deref(), Some("15.8"));
+    }
+
+    #[test]
+    fn exact_versions_only() {
+        let policy = policy(&[], "nothing", VersionPolicy::Exact);
+        assert_eq!(select(&policy, "16.2").as_deref(), Some("16.2"));
+        assert_eq!(select(&policy, "16.3"), None);
+        assert_eq!(select(&policy, "16").as_deref(), Some("16.4"));
+        // Without the exact policy, 16.3 means 16.3 or later.
+        assert_eq!(
+            select(&SelectionPolicy::default(), "16.3").as_deref(),
+            Some("16.4")
+        );
+    }
+
+    #[test]
+    fn policy_from_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("policy.json");
+        std::fs::write(
+            &path,
+            r#"{"prefer": ["/usr/lib/**"], "exclude": ">=17", "versions": "newest-minor"}"#,
+        )?;
+        let policy = SelectionPolicy::from_file(&path)?;
+        assert_eq!(policy.prefer.len(), 1);
+        assert_eq!(policy.exclude.to_string(), ">=17");
+        assert_eq!(policy.versions, VersionPolicy::NewestMinor);
+        std::fs::write(&path, r#"{"exclude": ">=17 and"}"#)?;
+        assert!(SelectionPolicy::from_file(&path).is_err());
+        std::fs::write(&path, r#"{"versions": "oldest"}"#)?;
+        assert!(SelectionPolicy::from_file(&path).is_err());
+        Ok(())
+    }
+}
@@ -85,6 +85,64 @@ pub fn percent(numerator: u64, denominator: u64) -> Option<u64> {
     })
 }
 
+/// Serialize and deserialize values using their [`Display`][std::fmt::Display]
+/// and [`FromStr`][std::str::FromStr] implementations. Use with `#[serde(with =
+/// "crate::util::serde_display")]`, or from hand-written `Serialize` and
+/// `Deserialize` implementations.
+pub(crate) mod serde_display {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Implement [`Serialize`][serde::Serialize] and
+/// [`Deserialize`][serde::Deserialize] for the given types using
+/// [`serde_display`], i.e. as strings.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_display {
+    ($($type:ty),+ $(,)?) => {
+        $(
+            impl serde::Serialize for $type {
+                fn serialize<S: serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    $crate::util::serde_display::serialize(self, serializer)
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $type {
+                fn deserialize<D: serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    $crate::util::serde_display::deserialize(deserializer)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "serde")]
+pub(crate) use impl_serde_display;
+
 #[cfg(test)]
 mod tests {
     use std::env;
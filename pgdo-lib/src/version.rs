@@ -14,7 +14,9 @@
 mod current;
 mod error;
 mod partial;
+mod prerelease;
 
 pub use current::Version;
 pub use error::VersionError;
 pub use partial::PartialVersion;
+pub use prerelease::{PartialPreRelease, PreRelease};
@@ -11,26 +11,25 @@
 //!
 //! [versioning]: https://www.postgresql.org/support/versioning/
 
-// TODO: Parse `server_version_num`/`PG_VERSION_NUM`, e.g. 120007 for version
-// 12.7, 90624 for 9.6.24. See https://pgpedia.info/s/server_version_num.html
-// and https://www.postgresql.org/docs/16/runtime-config-preset.html.
-
+use std::cmp::Ordering;
 use std::fmt;
 use std::str::FromStr;
 use std::sync::LazyLock;
 
 use regex::Regex;
 
-use super::VersionError;
+use super::{PreRelease, VersionError};
 
 /// Represents a full PostgreSQL version. This is the kind of thing we see when
 /// running `pg_ctl --version` for example.
 ///
 /// The "Current minor" column shown on the [PostgreSQL "Versioning Policy"
-/// page][versioning] is what this models.
+/// page][versioning] is what this models, as well as pre-releases, e.g.
+/// `17beta2`, and development builds, e.g. `18devel`. A pre-release sorts
+/// before all releases of the same major version.
 ///
 /// [versioning]: https://www.postgresql.org/support/versioning/
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Version {
     /// Pre-PostgreSQL 10, with major, point, and minor version numbers, e.g.
     /// 9.6.17. It is an error to create this variant with a major number >= 10.
@@ -38,6 +37,72 @@ pub enum Version {
     /// PostgreSQL 10+, with major and minor version number, e.g. 10.3. It is an
     /// error to create this variant with a major number < 10.
     Post10(u32, u32),
+    /// A pre-release of PostgreSQL before 10, with major and point version
+    /// numbers, e.g. 9.6beta1. It is an error to create this variant with a
+    /// major number >= 10.
+    Pre10Dev(u32, u32, PreRelease),
+    /// A pre-release of PostgreSQL 10+, with major version number, e.g.
+    /// 17beta2 or 18devel. It is an error to create this variant with a major
+    /// number < 10.
+    Post10Dev(u32, PreRelease),
+}
+
+impl Version {
+    /// Convert a numeric version, as found in `server_version_num` or
+    /// `PG_VERSION_NUM`, into a [`Version`], e.g. 120007 is 12.7 and 90624 is
+    /// 9.6.24. See <https://pgpedia.info/s/server_version_num.html>.
+    ///
+    /// ```rust
+    /// # use pgdo::version::Version;
+    /// assert_eq!(Version::from_num(120007), Ok(Version::Post10(12, 7)));
+    /// assert_eq!(Version::from_num(90624), Ok(Version::Pre10(9, 6, 24)));
+    /// ```
+    pub fn from_num(num: u32) -> Result<Self, VersionError> {
+        match num {
+            100_000.. => Ok(Self::Post10(num / 10000, num % 10000)),
+            10_000.. => Ok(Self::Pre10(num / 10000, num / 100 % 100, num % 100)),
+            _ => Err(VersionError::BadlyFormed { text: Some(num.to_string()) }),
+        }
+    }
+
+    /// Convert this [`Version`] into its numeric form, the inverse of
+    /// [`from_num`][`Self::from_num`].
+    ///
+    /// Pre-releases have the same number as the first release of the same
+    /// major version, e.g. 17beta2 is 170000, as they do in PostgreSQL itself.
+    pub fn to_num(&self) -> u32 {
+        match *self {
+            Self::Pre10(a, b, c) => a * 10000 + b * 100 + c,
+            Self::Post10(a, b) => a * 10000 + b,
+            Self::Pre10Dev(a, b, _) => a * 10000 + b * 100,
+            Self::Post10Dev(a, _) => a * 10000,
+        }
+    }
+
+    /// A key for ordering: major, point, pre-release stage, then minor or
+    /// pre-release number. See [`PartialVersion::sort_key`][sk].
+    ///
+    /// [sk]: super::PartialVersion::sort_key
+    pub(super) fn key(&self) -> [u32; 4] {
+        match *self {
+            Self::Pre10(a, b, c) => [a, b, PreRelease::RELEASED, c],
+            Self::Post10(a, b) => [a, 0, PreRelease::RELEASED, b],
+            Self::Pre10Dev(a, b, pre) => [a, b, pre.key().0, pre.key().1],
+            Self::Post10Dev(a, pre) => [a, 0, pre.key().0, pre.key().1],
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl fmt::Display for Version {
@@ -45,6 +110,8 @@ impl fmt::Display for Version {
         match self {
             Version::Pre10(a, b, c) => fmt.pad(&format!("{a}.{b}.{c}")),
             Version::Post10(a, b) => fmt.pad(&format!("{a}.{b}")),
+            Version::Pre10Dev(a, b, pre) => fmt.pad(&format!("{a}.{b}{pre}")),
+            Version::Post10Dev(a, pre) => fmt.pad(&format!("{a}{pre}")),
         }
     }
 }
@@ -54,30 +121,40 @@ impl FromStr for Version {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         static RE: LazyLock<Regex> = LazyLock::new(|| {
-            Regex::new(r"(?x) \b (\d+) [.] (\d+) (?: [.] (\d+) )? \b")
-                .expect("invalid regex (for matching PostgreSQL versions)")
+            Regex::new(
+                r"(?x) \b (\d+) (?: [.] (\d+) (?: [.] (\d+) )? )? ( devel | (?:alpha|beta|rc)\d* )? \b",
+            )
+            .expect("invalid regex (for matching PostgreSQL versions)")
         });
-        let badly_formed = |_| VersionError::BadlyFormed { text: Some(s.into()) };
-        match RE.captures(s) {
-            Some(caps) => {
-                let a = caps[1].parse::<u32>().map_err(badly_formed)?;
-                let b = caps[2].parse::<u32>().map_err(badly_formed)?;
-                match caps.get(3) {
-                    None if a >= 10 => Ok(Version::Post10(a, b)),
-                    None => Err(VersionError::BadlyFormed { text: Some(s.into()) }),
-                    Some(_) if a >= 10 => Err(VersionError::BadlyFormed { text: Some(s.into()) }),
-                    Some(m) => Ok(m
-                        .as_str()
-                        .parse::<u32>()
-                        .map(|c| Version::Pre10(a, b, c))
-                        .map_err(badly_formed)?),
-                }
-            }
-            None => Err(VersionError::NotFound { text: Some(s.into()) }),
+        let badly_formed = || VersionError::BadlyFormed { text: Some(s.into()) };
+        let number = |m: regex::Match| m.as_str().parse::<u32>().map_err(|_| badly_formed());
+        // A version has at least two parts, or is a pre-release; skip any other
+        // numbers, e.g. "(PostgreSQL) 1 2.3" is 2.3.
+        let caps = RE
+            .captures_iter(s)
+            .find(|caps| caps.get(2).is_some() || caps.get(4).is_some())
+            .ok_or_else(|| VersionError::NotFound { text: Some(s.into()) })?;
+        let a = number(caps.get(1).ok_or_else(badly_formed)?)?;
+        let b = caps.get(2).map(number).transpose()?;
+        let c = caps.get(3).map(number).transpose()?;
+        let pre = match caps.get(4) {
+            Some(pre) => Some(PreRelease::parse(pre.as_str()).ok_or_else(badly_formed)?),
+            None => None,
+        };
+        match (a, b, c, pre) {
+            (a, Some(b), None, None) if a >= 10 => Ok(Version::Post10(a, b)),
+            (a, Some(b), Some(c), None) if a < 10 => Ok(Version::Pre10(a, b, c)),
+            (a, None, None, Some(pre)) if a >= 10 => Ok(Version::Post10Dev(a, pre)),
+            (a, Some(b), None, Some(pre)) if a < 10 => Ok(Version::Pre10Dev(a, b, pre)),
+            _ => Err(badly_formed()),
         }
     }
 }
 
+// Serialized as a string, e.g. `"16.4"`.
+#[cfg(feature = "serde")]
+crate::util::impl_serde_display!(Version);
+
 #[cfg(test)]
 mod tests {
     use super::Version::{Post10, Pre10};
@@ -109,6 +186,46 @@ mod tests {
         assert!(matches!("foo".parse::<Version>(), Err(NotFound { .. })));
     }
 
+    #[test]
+    fn parses_pre_release_versions() {
+        use super::super::PreRelease::*;
+        assert_eq!(Ok(Version::Post10Dev(17, Beta(2))), "17beta2".parse());
+        assert_eq!(Ok(Version::Post10Dev(18, Rc(1))), "18rc1".parse());
+        assert_eq!(
+            Ok(Version::Post10Dev(18, Devel)),
+            "pg_ctl (PostgreSQL) 18devel".parse()
+        );
+        assert_eq!(Ok(Version::Pre10Dev(9, 6, Beta(1))), "9.6beta1".parse());
+        assert!(matches!(
+            "17beta".parse::<Version>(),
+            Err(BadlyFormed { .. })
+        ));
+        assert!(matches!(
+            "17.1beta1".parse::<Version>(),
+            Err(BadlyFormed { .. })
+        ));
+        assert!(matches!("17".parse::<Version>(), Err(NotFound { .. })));
+    }
+
+    #[test]
+    fn displays_pre_release_versions() {
+        for version in ["17beta2", "18rc1", "18devel", "9.6beta1", "9.5alpha2"] {
+            assert_eq!(version, version.parse::<Version>().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn converts_to_and_from_num() {
+        assert_eq!(Version::from_num(120_007), Ok(Post10(12, 7)));
+        assert_eq!(Version::from_num(90624), Ok(Pre10(9, 6, 24)));
+        assert_eq!(Version::from_num(100_000), Ok(Post10(10, 0)));
+        assert!(matches!(Version::from_num(9999), Err(BadlyFormed { .. })));
+        assert_eq!(Post10(12, 7).to_num(), 120_007);
+        assert_eq!(Pre10(9, 6, 24).to_num(), 90624);
+        assert_eq!("17beta2".parse::<Version>().unwrap().to_num(), 170_000);
+        assert_eq!("9.6beta1".parse::<Version>().unwrap().to_num(), 90600);
+    }
+
     #[test]
     fn displays_version_below_10() {
         assert_eq!("9.6.17", format!("{}", Pre10(9, 6, 17)));
@@ -149,4 +266,31 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn pre_releases_sort_before_releases() {
+        let mut versions: Vec<Version> = ["18.0", "17.2", "18rc1", "18beta2", "18devel", "18beta1"]
+            .iter()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        versions.sort();
+        let versions: Vec<_> = versions.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            versions,
+            ["17.2", "18devel", "18beta1", "18beta2", "18rc1", "18.0"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        for version in ["9.6.24", "16.4", "18beta2"] {
+            let version: Version = version.parse().unwrap();
+            let json = serde_json::to_string(&version).unwrap();
+            assert_eq!(json, format!("\"{version}\""));
+            assert_eq!(serde_json::from_str::<Version>(&json).unwrap(), version);
+        }
+        assert!(serde_json::from_str::<Version>("\"foo\"").is_err());
+        assert!(serde_json::from_str::<Version>("16").is_err());
+    }
 }
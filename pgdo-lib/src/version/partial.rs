@@ -4,14 +4,15 @@ use std::{cmp::Ordering, sync::LazyLock};
 
 use regex::Regex;
 
-use super::{Version, VersionError};
+use super::{PartialPreRelease, Version, VersionError};
 
 /// Represents a PostgreSQL version with some parts missing. This is the kind of
 /// thing we might find in a cluster's `PG_VERSION` file.
 ///
 /// The "Version" column on the [PostgreSQL "Versioning Policy"
 /// page][versioning] is roughly what this models, but this can also optionally
-/// represent the "Current minor" column too.
+/// represent the "Current minor" column too, as well as pre-releases, e.g.
+/// `18beta` or `17rc1`.
 ///
 /// [versioning]: https://www.postgresql.org/support/versioning/
 #[derive(Copy, Clone, Debug)]
@@ -32,14 +33,23 @@ pub enum PartialVersion {
     /// error to create this variant with a major number < 10; see
     /// [`checked`][`Self::checked`] for a way to guard against this.
     Post10mm(u32, u32),
+    /// A pre-release of PostgreSQL before 10, with major and point version
+    /// numbers, e.g. 9.6beta or 9.6beta1. It is an error to create this variant
+    /// with a major number >= 10; see [`checked`][`Self::checked`] for a way to
+    /// guard against this.
+    Pre10mDev(u32, u32, PartialPreRelease),
+    /// A pre-release of PostgreSQL 10+, with major version number, e.g. 18beta
+    /// or 17rc1. It is an error to create this variant with a major number <
+    /// 10; see [`checked`][`Self::checked`] for a way to guard against this.
+    Post10mDev(u32, PartialPreRelease),
 }
 
 /// Convert a [`PartialVersion`] into a [`Version`] that's useful for
 /// comparisons.
 ///
 /// The [`Version`] returned has 0 (zero) in the place of the missing parts. For
-/// example, a partial version of `9.6.*` becomes `9.6.0`, and `12.*` becomes
-/// `12.0`.
+/// example, a partial version of `9.6.*` becomes `9.6.0`, `12.*` becomes
+/// `12.0`, and `18beta` becomes `18beta0`.
 impl From<&PartialVersion> for Version {
     fn from(partial: &PartialVersion) -> Self {
         use PartialVersion::*;
@@ -48,6 +58,8 @@ impl From<&PartialVersion> for Version {
             Pre10mm(a, b, c) => Version::Pre10(a, b, c),
             Post10m(a) => Version::Post10(a, 0),
             Post10mm(a, b) => Version::Post10(a, b),
+            Pre10mDev(a, b, pre) => Version::Pre10Dev(a, b, pre.into()),
+            Post10mDev(a, pre) => Version::Post10Dev(a, pre.into()),
         }
     }
 }
@@ -66,6 +78,8 @@ impl From<&Version> for PartialVersion {
         match *version {
             Pre10(a, b, c) => PartialVersion::Pre10mm(a, b, c),
             Post10(a, b) => PartialVersion::Post10mm(a, b),
+            Pre10Dev(a, b, pre) => PartialVersion::Pre10mDev(a, b, pre.into()),
+            Post10Dev(a, pre) => PartialVersion::Post10mDev(a, pre.into()),
         }
     }
 }
@@ -81,15 +95,16 @@ impl PartialVersion {
     /// Return self if it is a valid [`PartialVersion`].
     ///
     /// This can be necessary when a [`PartialVersion`] has been constructed
-    /// directly. It checks that [`PartialVersion::Pre10m`] and
-    /// [`PartialVersion::Pre10mm`] have a major version number less than 10,
-    /// and that [`PartialVersion::Post10m`] and [`PartialVersion::Post10mm`]
-    /// have a major version number greater than or equal to 10.
+    /// directly. It checks that [`PartialVersion::Pre10m`],
+    /// [`PartialVersion::Pre10mm`], and [`PartialVersion::Pre10mDev`] have a
+    /// major version number less than 10, and that [`PartialVersion::Post10m`],
+    /// [`PartialVersion::Post10mm`], and [`PartialVersion::Post10mDev`] have a
+    /// major version number greater than or equal to 10.
     pub fn checked(self) -> Result<Self, VersionError> {
         use PartialVersion::*;
         match self {
-            Pre10m(a, ..) | Pre10mm(a, ..) if a < 10 => Ok(self),
-            Post10m(a) | Post10mm(a, ..) if a >= 10 => Ok(self),
+            Pre10m(a, ..) | Pre10mm(a, ..) | Pre10mDev(a, ..) if a < 10 => Ok(self),
+            Post10m(a) | Post10mm(a, ..) | Post10mDev(a, ..) if a >= 10 => Ok(self),
             _ => Err(VersionError::BadlyFormed { text: Some(self.to_string()) }),
         }
     }
@@ -115,25 +130,45 @@ impl PartialVersion {
     /// must be greater than or equal to this `PartialVersion`'s minor number.
     /// When this `PartialVersion` has no minor number, the given version is
     /// assumed to be compatible.
+    ///
+    /// Pre-releases are compatible when this `PartialVersion` has only a major
+    /// number (and point number before 10), or when this is a pre-release of
+    /// the same major version and stage, e.g. 18beta2 is compatible with
+    /// `18beta` and `18beta2`, but not with `18beta1` or `18.0`.
     #[allow(dead_code)]
     pub fn compatible(&self, version: Version) -> bool {
         use PartialVersion::*;
         match (*self, version) {
-            (Pre10m(a, b), Version::Pre10(x, y, _)) => a == x && b == y,
+            (Pre10m(a, b), Version::Pre10(x, y, _) | Version::Pre10Dev(x, y, _)) => {
+                a == x && b == y
+            }
             (Pre10mm(a, b, c), Version::Pre10(x, y, z)) => a == x && b == y && c <= z,
-            (Post10m(a), Version::Post10(x, _)) => a == x,
+            (Pre10mDev(a, b, p), Version::Pre10Dev(x, y, q)) => a == x && b == y && p.matches(q),
+            (Post10m(a), Version::Post10(x, _) | Version::Post10Dev(x, _)) => a == x,
             (Post10mm(a, b), Version::Post10(x, y)) => a == x && b <= y,
+            (Post10mDev(a, p), Version::Post10Dev(x, q)) => a == x && p.matches(q),
             _ => false,
         }
     }
 
-    /// Remove minor number.
+    /// Compare the given [`Version`] with this [`PartialVersion`], considering
+    /// only the parts present in this [`PartialVersion`]. Returns the ordering
+    /// of `version` relative to `self`.
+    ///
+    /// For example, 15.4 is equal to 15, greater than 14, and less than 15.6,
+    /// and 18beta2 is equal to 18beta but less than 18.0.
+    pub fn compare(&self, version: Version) -> Ordering {
+        let partial = self.key();
+        compare_keys(version.key().map(Some), partial)
+    }
+
+    /// Remove minor number or pre-release.
     #[must_use]
     pub fn widened(&self) -> PartialVersion {
         use PartialVersion::*;
         match self {
-            Pre10mm(a, b, _) => Pre10m(*a, *b),
-            Post10mm(a, _) => Post10m(*a),
+            Pre10mm(a, b, _) | Pre10mDev(a, b, _) => Pre10m(*a, *b),
+            Post10mm(a, _) | Post10mDev(a, _) => Post10m(*a),
             _ => *self,
         }
     }
@@ -144,18 +179,45 @@ impl PartialVersion {
     /// disagree with its [`PartialEq`] and [`PartialOrd`] implementations, so
     /// this function provides a sort key that implements [`Ord`] and can be
     /// used with sorting functions, e.g. [`slice::sort_by_key`].
+    ///
+    /// The key is the major number, the point number, the pre-release stage,
+    /// then the minor or pre-release number. Missing parts sort first, so, for
+    /// example, `18` < `18beta` < `18beta1` < `18rc1` < `18.0`.
     #[allow(dead_code)]
-    pub fn sort_key(&self) -> (u32, Option<u32>, Option<u32>) {
+    pub fn sort_key(&self) -> (u32, Option<u32>, Option<u32>, Option<u32>) {
+        let [a, b, c, d] = self.key();
+        (a.unwrap_or_default(), b, c, d)
+    }
+
+    /// The same as [`sort_key`][`Self::sort_key`], as an array. The first
+    /// element is always present.
+    fn key(&self) -> [Option<u32>; 4] {
         use PartialVersion::*;
+        const RELEASED: Option<u32> = Some(super::PreRelease::RELEASED);
         match *self {
-            Pre10m(a, b) => (a, Some(b), None),
-            Pre10mm(a, b, c) => (a, Some(b), Some(c)),
-            Post10m(a) => (a, None, None),
-            Post10mm(a, b) => (a, Some(b), None),
+            Pre10m(a, b) => [Some(a), Some(b), None, None],
+            Pre10mm(a, b, c) => [Some(a), Some(b), RELEASED, Some(c)],
+            Post10m(a) => [Some(a), None, None, None],
+            Post10mm(a, b) => [Some(a), Some(0), RELEASED, Some(b)],
+            Pre10mDev(a, b, pre) => [Some(a), Some(b), Some(pre.key().0), pre.key().1],
+            Post10mDev(a, pre) => [Some(a), Some(0), Some(pre.key().0), pre.key().1],
         }
     }
 }
 
+/// Compare two keys – see [`PartialVersion::sort_key`] – considering only the
+/// parts present in both.
+fn compare_keys(a: [Option<u32>; 4], b: [Option<u32>; 4]) -> Ordering {
+    a.into_iter()
+        .zip(b)
+        .map_while(|pair| match pair {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => None,
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
 impl PartialEq for PartialVersion {
     fn eq(&self, other: &Self) -> bool {
         self.partial_cmp(other) == Some(Ordering::Equal)
@@ -164,28 +226,7 @@ impl PartialEq for PartialVersion {
 
 impl PartialOrd for PartialVersion {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        use PartialVersion::*;
-        match (*self, *other) {
-            (Pre10m(a, b), Pre10m(x, y)) => Some((a, b).cmp(&(x, y))),
-            (Pre10m(a, b), Pre10mm(x, y, _)) => Some((a, b).cmp(&(x, y))),
-            (Pre10mm(a, b, _), Pre10m(x, y)) => Some((a, b).cmp(&(x, y))),
-            (Pre10mm(a, b, c), Pre10mm(x, y, z)) => Some((a, b, c).cmp(&(x, y, z))),
-
-            (Post10m(a), Post10m(x)) => Some(a.cmp(&x)),
-            (Post10m(a), Post10mm(x, _)) => Some(a.cmp(&x)),
-            (Post10mm(a, _), Post10m(x)) => Some(a.cmp(&x)),
-            (Post10mm(a, b), Post10mm(x, y)) => Some((a, b).cmp(&(x, y))),
-
-            (Pre10m(..), Post10m(..)) => Some(Ordering::Less),
-            (Pre10m(..), Post10mm(..)) => Some(Ordering::Less),
-            (Pre10mm(..), Post10m(..)) => Some(Ordering::Less),
-            (Pre10mm(..), Post10mm(..)) => Some(Ordering::Less),
-
-            (Post10m(..), Pre10m(..)) => Some(Ordering::Greater),
-            (Post10m(..), Pre10mm(..)) => Some(Ordering::Greater),
-            (Post10mm(..), Pre10m(..)) => Some(Ordering::Greater),
-            (Post10mm(..), Pre10mm(..)) => Some(Ordering::Greater),
-        }
+        Some(compare_keys(self.key(), other.key()))
     }
 }
 
@@ -196,6 +237,8 @@ impl fmt::Display for PartialVersion {
             Self::Pre10mm(a, b, c) => fmt.pad(&format!("{a}.{b}.{c}")),
             Self::Post10m(a) => fmt.pad(&format!("{a}")),
             Self::Post10mm(a, b) => fmt.pad(&format!("{a}.{b}")),
+            Self::Pre10mDev(a, b, pre) => fmt.pad(&format!("{a}.{b}{pre}")),
+            Self::Post10mDev(a, pre) => fmt.pad(&format!("{a}{pre}")),
         }
     }
 }
@@ -205,19 +248,27 @@ impl FromStr for PartialVersion {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         static RE: LazyLock<Regex> = LazyLock::new(|| {
-            Regex::new(r"(?x) \b (\d+) (?: [.] (\d+) (?: [.] (\d+) )? )? \b")
-                .expect("invalid regex (for matching partial PostgreSQL versions)")
+            Regex::new(
+                r"(?x) \b (\d+) (?: [.] (\d+) (?: [.] (\d+) )? )? ( devel | (?:alpha|beta|rc)\d* )? \b",
+            )
+            .expect("invalid regex (for matching partial PostgreSQL versions)")
         });
         match RE.captures(s) {
             Some(caps) => match (
                 caps.get(1).and_then(|n| n.as_str().parse::<u32>().ok()),
                 caps.get(2).and_then(|n| n.as_str().parse::<u32>().ok()),
                 caps.get(3).and_then(|n| n.as_str().parse::<u32>().ok()),
+                caps.get(4)
+                    .map(|pre| PartialPreRelease::parse(pre.as_str())),
             ) {
-                (Some(a), Some(b), None) if a < 10 => Ok(Self::Pre10m(a, b)),
-                (Some(a), Some(b), Some(c)) if a < 10 => Ok(Self::Pre10mm(a, b, c)),
-                (Some(a), None, None) if a >= 10 => Ok(Self::Post10m(a)),
-                (Some(a), Some(b), None) if a >= 10 => Ok(Self::Post10mm(a, b)),
+                (Some(a), Some(b), None, None) if a < 10 => Ok(Self::Pre10m(a, b)),
+                (Some(a), Some(b), Some(c), None) if a < 10 => Ok(Self::Pre10mm(a, b, c)),
+                (Some(a), None, None, None) if a >= 10 => Ok(Self::Post10m(a)),
+                (Some(a), Some(b), None, None) if a >= 10 => Ok(Self::Post10mm(a, b)),
+                (Some(a), Some(b), None, Some(Some(pre))) if a < 10 => {
+                    Ok(Self::Pre10mDev(a, b, pre))
+                }
+                (Some(a), None, None, Some(Some(pre))) if a >= 10 => Ok(Self::Post10mDev(a, pre)),
                 _ => Err(VersionError::BadlyFormed { text: Some(s.into()) }),
             },
             None => Err(VersionError::NotFound { text: Some(s.into()) }),
@@ -225,6 +276,10 @@ impl FromStr for PartialVersion {
     }
 }
 
+// Serialized as a string, e.g. `"16"`.
+#[cfg(feature = "serde")]
+crate::util::impl_serde_display!(PartialVersion);
+
 #[cfg(test)]
 mod tests {
     use super::super::{Version, VersionError::*};
@@ -245,6 +300,57 @@ mod tests {
         assert_eq!(Ok(Post10m(12)), "12".parse());
     }
 
+    #[test]
+    fn parses_pre_release_versions() {
+        use super::super::PartialPreRelease::*;
+        assert_eq!(Ok(Post10mDev(18, Beta(None))), "18beta".parse());
+        assert_eq!(Ok(Post10mDev(17, Rc(Some(1)))), "17rc1".parse());
+        assert_eq!(Ok(Post10mDev(18, Devel)), "18devel".parse());
+        assert_eq!(Ok(Pre10mDev(9, 6, Beta(Some(1)))), "9.6beta1".parse());
+        assert!(matches!(
+            "18.1beta".parse::<PartialVersion>(),
+            Err(BadlyFormed { .. })
+        ));
+        assert!(matches!(
+            "9beta1".parse::<PartialVersion>(),
+            Err(BadlyFormed { .. })
+        ));
+        for version in ["18beta", "17rc1", "18devel", "9.6beta1"] {
+            assert_eq!(
+                version,
+                version.parse::<PartialVersion>().unwrap().to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn compatible_with_pre_releases() {
+        let version = "18beta2".parse().unwrap();
+        assert!(Post10m(18).compatible(version));
+        assert!("18beta"
+            .parse::<PartialVersion>()
+            .unwrap()
+            .compatible(version));
+        assert!("18beta2"
+            .parse::<PartialVersion>()
+            .unwrap()
+            .compatible(version));
+        assert!(!"18beta1"
+            .parse::<PartialVersion>()
+            .unwrap()
+            .compatible(version));
+        assert!(!"18rc"
+            .parse::<PartialVersion>()
+            .unwrap()
+            .compatible(version));
+        assert!(!Post10mm(18, 0).compatible(version));
+        let version = "18.0".parse().unwrap();
+        assert!(!"18beta"
+            .parse::<PartialVersion>()
+            .unwrap()
+            .compatible(version));
+    }
+
     #[test]
     fn parse_returns_error_when_version_is_invalid() {
         // 4294967295 is (2^32 + 1), so won't fit in a u32.
@@ -393,6 +499,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compare_considers_only_parts_present() {
+        use std::cmp::Ordering::*;
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        assert_eq!(Post10m(15).compare(version("15.4")), Equal);
+        assert_eq!(Post10m(14).compare(version("15.4")), Greater);
+        assert_eq!(Post10mm(15, 6).compare(version("15.4")), Less);
+        assert_eq!(Pre10m(9, 6).compare(version("9.6.24")), Equal);
+        assert_eq!(Pre10mm(9, 6, 25).compare(version("9.6.24")), Less);
+        assert_eq!(Post10m(10).compare(version("9.6.24")), Less);
+        let beta = "18beta".parse::<PartialVersion>().unwrap();
+        assert_eq!(beta.compare(version("18beta2")), Equal);
+        assert_eq!(beta.compare(version("18devel")), Less);
+        assert_eq!(beta.compare(version("18rc1")), Greater);
+        assert_eq!(beta.compare(version("18.0")), Greater);
+        assert_eq!(Post10m(18).compare(version("18beta2")), Equal);
+        assert_eq!(Post10mm(18, 0).compare(version("18beta2")), Less);
+    }
+
+    #[test]
+    fn sort_key_orders_pre_releases() {
+        let mut versions: Vec<PartialVersion> = [
+            "18.0", "18rc1", "18", "18beta1", "18beta", "18devel", "17.6",
+        ]
+        .iter()
+        .map(|v| v.parse().unwrap())
+        .collect();
+        versions.sort_by_key(PartialVersion::sort_key);
+        let versions: Vec<_> = versions.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            versions,
+            ["17.6", "18", "18devel", "18beta", "18beta1", "18rc1", "18.0"]
+        );
+    }
+
     #[test]
     fn sort_key_works_as_expected() {
         let mut versions = vec![
@@ -426,4 +567,16 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        for version in ["9.6", "9.6.24", "16", "16.4", "18beta"] {
+            let partial: PartialVersion = version.parse().unwrap();
+            let json = serde_json::to_string(&partial).unwrap();
+            assert_eq!(json, format!("\"{version}\""));
+            let partial: PartialVersion = serde_json::from_str(&json).unwrap();
+            assert_eq!(partial.to_string(), version);
+        }
+    }
 }
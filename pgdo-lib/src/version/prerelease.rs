@@ -0,0 +1,179 @@
+use std::fmt;
+
+/// A pre-release of a PostgreSQL major version, e.g. the `beta2` in
+/// `17beta2`. Development builds – from the master branch – are `devel`.
+///
+/// The derived ordering is correct: devel < alpha < beta < rc.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PreRelease {
+    Devel,
+    Alpha(u32),
+    Beta(u32),
+    Rc(u32),
+}
+
+impl PreRelease {
+    /// Rank of a released version, i.e. not a pre-release; see [`Self::key`].
+    pub(super) const RELEASED: u32 = 4;
+
+    /// A key for ordering: the stage, then the number within that stage.
+    pub(super) fn key(self) -> (u32, u32) {
+        match self {
+            Self::Devel => (0, 0),
+            Self::Alpha(n) => (1, n),
+            Self::Beta(n) => (2, n),
+            Self::Rc(n) => (3, n),
+        }
+    }
+
+    /// Parse a pre-release suffix, e.g. `beta2`, `devel`.
+    pub(super) fn parse(s: &str) -> Option<Self> {
+        match PartialPreRelease::parse(s)? {
+            PartialPreRelease::Devel => Some(Self::Devel),
+            PartialPreRelease::Alpha(n) => n.map(Self::Alpha),
+            PartialPreRelease::Beta(n) => n.map(Self::Beta),
+            PartialPreRelease::Rc(n) => n.map(Self::Rc),
+        }
+    }
+}
+
+/// Convert a [`PartialPreRelease`] into a [`PreRelease`], with 0 (zero) in
+/// place of a missing number, e.g. `beta` becomes `beta0`.
+impl From<PartialPreRelease> for PreRelease {
+    fn from(partial: PartialPreRelease) -> Self {
+        match partial {
+            PartialPreRelease::Devel => Self::Devel,
+            PartialPreRelease::Alpha(n) => Self::Alpha(n.unwrap_or_default()),
+            PartialPreRelease::Beta(n) => Self::Beta(n.unwrap_or_default()),
+            PartialPreRelease::Rc(n) => Self::Rc(n.unwrap_or_default()),
+        }
+    }
+}
+
+impl fmt::Display for PreRelease {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        PartialPreRelease::from(*self).fmt(fmt)
+    }
+}
+
+/// A pre-release with the number optionally missing, e.g. the `beta` in
+/// `18beta`, which matches any beta of PostgreSQL 18.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PartialPreRelease {
+    Devel,
+    Alpha(Option<u32>),
+    Beta(Option<u32>),
+    Rc(Option<u32>),
+}
+
+impl PartialPreRelease {
+    /// Does the given pre-release match this? The stage must be the same, as
+    /// must the number, if this has one.
+    pub fn matches(&self, pre: PreRelease) -> bool {
+        let (stage, number) = self.key();
+        let (x, y) = pre.key();
+        stage == x && number.is_none_or(|n| n == y)
+    }
+
+    /// A key for ordering: the stage, then the number, if there is one.
+    pub(super) fn key(self) -> (u32, Option<u32>) {
+        match self {
+            Self::Devel => (0, Some(0)),
+            Self::Alpha(n) => (1, n),
+            Self::Beta(n) => (2, n),
+            Self::Rc(n) => (3, n),
+        }
+    }
+
+    /// Parse a pre-release suffix, e.g. `beta`, `beta2`, `devel`.
+    pub(super) fn parse(s: &str) -> Option<Self> {
+        let split = s.find(|c: char| c.is_ascii_digit()).unwrap_or(s.len());
+        let number = match &s[split..] {
+            "" => None,
+            number => Some(number.parse().ok()?),
+        };
+        match &s[..split] {
+            "devel" if number.is_none() => Some(Self::Devel),
+            "alpha" => Some(Self::Alpha(number)),
+            "beta" => Some(Self::Beta(number)),
+            "rc" => Some(Self::Rc(number)),
+            _ => None,
+        }
+    }
+}
+
+impl From<PreRelease> for PartialPreRelease {
+    fn from(pre: PreRelease) -> Self {
+        match pre {
+            PreRelease::Devel => Self::Devel,
+            PreRelease::Alpha(n) => Self::Alpha(Some(n)),
+            PreRelease::Beta(n) => Self::Beta(Some(n)),
+            PreRelease::Rc(n) => Self::Rc(Some(n)),
+        }
+    }
+}
+
+impl fmt::Display for PartialPreRelease {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (stage, number) = match self {
+            Self::Devel => ("devel", None),
+            Self::Alpha(n) => ("alpha", *n),
+            Self::Beta(n) => ("beta", *n),
+            Self::Rc(n) => ("rc", *n),
+        };
+        match number {
+            Some(number) => write!(fmt, "{stage}{number}"),
+            None => write!(fmt, "{stage}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartialPreRelease, PreRelease};
+
+    #[test]
+    fn parses_pre_releases() {
+        assert_eq!(PreRelease::parse("devel"), Some(PreRelease::Devel));
+        assert_eq!(PreRelease::parse("beta2"), Some(PreRelease::Beta(2)));
+        assert_eq!(PreRelease::parse("rc1"), Some(PreRelease::Rc(1)));
+        assert_eq!(PreRelease::parse("beta"), None);
+        assert_eq!(PreRelease::parse("devel1"), None);
+        assert_eq!(PreRelease::parse("gamma1"), None);
+        assert_eq!(
+            PartialPreRelease::parse("beta"),
+            Some(PartialPreRelease::Beta(None))
+        );
+    }
+
+    #[test]
+    fn orders_pre_releases() {
+        let mut pres = vec![
+            PreRelease::Rc(1),
+            PreRelease::Beta(2),
+            PreRelease::Devel,
+            PreRelease::Beta(1),
+            PreRelease::Alpha(3),
+        ];
+        pres.sort();
+        assert_eq!(
+            pres,
+            vec![
+                PreRelease::Devel,
+                PreRelease::Alpha(3),
+                PreRelease::Beta(1),
+                PreRelease::Beta(2),
+                PreRelease::Rc(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_pre_release_matches() {
+        assert!(PartialPreRelease::Beta(None).matches(PreRelease::Beta(2)));
+        assert!(PartialPreRelease::Beta(Some(2)).matches(PreRelease::Beta(2)));
+        assert!(!PartialPreRelease::Beta(Some(1)).matches(PreRelease::Beta(2)));
+        assert!(!PartialPreRelease::Beta(None).matches(PreRelease::Rc(1)));
+        assert!(PartialPreRelease::Devel.matches(PreRelease::Devel));
+    }
+}
@@ -4,15 +4,25 @@ use std::fs::File;
 use std::os::unix::ffi::OsStringExt;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 use shell_quote::{QuoteExt, Sh};
 
 use pgdo::cluster::{
-    self, exists,
+    self,
+    control::DatabaseState,
+    exists,
+    metadata::{Metadata, RuntimePin},
+    pidfile,
     sqlx::{query, Row},
-    version, Cluster, ClusterError, ClusterStatus,
+    version, Cluster, ClusterError, ClusterStatus, ShutdownMode, StopOptions,
 };
 use pgdo::coordinate::State::*;
+use pgdo::runtime::{
+    constraint::Constraint,
+    strategy::{SelectionPolicy, Strategy},
+    Runtime,
+};
 use pgdo::version::{PartialVersion, Version};
 use pgdo_test::for_all_runtimes;
 
@@ -184,7 +194,7 @@ fn cluster_create_creates_cluster_with_neutral_locale_and_timezone() -> TestResu
     assert_eq!(params.get("lc_monetary"), Some(&"C".into()));
     assert_eq!(params.get("lc_numeric"), Some(&"C".into()));
     assert_eq!(params.get("lc_time"), Some(&"C".into()));
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -201,6 +211,82 @@ fn cluster_create_does_nothing_when_it_already_exists() -> TestResult {
     Ok(())
 }
 
+#[for_all_runtimes]
+#[test]
+fn cluster_create_pins_runtime() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let mut cluster = Cluster::new(&data_dir, runtime.clone())?;
+    cluster.create()?;
+    let pin = Metadata::read(&data_dir)?.and_then(|metadata| metadata.runtime);
+    assert_eq!(pin, Some(RuntimePin::from(&runtime)));
+
+    // The pinned runtime has changed; it's still used unless strict.
+    let changed = Metadata {
+        runtime: Some(RuntimePin {
+            version: Version::Post10(10, 0),
+            ..RuntimePin::from(&runtime)
+        }),
+    };
+    changed.write(&data_dir)?;
+    assert_eq!(cluster.runtime()?, runtime);
+    cluster.strict_runtime = true;
+    assert!(matches!(
+        cluster.runtime(),
+        Err(ClusterError::RuntimePinChanged { .. })
+    ));
+
+    // The pinned runtime has disappeared; another is selected unless strict.
+    let missing = Metadata {
+        runtime: Some(RuntimePin {
+            bindir: temp_dir.path().join("missing"),
+            ..RuntimePin::from(&runtime)
+        }),
+    };
+    missing.write(&data_dir)?;
+    assert!(matches!(
+        cluster.runtime(),
+        Err(ClusterError::RuntimePinMissing { .. })
+    ));
+    cluster.strict_runtime = false;
+    assert_eq!(cluster.runtime()?, runtime);
+
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_runtime_selects_pinned_runtime_through_strategy() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    Cluster::new(&data_dir, runtime.clone())?.create()?;
+
+    // Another runtime with the same version, which the strategy would
+    // otherwise select first.
+    let other = Runtime {
+        bindir: temp_dir.path().join("other"),
+        version: runtime.version,
+    };
+    let strategy = Strategy::from(runtime.clone()).push_front(other.clone());
+    let cluster = Cluster::new(&data_dir, strategy)?;
+    assert_eq!(cluster.runtime()?, runtime);
+
+    // The strategy's policy excludes the pinned runtime.
+    let policy = SelectionPolicy {
+        exclude: Constraint::path(runtime.bindir.to_str().unwrap()).unwrap(),
+        ..SelectionPolicy::default()
+    };
+    let strategy = Strategy::from(runtime.clone())
+        .push_front(other.clone())
+        .with_policy(policy);
+    let mut cluster = Cluster::new(&data_dir, strategy)?;
+    assert_eq!(cluster.runtime()?, other);
+    cluster.strict_runtime = true;
+    assert_eq!(cluster.runtime()?, other);
+
+    Ok(())
+}
+
 #[for_all_runtimes]
 #[test]
 fn cluster_start_stop_starts_and_stops_cluster() -> TestResult {
@@ -212,8 +298,204 @@ fn cluster_start_stop_starts_and_stops_cluster() -> TestResult {
     assert_eq!(cluster.status()?, ClusterStatus::Stopped);
     cluster.start(&[])?;
     assert_eq!(cluster.status()?, ClusterStatus::Running);
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
+    assert_eq!(cluster.status()?, ClusterStatus::Stopped);
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_stop_escalates_when_timeout_expires() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    cluster.start(&[])?;
+    // A smart shutdown waits for this session to end, which it never will.
+    let mut client = cluster.connection().client()?;
+    let options = StopOptions {
+        mode: ShutdownMode::Smart,
+        timeout: Some(Duration::from_secs(1)),
+    };
+    assert_eq!(cluster.stop(options)?, Modified);
+    assert_eq!(cluster.status()?, ClusterStatus::Stopped);
+    assert!(client.is_valid(Duration::from_secs(1)).is_err());
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_stop_immediate_requires_crash_recovery() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    cluster.start(&[])?;
+    assert_eq!(cluster.stop(ShutdownMode::Immediate.into())?, Modified);
     assert_eq!(cluster.status()?, ClusterStatus::Stopped);
+    let state = DatabaseState::read(&cluster.datadir)?.expect("pg_control not found");
+    assert!(state.needs_crash_recovery(), "{state}");
+    cluster.start(&[])?;
+    let log = std::fs::read_to_string(cluster.logfile())?;
+    assert!(log.contains("automatic recovery in progress"), "{log}");
+    cluster.stop(StopOptions::default())?;
+    Ok(())
+}
+
+#[for_all_runtimes(min = "9.2")]
+#[test]
+fn cluster_restart_keeps_options() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    assert_eq!(cluster.restart(StopOptions::default())?, Unmodified);
+    cluster.start(&[("example.setting".into(), "Hello, World!".into())])?;
+    let before = cluster.read_pidfile()?.expect("postmaster.pid not found");
+    assert_eq!(cluster.restart(StopOptions::default())?, Modified);
+    assert_eq!(cluster.status()?, ClusterStatus::Running);
+    let after = cluster.read_pidfile()?.expect("postmaster.pid not found");
+    assert_ne!(before.pid, after.pid);
+    let mut client = cluster.connection().client()?;
+    let row = client.query_one("SHOW example.setting", &[])?;
+    assert_eq!(row.get::<_, String>(0), "Hello, World!");
+    cluster.stop(StopOptions::default())?;
+    Ok(())
+}
+
+#[for_all_runtimes(min = "9.5")]
+#[test]
+fn cluster_reload_and_pending_restart() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    assert_eq!(cluster.reload()?, Unmodified);
+    cluster.start(&[])?;
+    assert_eq!(cluster.reload()?, Modified);
+    assert!(cluster.pending_restart()?.is_empty());
+    let mut client = cluster.connection().client()?;
+    client.batch_execute("ALTER SYSTEM SET work_mem = '8MB'")?;
+    client.batch_execute("ALTER SYSTEM SET max_prepared_transactions = 3")?;
+    // Nothing changes until the configuration is reloaded.
+    assert!(cluster.pending_restart()?.is_empty());
+    assert_eq!(cluster.reload()?, Modified);
+    assert_eq!(
+        cluster.pending_restart()?,
+        vec!["max_prepared_transactions"]
+    );
+    // New connections see the reloaded configuration.
+    let mut client = cluster.connection().client()?;
+    let row = client.query_one("SHOW work_mem", &[])?;
+    assert_eq!(row.get::<_, String>(0), "8MB");
+    cluster.restart(StopOptions::default())?;
+    assert!(cluster.pending_restart()?.is_empty());
+    let mut client = cluster.connection().client()?;
+    let row = client.query_one("SHOW max_prepared_transactions", &[])?;
+    assert_eq!(row.get::<_, String>(0), "3");
+    cluster.stop(StopOptions::default())?;
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_status_reads_pidfile() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let cluster = Cluster::new(&data_dir, runtime)?;
+    assert_eq!(cluster.read_pidfile()?, None);
+    cluster.start(&[])?;
+    let pidfile = cluster.read_pidfile()?.expect("postmaster.pid not found");
+    assert!(pidfile.pid > 0);
+    assert!(pidfile.process_exists());
+    assert_eq!(pidfile.socket_dir.as_deref(), Some(data_dir.as_path()));
+    cluster.stop(StopOptions::default())?;
+    assert_eq!(cluster.read_pidfile()?, None);
+
+    // Leave behind a `postmaster.pid` naming a process that no longer exists.
+    let mut child = std::process::Command::new("true").spawn()?;
+    child.wait()?;
+    let stale = format!("{}\n{}\n", child.id(), data_dir.display());
+    std::fs::write(cluster.pidfile(), stale)?;
+    assert_eq!(cluster.status()?, ClusterStatus::StalePidFile);
+    assert!(!cluster.running()?);
+
+    // An empty `postmaster.pid` may be one that the server is writing now,
+    // but not if it has been left like that for a while.
+    let file = File::create(cluster.pidfile())?;
+    assert_eq!(cluster.status()?, ClusterStatus::Starting);
+    file.set_modified(SystemTime::now() - pidfile::WRITE_GRACE_PERIOD)?;
+    assert_eq!(cluster.status()?, ClusterStatus::StalePidFile);
+    assert!(!cluster.running()?);
+    Ok(())
+}
+
+/// Whether a process is the server is only known on Linux.
+#[cfg(target_os = "linux")]
+#[for_all_runtimes]
+#[test]
+fn cluster_start_removes_stale_pidfile() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let cluster = Cluster::new(&data_dir, runtime)?;
+    cluster.create()?;
+    assert_eq!(cluster.remove_stale_pidfile()?, Unmodified);
+
+    // Leave behind a `postmaster.pid` naming a process that exists – this one
+    // – but is not the server, as if the PID had been reused after a crash.
+    // The server refuses to start while its socket lock file names it too.
+    let stale = format!("{}\n{}\n", std::process::id(), data_dir.display());
+    std::fs::write(cluster.pidfile(), &stale)?;
+    std::fs::write(data_dir.join(".s.PGSQL.5432.lock"), &stale)?;
+    assert_eq!(cluster.status()?, ClusterStatus::StalePidFile);
+    assert_eq!(cluster.start(&[])?, Modified);
+    assert_eq!(cluster.status()?, ClusterStatus::Running);
+    assert_eq!(cluster.remove_stale_pidfile()?, Unmodified);
+    cluster.stop(StopOptions::default())?;
+
+    // Leave behind an empty `postmaster.pid`, as if the server crashed while
+    // writing it. It's left alone while it might still be being written.
+    let file = File::create(cluster.pidfile())?;
+    assert_eq!(cluster.remove_stale_pidfile()?, Unmodified);
+    file.set_modified(SystemTime::now() - pidfile::WRITE_GRACE_PERIOD)?;
+    assert_eq!(cluster.start(&[])?, Modified);
+    assert_eq!(cluster.status()?, ClusterStatus::Running);
+    cluster.stop(StopOptions::default())?;
+    Ok(())
+}
+
+/// `standby.signal` is only supported from PostgreSQL 12.
+#[for_all_runtimes(min = "12")]
+#[test]
+fn cluster_promote_promotes_standby() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let primary = Cluster::new(temp_dir.path().join("primary"), runtime.clone())?;
+    primary.start(&[])?;
+    assert!(!primary.in_recovery()?);
+    assert_eq!(primary.promote()?, Unmodified);
+
+    let standby_dir = temp_dir.path().join("standby");
+    let status = primary.exec(
+        None,
+        "pg_basebackup".into(),
+        &[
+            OsString::from("--pgdata"),
+            standby_dir.clone().into(),
+            "--write-recovery-conf".into(),
+        ],
+    )?;
+    assert!(status.success());
+    // The primary's socket lock file is copied too; remove it.
+    for entry in standby_dir.read_dir()? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "lock") {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let standby = Cluster::new(standby_dir, runtime)?;
+    standby.start(&[])?;
+    assert!(standby.in_recovery()?);
+    assert_eq!(standby.status()?, ClusterStatus::InRecovery);
+    assert_eq!(standby.promote()?, Modified);
+    assert!(!standby.in_recovery()?);
+    assert_eq!(standby.status()?, ClusterStatus::Running);
+    assert_eq!(standby.promote()?, Unmodified);
+
+    standby.stop(StopOptions::default())?;
+    primary.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -232,7 +514,7 @@ fn cluster_start_with_options() -> TestResult {
     })
     .map(|row| row.get::<String, _>(0))?;
     assert_eq!(example_setting, "Hello, World!");
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -264,7 +546,7 @@ fn cluster_exec_sets_environment() -> TestResult {
     );
     assert_eq!(env.get("PGDATABASE"), Some("postgres").as_ref());
     assert!(matches!(env.get("DATABASE_URL"), Some(url) if url.starts_with("postgresql://")));
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -360,7 +642,7 @@ fn cluster_databases_that_already_exist_can_be_created_without_error() -> TestRe
     cluster.start(&[])?;
     assert!(matches!(cluster.createdb("foo-bar")?, Modified));
     assert!(matches!(cluster.createdb("foo-bar")?, Unmodified));
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -374,7 +656,45 @@ fn cluster_databases_that_do_not_exist_can_be_dropped_without_error() -> TestRes
     cluster.createdb("foo-bar")?;
     assert!(matches!(cluster.dropdb("foo-bar")?, Modified));
     assert!(matches!(cluster.dropdb("foo-bar")?, Unmodified));
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_connection_uses_database_role_and_application_name() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let cluster = Cluster::new(data_dir, runtime)?;
+    cluster.start(&[])?;
+    cluster.createdb("foo")?;
+    cluster
+        .connection()
+        .client()?
+        .batch_execute("CREATE ROLE bob LOGIN")?;
+    let connection = cluster
+        .connection()
+        .database("foo")
+        .role("bob")
+        .application_name("example")
+        .statement_timeout(Duration::from_millis(1234));
+    let sql = "SELECT current_database(), current_user, \
+        current_setting('application_name'), current_setting('statement_timeout')";
+    let expected = ("foo", "bob", "example", "1234ms");
+    // Sync client.
+    let row = connection.client()?.query_one(sql, &[])?;
+    assert_eq!((row.get(0), row.get(1), row.get(2), row.get(3)), expected);
+    // Async client and pool.
+    block_on(async {
+        let row = connection.client_async().await?.query_one(sql, &[]).await?;
+        assert_eq!((row.get(0), row.get(1), row.get(2), row.get(3)), expected);
+        let pool = connection.max_connections(1).pool()?;
+        let row = query(sql).fetch_one(&pool).await?;
+        assert_eq!((row.get(0), row.get(1), row.get(2), row.get(3)), expected);
+        pool.close().await;
+        Ok::<_, ClusterError>(())
+    })?;
+    cluster.destroy()?;
     Ok(())
 }
 
@@ -0,0 +1,121 @@
+#![cfg(feature = "async")]
+
+use pgdo::cluster::{sqlx, AsyncCluster, AsyncClusterGuard, Cluster, ClusterStatus, StopOptions};
+use pgdo::coordinate::{run_and_stop_async, State::*};
+use pgdo::lock;
+use pgdo_test::for_all_runtimes;
+
+type TestResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Runtime::new().unwrap().block_on(future)
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_create_start_stop_destroy() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = AsyncCluster::new(data_dir.path().join("data"), runtime)?;
+    block_on(async {
+        assert_eq!(cluster.status().await?, ClusterStatus::Missing);
+        assert_eq!(cluster.create().await?, Modified);
+        assert_eq!(cluster.create().await?, Unmodified);
+        assert_eq!(cluster.status().await?, ClusterStatus::Stopped);
+        assert_eq!(cluster.start(&[]).await?, Modified);
+        assert_eq!(cluster.start(&[]).await?, Unmodified);
+        assert!(cluster.running().await?);
+        assert!(!cluster.in_recovery().await?);
+        assert_eq!(cluster.stop(StopOptions::default()).await?, Modified);
+        assert_eq!(cluster.stop(StopOptions::default()).await?, Unmodified);
+        assert_eq!(cluster.destroy().await?, Modified);
+        assert_eq!(cluster.destroy().await?, Unmodified);
+        assert_eq!(cluster.status().await?, ClusterStatus::Missing);
+        Ok(())
+    })
+}
+
+#[for_all_runtimes]
+#[test]
+fn cluster_databases_create_and_drop() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = AsyncCluster::new(data_dir.path().join("data"), runtime)?;
+    block_on(async {
+        cluster.start(&[]).await?;
+        assert_eq!(cluster.createdb("demo").await?, Modified);
+        assert_eq!(cluster.createdb("demo").await?, Unmodified);
+        assert!(cluster.databases().await?.contains(&"demo".to_string()));
+        assert_eq!(cluster.dropdb("demo").await?, Modified);
+        assert_eq!(cluster.dropdb("demo").await?, Unmodified);
+        assert!(!cluster.databases().await?.contains(&"demo".to_string()));
+        cluster.destroy().await?;
+        Ok(())
+    })
+}
+
+#[for_all_runtimes(min = "9.5")]
+#[test]
+fn cluster_reload_and_restart() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = AsyncCluster::new(data_dir.path().join("data"), runtime)?;
+    block_on(async {
+        assert_eq!(cluster.reload().await?, Unmodified);
+        assert_eq!(cluster.restart(StopOptions::default()).await?, Unmodified);
+        cluster.start(&[]).await?;
+        let pool = cluster.pool(None)?;
+        sqlx::query("ALTER SYSTEM SET max_prepared_transactions = 3")
+            .execute(&pool)
+            .await?;
+        pool.close().await;
+        assert_eq!(cluster.reload().await?, Modified);
+        assert_eq!(
+            cluster.pending_restart().await?,
+            vec!["max_prepared_transactions"]
+        );
+        assert_eq!(cluster.restart(StopOptions::default()).await?, Modified);
+        assert!(cluster.running().await?);
+        assert!(cluster.pending_restart().await?.is_empty());
+        cluster.destroy().await?;
+        Ok(())
+    })
+}
+
+#[for_all_runtimes]
+#[test]
+fn run_and_stop_async_leaves_the_cluster_in_place() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let cluster = AsyncCluster::new(&data_dir, runtime)?;
+    let lock = lock::UnlockedFile::try_from(&temp_dir.path().join("lock"))?;
+    block_on(async {
+        let databases = run_and_stop_async(
+            &cluster,
+            &[],
+            StopOptions::default(),
+            lock,
+            cluster.databases(),
+        )
+        .await??;
+        assert!(!databases.is_empty());
+        assert!(!cluster.running().await?);
+        assert!(data_dir.exists());
+        Ok(())
+    })
+}
+
+#[for_all_runtimes]
+#[test]
+fn async_guard_stops_cluster() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let cluster = Cluster::new(&data_dir, runtime.clone())?;
+    let lock = lock::UnlockedFile::try_from(&temp_dir.path().join("lock"))?;
+    block_on(async {
+        let guard =
+            AsyncClusterGuard::startup(lock, AsyncCluster::new(&data_dir, runtime)?, &[]).await?;
+        assert!(guard.running().await?);
+        assert!(cluster.running()?);
+        guard.shutdown().await?;
+        assert!(!cluster.running()?);
+        Ok(())
+    })
+}
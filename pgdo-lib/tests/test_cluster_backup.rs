@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::ffi::OsString;
 
-use pgdo::cluster::{backup, resource, Cluster, ClusterError};
+use pgdo::cluster::{backup, resource, sqlx, Cluster, ClusterError, StopOptions};
 use pgdo::coordinate;
 use pgdo_test::for_all_runtimes;
 
@@ -16,7 +16,7 @@ fn cluster_backup() -> TestResult {
     let data_dir = temp_dir.path().join("data");
     let backup_dir = tempfile::TempDir::new()?;
 
-    let cluster = Cluster::new(data_dir, runtime)?;
+    let cluster = Cluster::new(data_dir, runtime.clone())?;
     let backup = rt
         .block_on(backup::Backup::prepare(backup_dir.path()))
         .unwrap();
@@ -31,19 +31,35 @@ fn cluster_backup() -> TestResult {
     // Run backup 3 times.
     for num in 1..=3 {
         let archive_command = format!("cp %p {}/%f", &backup.backup_wal_dir.display());
-        let restart_needed = rt
-            .block_on(backup.do_configure_archiving(&resource, &archive_command))
+        rt.block_on(backup.do_configure_archiving(&resource, &archive_command))
             .unwrap();
-        assert!((restart_needed && num == 1) || (!restart_needed && num > 1));
 
-        // Restart cluster via the `resource`.
-        if let either::Right(ref resource) = resource {
-            resource.facet().stop()?;
-            resource.facet().start(&[])?;
+        // Restart cluster, if necessary, via the `resource`. Only the first
+        // time through are there changes that need a restart.
+        let applied = resource::ensure_applied(&resource, StopOptions::default())?;
+        if num == 1 {
+            assert_eq!(
+                applied,
+                resource::Applied::Restarted(vec!["archive_mode".into()])
+            );
+        } else {
+            assert_eq!(applied, resource::Applied::Reloaded);
         }
 
         // Run backup.
-        rt.block_on(backup.do_base_backup(&resource)).unwrap();
+        let backup_data_dir = rt
+            .block_on(backup.do_base_backup(&resource, &Default::default()))
+            .unwrap();
+
+        // Metadata has been recorded next to the backup.
+        let metadata = backup::Metadata::for_backup(&backup_data_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.server_version, runtime.version);
+        assert_eq!(metadata.bindir, runtime.bindir);
+        assert_eq!(metadata.format, backup::Format::Plain);
+        assert!(metadata.start_time <= metadata.end_time);
+        assert!(metadata.start_lsn.is_some());
 
         // WAL files have been archived.
         let files_wal = backup
@@ -74,6 +90,164 @@ fn cluster_backup() -> TestResult {
     Ok(())
 }
 
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_backup_tar_compressed() -> TestResult {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let backup_dir = tempfile::TempDir::new()?;
+
+    let cluster = Cluster::new(data_dir, runtime)?;
+    let backup = rt
+        .block_on(backup::Backup::prepare(backup_dir.path()))
+        .unwrap();
+    let lock = pgdo::lock::UnlockedFile::try_from(&temp_dir.path().join(".lock"))?;
+    let resource = coordinate::resource::ResourceFree::new(lock, cluster);
+
+    // Start the cluster, configure archiving, and restart the cluster.
+    let (_, resource) = resource::startup(resource, &[]).unwrap();
+    let archive_command = format!("cp %p {}/%f", &backup.backup_wal_dir.display());
+    rt.block_on(backup.do_configure_archiving(&resource, &archive_command))
+        .unwrap();
+    if let either::Right(ref resource) = resource {
+        resource.facet().stop(StopOptions::default())?;
+        resource.facet().start(&[])?;
+    }
+
+    // Client-side gzip compression is supported by all versions.
+    let options = backup::BaseBackupOptions {
+        format: backup::Format::Tar(Some("client-gzip:1".parse().unwrap())),
+        ..Default::default()
+    };
+    let backup_data_dir = rt
+        .block_on(backup.do_base_backup(&resource, &options))
+        .unwrap();
+    assert!(backup_data_dir.join("base.tar.gz").is_file());
+
+    Ok(())
+}
+
+#[for_all_runtimes(min = "17")]
+#[test]
+fn cluster_backup_incremental() -> TestResult {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let backup_dir = tempfile::TempDir::new()?;
+
+    let cluster = Cluster::new(data_dir, runtime)?;
+    let backup = rt
+        .block_on(backup::Backup::prepare(backup_dir.path()))
+        .unwrap();
+    let lock = pgdo::lock::UnlockedFile::try_from(&temp_dir.path().join(".lock"))?;
+    let resource = coordinate::resource::ResourceFree::new(lock, cluster);
+
+    // Start the cluster, configure archiving, and restart the cluster.
+    let (_, resource) = resource::startup(resource, &[]).unwrap();
+    let archive_command = format!("cp %p {}/%f", &backup.backup_wal_dir.display());
+    rt.block_on(backup.do_configure_archiving(&resource, &archive_command))
+        .unwrap();
+    if let either::Right(ref resource) = resource {
+        resource.facet().stop(StopOptions::default())?;
+        resource.facet().start(&[])?;
+    }
+
+    let options = backup::BaseBackupOptions { incremental: true, ..Default::default() };
+
+    // The first backup is full because WAL summarization was off, but it is
+    // turned on beforehand so the second backup can be incremental.
+    let backup1 = rt
+        .block_on(backup.do_base_backup(&resource, &options))
+        .unwrap();
+    let backup2 = rt
+        .block_on(backup.do_base_backup(&resource, &options))
+        .unwrap();
+    assert!(!backup::is_incremental(&backup1)?);
+    assert!(backup::is_incremental(&backup2)?);
+    assert_eq!(backup::chain(&backup2).unwrap(), vec![backup1, backup2]);
+
+    Ok(())
+}
+
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_backup_with_tablespace() -> TestResult {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    let temp_dir = tempfile::tempdir()?;
+    let data_dir = temp_dir.path().join("data");
+    let tablespace_dir = tempfile::TempDir::new()?;
+    let tablespace_location = tablespace_dir.path().canonicalize()?;
+    let backup_dir = tempfile::TempDir::new()?;
+
+    let cluster = Cluster::new(data_dir, runtime)?;
+    let backup = rt
+        .block_on(backup::Backup::prepare(backup_dir.path()))
+        .unwrap();
+    let lock = pgdo::lock::UnlockedFile::try_from(&temp_dir.path().join(".lock"))?;
+    let resource = coordinate::resource::ResourceFree::new(lock, cluster);
+
+    // Start the cluster and obtain `resource`.
+    let (_, resource) = resource::startup(resource, &[]).unwrap();
+    let either::Right(ref facet) = resource else {
+        panic!("expected exclusive resource");
+    };
+
+    // Configure archiving and restart the cluster.
+    let archive_command = format!("cp %p {}/%f", &backup.backup_wal_dir.display());
+    rt.block_on(backup.do_configure_archiving(&resource, &archive_command))
+        .unwrap();
+    facet.facet().stop(StopOptions::default())?;
+    facet.facet().start(&[])?;
+
+    // Create a tablespace with a table in it.
+    let oid = rt.block_on(async {
+        let pool = facet.facet().pool(None)?;
+        sqlx::query(&format!(
+            "CREATE TABLESPACE extra LOCATION '{}'",
+            tablespace_location.display()
+        ))
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE TABLE things (id int) TABLESPACE extra")
+            .execute(&pool)
+            .await?;
+        sqlx::query_scalar::<_, i64>("SELECT oid::int8 FROM pg_tablespace WHERE spcname = 'extra'")
+            .fetch_one(&pool)
+            .await
+            .map_err(ClusterError::from)
+    })?;
+
+    // Run backup.
+    let backup_data_dir = rt
+        .block_on(backup.do_base_backup(&resource, &Default::default()))
+        .unwrap();
+
+    // The tablespace has been backed up alongside the data directory, and its
+    // original location has been recorded.
+    let backup_tablespaces_dir = backup::tablespaces_dir(&backup_data_dir);
+    assert!(backup_tablespaces_dir.join(oid.to_string()).is_dir());
+    let tablespace_map = backup::TablespaceMap::for_backup(&backup_data_dir).unwrap();
+    assert_eq!(
+        tablespace_map.0.into_iter().collect::<Vec<_>>(),
+        vec![(u32::try_from(oid).unwrap(), tablespace_location)]
+    );
+
+    // The base backup links to the backed-up tablespace.
+    assert_eq!(
+        backup_data_dir
+            .join("pg_tblspc")
+            .join(oid.to_string())
+            .read_link()?,
+        backup_tablespaces_dir.join(oid.to_string())
+    );
+
+    Ok(())
+}
+
 fn is_file(entry: &std::fs::DirEntry) -> bool {
     entry
         .file_type()
@@ -1,4 +1,4 @@
-use pgdo::cluster::{config, Cluster, ClusterError};
+use pgdo::cluster::{config, Cluster, ClusterError, StopOptions};
 use pgdo_test::for_all_runtimes;
 
 type TestResult = Result<(), ClusterError>;
@@ -37,7 +37,7 @@ fn cluster_parameter_set() -> TestResult {
         Ok::<(), ClusterError>(())
     })?;
 
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -56,7 +56,7 @@ fn cluster_parameter_get() -> TestResult {
     })?;
     assert_eq!(value, Some(config::Value::String("pgdo".to_owned())));
 
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -81,7 +81,7 @@ fn cluster_setting_list() -> TestResult {
         println!("{parameter}: {value}");
     }
 
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
 
@@ -104,6 +104,6 @@ fn cluster_setting_get() -> TestResult {
     assert_eq!(application_name.setting, "pgdo");
     assert_eq!(application_name.vartype, "string");
 
-    cluster.stop()?;
+    cluster.stop(StopOptions::default())?;
     Ok(())
 }
@@ -0,0 +1,114 @@
+use pgdo::cluster::{
+    extension::{ExtensionState, Preload},
+    resource, Cluster, ClusterError,
+};
+use pgdo::coordinate::{self, State};
+use pgdo_test::for_all_runtimes;
+
+type TestResult = Result<(), ClusterError>;
+
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_extensions_can_be_listed_added_and_removed() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(&data_dir, runtime)?;
+    cluster.start(&[])?;
+
+    let extensions = cluster.extensions(None)?;
+    let plpgsql = extensions.iter().find(|ext| ext.name == "plpgsql");
+    assert!(plpgsql.is_some_and(|ext| ext.is_installed()));
+
+    // `earthdistance` depends on `cube`, which is installed too.
+    let state = cluster.ensure_extension(None, "earthdistance", None, Preload::WellKnown)?;
+    assert_eq!(state, ExtensionState::Modified);
+    let state = cluster.ensure_extension(None, "earthdistance", None, Preload::WellKnown)?;
+    assert_eq!(state, ExtensionState::Unmodified);
+    let installed: Vec<_> = cluster
+        .extensions(None)?
+        .into_iter()
+        .filter(|ext| ext.is_installed())
+        .map(|ext| ext.name)
+        .collect();
+    assert_eq!(installed, vec!["cube", "earthdistance", "plpgsql"]);
+
+    assert_eq!(
+        cluster.remove_extension(None, "earthdistance")?,
+        State::Modified
+    );
+    assert_eq!(
+        cluster.remove_extension(None, "earthdistance")?,
+        State::Unmodified
+    );
+
+    cluster.destroy()?;
+    Ok(())
+}
+
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_extension_not_available_names_bindir() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(&data_dir, runtime.clone())?;
+    cluster.start(&[])?;
+
+    let err = cluster
+        .ensure_extension(None, "no_such_extension", None, Preload::WellKnown)
+        .unwrap_err();
+    assert!(matches!(err, ClusterError::ExtensionNotAvailable { .. }));
+    assert!(err
+        .to_string()
+        .contains(&runtime.bindir.display().to_string()));
+
+    let err = cluster
+        .ensure_extension(None, "cube", Some("0.0"), Preload::WellKnown)
+        .unwrap_err();
+    assert!(err.to_string().contains("version \"0.0\""));
+
+    cluster.destroy()?;
+    Ok(())
+}
+
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_extension_that_needs_preloading_restarts_cluster() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    let lock = pgdo::lock::UnlockedFile::try_from(&temp_dir.path().join(".lock"))?;
+    let resource = coordinate::resource::ResourceFree::new(lock, cluster);
+    let (_, resource) = resource::startup(resource, &[]).unwrap();
+    let either::Right(resource) = resource else {
+        panic!("expected exclusive resource");
+    };
+    let facet = resource.facet();
+    let state = facet.ensure_extension(None, "pg_stat_statements", None, Preload::WellKnown)?;
+    assert_eq!(state, ExtensionState::Modified);
+    let mut client = facet.connection().client()?;
+    let row = client.query_one("SHOW shared_preload_libraries", &[])?;
+    assert_eq!(row.get::<_, String>(0), "pg_stat_statements");
+    client.query("SELECT * FROM pg_stat_statements", &[])?;
+    facet.destroy()?;
+    Ok(())
+}
+
+#[for_all_runtimes(min = "10")]
+#[test]
+fn cluster_extension_with_explicit_preload_library_restarts_cluster() -> TestResult {
+    let temp_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(temp_dir.path().join("data"), runtime)?;
+    let lock = pgdo::lock::UnlockedFile::try_from(&temp_dir.path().join(".lock"))?;
+    let resource = coordinate::resource::ResourceFree::new(lock, cluster);
+    let (_, resource) = resource::startup(resource, &[]).unwrap();
+    let either::Right(resource) = resource else {
+        panic!("expected exclusive resource");
+    };
+    let facet = resource.facet();
+    // `cube` doesn't need preloading, so name another library explicitly.
+    let preload = Preload::Library("auto_explain");
+    let state = facet.ensure_extension(None, "cube", None, preload)?;
+    assert_eq!(state, ExtensionState::Modified);
+    let mut client = facet.connection().client()?;
+    let row = client.query_one("SHOW shared_preload_libraries", &[])?;
+    assert_eq!(row.get::<_, String>(0), "auto_explain");
+    facet.destroy()?;
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use pgdo::cluster::{
+    roles::{self, Object, Password, Privilege, RoleOptions},
+    Cluster, ClusterError,
+};
+use pgdo::coordinate::State::*;
+use pgdo_test::for_all_runtimes;
+
+type TestResult = Result<(), ClusterError>;
+
+#[for_all_runtimes]
+#[test]
+fn roles_create_alter_and_drop() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(&data_dir, runtime)?;
+    cluster.start(&[])?;
+
+    let options = RoleOptions { login: Some(true), ..Default::default() };
+    assert_eq!(
+        roles::create(&cluster, "readers", &RoleOptions::default())?,
+        Modified
+    );
+    assert_eq!(roles::create(&cluster, "Bob Smith", &options)?, Modified);
+    assert_eq!(roles::create(&cluster, "Bob Smith", &options)?, Unmodified);
+
+    let bob = roles::get(&cluster, "Bob Smith")?.expect("role not found");
+    assert!(bob.login);
+    assert!(!bob.superuser);
+    assert!(bob.member_of.is_empty());
+    assert!(roles::list(&cluster)?.contains(&bob));
+
+    let options = RoleOptions {
+        login: Some(false),
+        create_db: Some(true),
+        password: Some(Password::Set("secret".into())),
+        member_of: vec!["readers".into()],
+        ..Default::default()
+    };
+    roles::alter(&cluster, "Bob Smith", &options)?;
+    let bob = roles::get(&cluster, "Bob Smith")?.expect("role not found");
+    assert!(!bob.login);
+    assert!(bob.create_db);
+    assert_eq!(bob.member_of, vec!["readers"]);
+
+    roles::revoke_membership(&cluster, "readers", "Bob Smith")?;
+    let bob = roles::get(&cluster, "Bob Smith")?.expect("role not found");
+    assert!(bob.member_of.is_empty());
+
+    assert_eq!(roles::drop(&cluster, "Bob Smith")?, Modified);
+    assert_eq!(roles::drop(&cluster, "Bob Smith")?, Unmodified);
+    assert_eq!(roles::get(&cluster, "Bob Smith")?, None);
+
+    cluster.destroy()?;
+    Ok(())
+}
+
+#[for_all_runtimes]
+#[test]
+fn roles_grant_and_revoke_privileges() -> TestResult {
+    let data_dir = tempfile::tempdir()?;
+    let cluster = Cluster::new(&data_dir, runtime)?;
+    cluster.start(&[])?;
+    cluster.createdb("example")?;
+    cluster
+        .connection()
+        .database("example")
+        .client()?
+        .batch_execute("CREATE TABLE things (id int)")?;
+
+    let options = RoleOptions { login: Some(true), ..Default::default() };
+    roles::create(&cluster, "alice", &options)?;
+    let alice = cluster.connection().database("example").role("alice");
+    let select = "SELECT * FROM things";
+
+    // Not permitted until granted.
+    assert!(alice.client()?.query(select, &[]).is_err());
+    roles::grant(
+        &cluster,
+        Some("example"),
+        &[Privilege::Select],
+        Object::Table("things"),
+        "alice",
+    )?;
+    assert!(alice.client()?.query(select, &[]).is_ok());
+
+    // Not permitted once revoked.
+    roles::revoke(
+        &cluster,
+        Some("example"),
+        &[Privilege::All],
+        Object::AllTablesInSchema("public"),
+        "alice",
+    )?;
+    assert!(alice.client()?.query(select, &[]).is_err());
+
+    cluster.destroy()?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use pgdo::cluster::Cluster;
+use pgdo::cluster::{Cluster, StopOptions};
 use pgdo::coordinate::CoordinateError;
 use pgdo::coordinate::{run_and_destroy, run_and_stop, run_and_stop_if_exists};
 use pgdo::{lock, runtime};
@@ -10,7 +10,9 @@ type TestResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
 #[test]
 fn run_and_stop_leaves_the_cluster_in_place() -> TestResult {
     let (setup, lock) = Setup::run(runtime)?;
-    let databases = run_and_stop(&setup.cluster, &[], lock, || setup.cluster.databases())??;
+    let databases = run_and_stop(&setup.cluster, &[], StopOptions::default(), lock, || {
+        setup.cluster.databases()
+    })??;
     assert!(!databases.is_empty());
     assert!(!setup.cluster.running()?);
     assert!(setup.datadir.exists());
@@ -22,7 +24,9 @@ fn run_and_stop_leaves_the_cluster_in_place() -> TestResult {
 fn run_and_stop_if_exists_leaves_the_cluster_in_place() -> TestResult {
     let (setup, lock) = Setup::run(runtime)?;
     setup.cluster.create()?;
-    let databases = run_and_stop(&setup.cluster, &[], lock, || setup.cluster.databases())??;
+    let databases = run_and_stop(&setup.cluster, &[], StopOptions::default(), lock, || {
+        setup.cluster.databases()
+    })??;
     assert!(!databases.is_empty());
     assert!(!setup.cluster.running()?);
     assert!(setup.datadir.exists());
@@ -34,7 +38,9 @@ fn run_and_stop_if_exists_leaves_the_cluster_in_place() -> TestResult {
 fn run_and_stop_if_exists_returns_error_if_cluster_does_not_exist() -> TestResult {
     let (setup, lock) = Setup::run(runtime)?;
     assert!(matches!(
-        run_and_stop_if_exists(&setup.cluster, &[], lock, || setup.cluster.databases()),
+        run_and_stop_if_exists(&setup.cluster, &[], StopOptions::default(), lock, || setup
+            .cluster
+            .databases()),
         Err(CoordinateError::DoesNotExist)
     ));
     Ok(())